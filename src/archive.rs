@@ -0,0 +1,919 @@
+use std::{
+    borrow::Cow,
+    io::{Error, ErrorKind, Result, SeekFrom},
+    path::{Path, PathBuf},
+    pin::Pin,
+    task::{Context, Poll, ready},
+    time::SystemTime,
+};
+
+use futures_lite::{
+    AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWriteExt, Stream, StreamExt, io::Cursor, stream,
+};
+use js_sys::{Array, Uint8Array};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Blob, ReadableWritablePair, Response};
+
+use crate::{
+    File, FileType, TEMP_DIR, create_dir_all, metadata, read_dir, tempfile,
+    util::{js_value_to_error, normalize},
+};
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZIP_LOCAL_HEADER: u32 = 0x0403_4b50;
+const ZIP_CENTRAL_HEADER: u32 = 0x0201_4b50;
+const ZIP_END: u32 = 0x0605_4b50;
+const TAR_BLOCK: usize = 512;
+/// The largest GNU long name or pax header read when importing, as they are kept in memory.
+const TAR_RECORD_MAX: u64 = 4 << 20;
+/// The end of central directory record and the longest comment that can follow it.
+const ZIP_TAIL: usize = 22 + u16::MAX as usize;
+
+/// A file or directory in an archive, with a normalized relative path.
+pub(crate) struct ArchiveEntry<'a> {
+    pub(crate) path: PathBuf,
+    /// The content of a file, `None` for a directory.
+    pub(crate) data: Option<Cow<'a, [u8]>>,
+}
+
+impl ArchiveEntry<'_> {
+    fn into_owned(self) -> ArchiveEntry<'static> {
+        ArchiveEntry {
+            path: self.path,
+            data: self.data.map(|data| Cow::Owned(data.into_owned())),
+        }
+    }
+}
+
+#[wasm_bindgen]
+extern "C" {
+    /// `DecompressionStream` is only exposed by web-sys with `web_sys_unstable_apis`.
+    #[wasm_bindgen(extends = ReadableWritablePair)]
+    type DecompressionStream;
+    #[wasm_bindgen(constructor, catch)]
+    fn new(format: &str) -> std::result::Result<DecompressionStream, JsValue>;
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// Continues a CRC-32 over `data`, starting from `!0` and finishing by inverting the bits.
+fn crc32_update(crc: u32, data: &[u8]) -> u32 {
+    data.iter().fold(crc, |crc, byte| {
+        CRC32_TABLE[((crc ^ *byte as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+/// The CRC-32 used by zip and gzip.
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    !crc32_update(!0, data)
+}
+
+/// Decompresses with the `DecompressionStream` of the browser, `format` is `"gzip"` or `"deflate-raw"`.
+async fn decompress(data: &[u8], format: &str) -> Result<Vec<u8>> {
+    if cfg!(not(target_arch = "wasm32")) {
+        return Err(Error::new(
+            ErrorKind::Unsupported,
+            "compressed archives are only supported in the browser",
+        ));
+    }
+    let stream = DecompressionStream::new(format).map_err(js_value_to_error)?;
+    let parts = Array::of1(&Uint8Array::from(data));
+    let blob = Blob::new_with_u8_array_sequence(&parts).map_err(js_value_to_error)?;
+    let response =
+        Response::new_with_opt_readable_stream(Some(&blob.stream().pipe_through(&stream)))
+            .map_err(js_value_to_error)?;
+    let buf = JsFuture::from(response.array_buffer().map_err(js_value_to_error)?)
+        .await
+        .map_err(js_value_to_error)?;
+    Ok(Uint8Array::new(&buf).to_vec())
+}
+
+/// Reads every entry of a tar, a gzipped tar or a zip archive, telling them apart by their magic numbers.
+///
+/// Entries that are neither files nor directories, like links, are skipped.
+/// Compressed archives and entries can only be read in the browser.
+pub(crate) async fn read_archive(archive: &[u8]) -> Result<Vec<ArchiveEntry<'_>>> {
+    if archive.starts_with(GZIP_MAGIC) {
+        let tar = decompress(archive, "gzip").await?;
+        Ok(read_tar(&tar)?
+            .into_iter()
+            .map(ArchiveEntry::into_owned)
+            .collect())
+    } else if archive.starts_with(&ZIP_LOCAL_HEADER.to_le_bytes())
+        || archive.starts_with(&ZIP_END.to_le_bytes())
+    {
+        read_zip(archive).await
+    } else {
+        read_tar(archive)
+    }
+}
+
+/// Parses a NUL terminated field of a tar header.
+fn tar_str(field: &[u8]) -> Cow<'_, str> {
+    let end = field
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end])
+}
+
+/// Parses a numeric field of a tar header, in octal or in the base-256 extension of GNU tar.
+fn tar_number(field: &[u8]) -> Result<u64> {
+    if field[0] & 0x80 != 0 {
+        return field[1..]
+            .iter()
+            .try_fold(0u64, |number, byte| {
+                Some(number.checked_mul(256)? | *byte as u64)
+            })
+            .ok_or(invalid("number too large in a tar header"));
+    }
+    let text = tar_str(field);
+    let text = text.trim_matches(|c: char| c == ' ' || c == '\0');
+    if text.is_empty() {
+        return Ok(0);
+    }
+    u64::from_str_radix(text, 8).map_err(|_| invalid("invalid number in a tar header"))
+}
+
+/// Finds the `path` record of a pax extended header.
+fn pax_path(data: &[u8]) -> Option<String> {
+    let mut rest = data;
+    while !rest.is_empty() {
+        let space = rest.iter().position(|byte| *byte == b' ')?;
+        let len: usize = std::str::from_utf8(&rest[..space]).ok()?.parse().ok()?;
+        let record = rest.get(space + 1..len)?;
+        if let Some(path) = record.strip_prefix(b"path=") {
+            let path = path.strip_suffix(b"\n").unwrap_or(path);
+            return Some(String::from_utf8_lossy(path).into_owned());
+        }
+        rest = &rest[len..];
+    }
+    None
+}
+
+/// The name of a tar entry, joining the ustar prefix if there is one.
+fn tar_name(header: &[u8]) -> String {
+    if &header[257..262] == b"ustar" && header[345] != 0 {
+        format!("{}/{}", tar_str(&header[345..500]), tar_str(&header[..100]))
+    } else {
+        tar_str(&header[..100]).into_owned()
+    }
+}
+
+fn read_tar(archive: &[u8]) -> Result<Vec<ArchiveEntry<'_>>> {
+    let mut entries = Vec::new();
+    let mut offset = 0;
+    // Set by GNU long name and pax headers for the entry that follows.
+    let mut long_name = None;
+    while offset + TAR_BLOCK <= archive.len() {
+        let header = &archive[offset..offset + TAR_BLOCK];
+        if header.iter().all(|byte| *byte == 0) {
+            break;
+        }
+        let size = usize::try_from(tar_number(&header[124..136])?)
+            .map_err(|_| invalid("truncated tar archive"))?;
+        let data_start = offset + TAR_BLOCK;
+        let data = data_start
+            .checked_add(size)
+            .and_then(|data_end| archive.get(data_start..data_end))
+            .ok_or(invalid("truncated tar archive"))?;
+        // The data fits in the archive, so only the padding can overflow.
+        offset = (data_start + size)
+            .checked_next_multiple_of(TAR_BLOCK)
+            .ok_or(invalid("truncated tar archive"))?;
+
+        let name = long_name.take().unwrap_or_else(|| tar_name(header));
+        match header[156] {
+            b'0' | b'\0' | b'7' => entries.push(ArchiveEntry {
+                path: normalize(&name)?,
+                data: Some(Cow::Borrowed(data)),
+            }),
+            b'5' => entries.push(ArchiveEntry {
+                path: normalize(&name)?,
+                data: None,
+            }),
+            b'L' => long_name = Some(tar_str(data).into_owned()),
+            b'x' => long_name = pax_path(data),
+            _ => (),
+        }
+    }
+    Ok(entries)
+}
+
+fn u16_at(data: &[u8], offset: usize) -> Result<u16> {
+    data.get(offset..offset + 2)
+        .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+        .ok_or(invalid("truncated zip archive"))
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Result<u32> {
+    data.get(offset..offset + 4)
+        .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .ok_or(invalid("truncated zip archive"))
+}
+
+/// Where the central directory of a zip archive is, found in the end of the archive.
+struct ZipEnd {
+    count: u16,
+    size: u32,
+    offset: u32,
+}
+
+/// Finds the end of central directory record in `tail`,
+/// which must include the last 64 KiB and 22 bytes of the archive to cover the longest comment.
+fn zip_end(tail: &[u8]) -> Result<ZipEnd> {
+    let end = (0..tail.len().saturating_sub(21))
+        .rev()
+        .find(|offset| u32_at(tail, *offset).is_ok_and(|signature| signature == ZIP_END))
+        .ok_or(invalid("missing the end of the zip central directory"))?;
+    let end = ZipEnd {
+        count: u16_at(tail, end + 10)?,
+        size: u32_at(tail, end + 12)?,
+        offset: u32_at(tail, end + 16)?,
+    };
+    if end.count == u16::MAX || end.offset == u32::MAX {
+        return Err(Error::new(ErrorKind::Unsupported, "zip64 is not supported"));
+    }
+    Ok(end)
+}
+
+/// An entry of the central directory of a zip archive.
+struct ZipEntry {
+    name: String,
+    method: u16,
+    crc: u32,
+    compressed_size: u32,
+    /// Offset of the local header.
+    local: u32,
+}
+
+impl ZipEntry {
+    fn is_dir(&self) -> bool {
+        self.name.ends_with('/')
+    }
+    /// Fails unless the data can be read with [`Self::decode`].
+    fn check_supported(&self, flags: u16) -> Result<()> {
+        if flags & 1 != 0 {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                "encrypted zip entries are not supported",
+            ));
+        }
+        if self.method != 0 && self.method != 8 {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                format!("zip compression method {} is not supported", self.method),
+            ));
+        }
+        Ok(())
+    }
+    /// Decompresses the data if needed and verifies the checksum.
+    async fn decode<'a>(&self, compressed: Cow<'a, [u8]>) -> Result<Cow<'a, [u8]>> {
+        let data = match self.method {
+            8 => Cow::Owned(decompress(&compressed, "deflate-raw").await?),
+            _ => compressed,
+        };
+        if crc32(&data) != self.crc {
+            return Err(invalid("zip entry checksum mismatch"));
+        }
+        Ok(data)
+    }
+}
+
+/// Parses `count` entries of a central directory.
+fn zip_entries(directory: &[u8], count: u16) -> Result<Vec<ZipEntry>> {
+    let mut entries = Vec::new();
+    let mut header = 0;
+    for _ in 0..count {
+        if u32_at(directory, header)? != ZIP_CENTRAL_HEADER {
+            return Err(invalid("invalid zip central directory"));
+        }
+        let name_len = u16_at(directory, header + 28)? as usize;
+        let name = directory
+            .get(header + 46..header + 46 + name_len)
+            .ok_or(invalid("truncated zip archive"))?;
+        let entry = ZipEntry {
+            name: String::from_utf8_lossy(name).into_owned(),
+            method: u16_at(directory, header + 10)?,
+            crc: u32_at(directory, header + 16)?,
+            compressed_size: u32_at(directory, header + 20)?,
+            local: u32_at(directory, header + 42)?,
+        };
+        if !entry.is_dir() {
+            entry.check_supported(u16_at(directory, header + 8)?)?;
+        }
+        entries.push(entry);
+        header += 46
+            + name_len
+            + u16_at(directory, header + 30)? as usize
+            + u16_at(directory, header + 32)? as usize;
+    }
+    Ok(entries)
+}
+
+/// The offset of the data after a local header of the given size.
+fn zip_data_start(local: usize, header: &[u8]) -> Result<usize> {
+    if u32_at(header, 0)? != ZIP_LOCAL_HEADER {
+        return Err(invalid("invalid zip local header"));
+    }
+    Ok(local + 30 + u16_at(header, 26)? as usize + u16_at(header, 28)? as usize)
+}
+
+async fn read_zip(archive: &[u8]) -> Result<Vec<ArchiveEntry<'_>>> {
+    let end = zip_end(&archive[archive.len().saturating_sub(ZIP_TAIL)..])?;
+    let directory = archive
+        .get(end.offset as usize..)
+        .ok_or(invalid("truncated zip archive"))?;
+    let mut entries = Vec::new();
+    for entry in zip_entries(directory, end.count)? {
+        if entry.is_dir() {
+            entries.push(ArchiveEntry {
+                path: normalize(&entry.name)?,
+                data: None,
+            });
+            continue;
+        }
+        let local = entry.local as usize;
+        let data_start = zip_data_start(local, archive.get(local..).unwrap_or_default())?;
+        let compressed = archive
+            .get(data_start..data_start + entry.compressed_size as usize)
+            .ok_or(invalid("truncated zip archive"))?;
+        entries.push(ArchiveEntry {
+            path: normalize(&entry.name)?,
+            data: Some(entry.decode(Cow::Borrowed(compressed)).await?),
+        });
+    }
+    Ok(entries)
+}
+
+/// Formats of [`export_dir`], [`import_archive`] detects the format itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    /// POSIX ustar, with GNU long names for paths longer than 100 bytes.
+    Tar,
+    /// Zip with stored, uncompressed entries.
+    Zip,
+}
+
+/// Size of the chunks files are read and written in when exporting and importing.
+const ARCHIVE_CHUNK: usize = 1 << 16;
+const ZIP_DATA_DESCRIPTOR: u32 = 0x0807_4b50;
+/// The data descriptor follows the data, and names are UTF-8.
+const ZIP_FILE_FLAGS: u16 = 0x0808;
+const ZIP_DIR_FLAGS: u16 = 0x0800;
+
+fn too_large_for_zip() -> Error {
+    Error::new(
+        ErrorKind::Unsupported,
+        "zip64 is not supported, the archive would be larger than 4 GiB or have more than 65535 entries",
+    )
+}
+
+/// Fills a numeric field of a tar header, in octal or in base-256 if it doesn't fit.
+fn put_tar_number(field: &mut [u8], number: u64) {
+    let digits = field.len() - 1;
+    if number < 1 << (3 * digits) {
+        field.copy_from_slice(format!("{number:0digits$o}\0").as_bytes());
+    } else {
+        field.fill(0);
+        field[0] = 0x80;
+        let bytes = number.to_be_bytes();
+        let len = field.len();
+        field[len - 8..].copy_from_slice(&bytes);
+    }
+}
+
+fn tar_header(name: &str, size: u64, modified: u64, kind: u8) -> [u8; TAR_BLOCK] {
+    let mut header = [0; TAR_BLOCK];
+    let name = name.as_bytes();
+    let len = name.len().min(100);
+    header[..len].copy_from_slice(&name[..len]);
+    let mode: &[u8] = if kind == b'5' {
+        b"0000755\0"
+    } else {
+        b"0000644\0"
+    };
+    header[100..108].copy_from_slice(mode);
+    put_tar_number(&mut header[108..116], 0);
+    put_tar_number(&mut header[116..124], 0);
+    put_tar_number(&mut header[124..136], size);
+    put_tar_number(&mut header[136..148], modified);
+    header[156] = kind;
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+    // The checksum is computed with its own field filled with spaces.
+    header[148..156].fill(b' ');
+    let checksum: u32 = header.iter().map(|byte| *byte as u32).sum();
+    header[148..156].copy_from_slice(format!("{checksum:06o}\0 ").as_bytes());
+    header
+}
+
+/// The headers of a tar entry, preceded by a GNU long name entry if the name doesn't fit.
+fn tar_headers(name: &str, size: u64, modified: u64, kind: u8) -> Vec<u8> {
+    let mut headers = Vec::new();
+    if name.len() > 100 {
+        let long_name = format!("{name}\0");
+        headers.extend_from_slice(&tar_header(
+            "././@LongLink",
+            long_name.len() as u64,
+            0,
+            b'L',
+        ));
+        headers.extend_from_slice(long_name.as_bytes());
+        headers.resize(headers.len().next_multiple_of(TAR_BLOCK), 0);
+    }
+    headers.extend_from_slice(&tar_header(name, size, modified, kind));
+    headers
+}
+
+/// Converts to the date and time of MS-DOS used by zip, in UTC.
+fn dos_date_time(time: Option<SystemTime>) -> (u16, u16) {
+    let secs = time
+        .and_then(|time| time.duration_since(SystemTime::UNIX_EPOCH).ok())
+        .map_or(0, |duration| duration.as_secs());
+    let (days, secs_of_day) = ((secs / 86400) as i64, secs % 86400);
+    // From days since 1970-01-01 to the civil date, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    if year < 1980 {
+        return ((1 << 5) | 1, 0);
+    }
+    let date = (((year - 1980).min(127) << 9) | (month << 5) | day) as u16;
+    let time = (((secs_of_day / 3600) << 11)
+        | ((secs_of_day % 3600 / 60) << 5)
+        | (secs_of_day % 60 / 2)) as u16;
+    (date, time)
+}
+
+/// A file being exported.
+struct ExportedFile {
+    file: File,
+    name: String,
+    /// Bytes left to read, files are cut to the size they had when opened.
+    remaining: u64,
+    size: u64,
+    crc: u32,
+    local: u32,
+    date_time: (u16, u16),
+}
+
+/// Walks a directory tree producing an archive chunk by chunk.
+struct Exporter {
+    root: PathBuf,
+    format: ArchiveFormat,
+    /// Relative paths waiting to be exported, popped from the end for a depth-first order.
+    pending: Vec<(PathBuf, FileType)>,
+    current: Option<ExportedFile>,
+    /// Bytes produced so far.
+    offset: u64,
+    /// The zip central directory.
+    directory: Vec<u8>,
+    entries: u64,
+    finished: bool,
+}
+
+impl Exporter {
+    fn name(path: &Path) -> String {
+        path.iter()
+            .map(|name| name.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+    fn zip_offset(&self) -> Result<u32> {
+        u32::try_from(self.offset).map_err(|_| too_large_for_zip())
+    }
+    /// Records an entry in the zip central directory.
+    fn push_central(
+        &mut self,
+        name: &str,
+        flags: u16,
+        date_time: (u16, u16),
+        crc: u32,
+        size: u32,
+        local: u32,
+    ) -> Result<()> {
+        self.entries += 1;
+        if self.entries > u16::MAX as u64 {
+            return Err(too_large_for_zip());
+        }
+        let directory = &mut self.directory;
+        directory.extend_from_slice(&ZIP_CENTRAL_HEADER.to_le_bytes());
+        directory.extend_from_slice(&20u16.to_le_bytes());
+        directory.extend_from_slice(&20u16.to_le_bytes());
+        directory.extend_from_slice(&flags.to_le_bytes());
+        directory.extend_from_slice(&0u16.to_le_bytes());
+        directory.extend_from_slice(&date_time.1.to_le_bytes());
+        directory.extend_from_slice(&date_time.0.to_le_bytes());
+        directory.extend_from_slice(&crc.to_le_bytes());
+        directory.extend_from_slice(&size.to_le_bytes());
+        directory.extend_from_slice(&size.to_le_bytes());
+        directory.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        // Extra field, comment, disk and internal attributes.
+        directory.extend_from_slice(&[0; 8]);
+        // The attribute of MS-DOS directories.
+        let attributes: u32 = if name.ends_with('/') { 0x10 } else { 0 };
+        directory.extend_from_slice(&attributes.to_le_bytes());
+        directory.extend_from_slice(&local.to_le_bytes());
+        directory.extend_from_slice(name.as_bytes());
+        Ok(())
+    }
+    fn zip_local_header(name: &str, flags: u16, date_time: (u16, u16)) -> Vec<u8> {
+        let mut header = Vec::with_capacity(30 + name.len());
+        header.extend_from_slice(&ZIP_LOCAL_HEADER.to_le_bytes());
+        header.extend_from_slice(&20u16.to_le_bytes());
+        header.extend_from_slice(&flags.to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes());
+        header.extend_from_slice(&date_time.1.to_le_bytes());
+        header.extend_from_slice(&date_time.0.to_le_bytes());
+        // The checksum and sizes are in the data descriptor.
+        header.extend_from_slice(&[0; 12]);
+        header.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes());
+        header.extend_from_slice(name.as_bytes());
+        header
+    }
+    async fn start_dir(&mut self, path: PathBuf) -> Result<Vec<u8>> {
+        let mut children = Vec::new();
+        // Normalized so that roots like "/" still find the temp dir.
+        let dir = normalize(self.root.join(&path))?;
+        let mut entries = read_dir(&dir).await?;
+        while let Some(entry) = entries.next().await {
+            let entry = entry?;
+            // Temp files of this and other sessions are not user data.
+            if dir.join(entry.file_name()) == Path::new(TEMP_DIR) {
+                continue;
+            }
+            let child = path.join(entry.file_name());
+            children.push((child, entry.file_type().await?));
+        }
+        children.sort_by(|a, b| b.0.cmp(&a.0));
+        self.pending.extend(children);
+        if path.as_os_str().is_empty() {
+            return Ok(Vec::new());
+        }
+        let name = format!("{}/", Self::name(&path));
+        Ok(match self.format {
+            ArchiveFormat::Tar => tar_headers(&name, 0, 0, b'5'),
+            ArchiveFormat::Zip => {
+                let local = self.zip_offset()?;
+                let date_time = dos_date_time(None);
+                self.push_central(&name, ZIP_DIR_FLAGS, date_time, 0, 0, local)?;
+                Self::zip_local_header(&name, ZIP_DIR_FLAGS, date_time)
+            }
+        })
+    }
+    async fn start_file(&mut self, path: PathBuf) -> Result<Vec<u8>> {
+        let full_path = self.root.join(&path);
+        let modified = metadata(&full_path).await?.modified().ok();
+        let file = File::open(&full_path).await?;
+        let size = file.size;
+        let name = Self::name(&path);
+        let date_time = dos_date_time(modified);
+        let mut local = 0;
+        let header = match self.format {
+            ArchiveFormat::Tar => {
+                let modified = modified
+                    .and_then(|time| time.duration_since(SystemTime::UNIX_EPOCH).ok())
+                    .map_or(0, |duration| duration.as_secs());
+                tar_headers(&name, size, modified, b'0')
+            }
+            ArchiveFormat::Zip => {
+                local = self.zip_offset()?;
+                Self::zip_local_header(&name, ZIP_FILE_FLAGS, date_time)
+            }
+        };
+        self.current = Some(ExportedFile {
+            file,
+            name,
+            remaining: size,
+            size,
+            crc: !0,
+            local,
+            date_time,
+        });
+        Ok(header)
+    }
+    async fn continue_file(&mut self) -> Result<Vec<u8>> {
+        let current = self.current.as_mut().unwrap();
+        let mut buf = vec![0; (current.remaining as usize).min(ARCHIVE_CHUNK)];
+        let read = if buf.is_empty() {
+            0
+        } else {
+            current.file.read(&mut buf).await?
+        };
+        buf.truncate(read);
+        current.crc = crc32_update(current.crc, &buf);
+        current.remaining -= read as u64;
+        if read > 0 && current.remaining > 0 {
+            return Ok(buf);
+        }
+        // Done, the file may have shrunk since it was opened.
+        let current = self.current.take().unwrap();
+        match self.format {
+            ArchiveFormat::Tar => {
+                // Zeros stand in for what was cut, then the data is padded to a whole block.
+                let padding = current.size.next_multiple_of(TAR_BLOCK as u64) - current.size;
+                buf.resize(buf.len() + (current.remaining + padding) as usize, 0);
+            }
+            ArchiveFormat::Zip => {
+                let crc = !current.crc;
+                let size = u32::try_from(current.size - current.remaining)
+                    .map_err(|_| too_large_for_zip())?;
+                buf.extend_from_slice(&ZIP_DATA_DESCRIPTOR.to_le_bytes());
+                buf.extend_from_slice(&crc.to_le_bytes());
+                buf.extend_from_slice(&size.to_le_bytes());
+                buf.extend_from_slice(&size.to_le_bytes());
+                self.push_central(
+                    &current.name,
+                    ZIP_FILE_FLAGS,
+                    current.date_time,
+                    crc,
+                    size,
+                    current.local,
+                )?;
+            }
+        }
+        Ok(buf)
+    }
+    fn finish(&mut self) -> Result<Vec<u8>> {
+        self.finished = true;
+        match self.format {
+            ArchiveFormat::Tar => Ok(vec![0; TAR_BLOCK * 2]),
+            ArchiveFormat::Zip => {
+                let offset = self.zip_offset()?;
+                let mut end = std::mem::take(&mut self.directory);
+                let size = end.len() as u32;
+                end.extend_from_slice(&ZIP_END.to_le_bytes());
+                end.extend_from_slice(&[0; 4]);
+                end.extend_from_slice(&(self.entries as u16).to_le_bytes());
+                end.extend_from_slice(&(self.entries as u16).to_le_bytes());
+                end.extend_from_slice(&size.to_le_bytes());
+                end.extend_from_slice(&offset.to_le_bytes());
+                end.extend_from_slice(&0u16.to_le_bytes());
+                Ok(end)
+            }
+        }
+    }
+    /// The next chunk of the archive, `None` at the end.
+    async fn next_chunk(&mut self) -> Result<Option<Vec<u8>>> {
+        let chunk = if self.current.is_some() {
+            self.continue_file().await?
+        } else if let Some((path, file_type)) = self.pending.pop() {
+            match file_type {
+                FileType::Dir => self.start_dir(path).await?,
+                FileType::File => self.start_file(path).await?,
+            }
+        } else if !self.finished {
+            self.finish()?
+        } else {
+            return Ok(None);
+        };
+        self.offset += chunk.len() as u64;
+        Ok(Some(chunk))
+    }
+}
+
+/// An archive of a directory tree produced while it is read, returned by [`export_dir`].
+pub struct ArchiveReader {
+    chunks: Pin<Box<dyn Stream<Item = Result<Vec<u8>>>>>,
+    chunk: Vec<u8>,
+    position: usize,
+}
+
+impl AsyncRead for ArchiveReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize>> {
+        loop {
+            if self.position < self.chunk.len() {
+                let len = buf.len().min(self.chunk.len() - self.position);
+                buf[..len].copy_from_slice(&self.chunk[self.position..self.position + len]);
+                self.position += len;
+                return Poll::Ready(Ok(len));
+            }
+            match ready!(self.chunks.as_mut().poll_next(cx)) {
+                Some(Ok(chunk)) => {
+                    self.chunk = chunk;
+                    self.position = 0;
+                }
+                Some(Err(e)) => return Poll::Ready(Err(e)),
+                None => return Poll::Ready(Ok(0)),
+            }
+        }
+    }
+}
+
+/// Archives the directory tree at `path`, for example to back it up as one download.
+///
+/// The archive is produced while it is read, one file chunk at a time,
+/// so the tree is never loaded into memory as a whole.
+/// Each file is cut to the size it had when the archive reached it.
+/// Errors, like `path` not being a directory, are returned when reading.
+/// Entries are in alphabetical order, and [`TEMP_DIR`] is skipped.
+pub fn export_dir<P: AsRef<Path>>(path: P, format: ArchiveFormat) -> ArchiveReader {
+    let exporter = Exporter {
+        root: path.as_ref().to_path_buf(),
+        format,
+        pending: vec![(PathBuf::new(), FileType::Dir)],
+        current: None,
+        offset: 0,
+        directory: Vec::new(),
+        entries: 0,
+        finished: false,
+    };
+    let chunks = stream::unfold(Some(exporter), |exporter| async move {
+        let mut exporter = exporter?;
+        match exporter.next_chunk().await {
+            Ok(Some(chunk)) => Some((Ok(chunk), Some(exporter))),
+            Ok(None) => None,
+            // Nothing more after an error.
+            Err(e) => Some((Err(e), None)),
+        }
+    });
+    ArchiveReader {
+        chunks: Box::pin(chunks),
+        chunk: Vec::new(),
+        position: 0,
+    }
+}
+
+/// Copies `len` bytes from `reader` to a new file at `path`, returning their CRC-32.
+async fn import_file<R: AsyncRead + Unpin>(reader: &mut R, path: &Path, len: u64) -> Result<u32> {
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent).await?;
+    }
+    let mut file = File::create(path).await?;
+    let mut buf = vec![0; (len as usize).min(ARCHIVE_CHUNK)];
+    let mut remaining = len;
+    let mut crc = !0;
+    while remaining > 0 {
+        let chunk = &mut buf[..(remaining as usize).min(ARCHIVE_CHUNK)];
+        reader.read_exact(chunk).await?;
+        crc = crc32_update(crc, chunk);
+        file.write_all(chunk).await?;
+        remaining -= chunk.len() as u64;
+    }
+    file.close().await?;
+    Ok(!crc)
+}
+
+async fn skip<R: AsyncRead + Unpin>(reader: &mut R, len: u64) -> Result<()> {
+    let skipped = futures_lite::io::copy(reader.take(len), futures_lite::io::sink()).await?;
+    if skipped < len {
+        return Err(Error::from(ErrorKind::UnexpectedEof));
+    }
+    Ok(())
+}
+
+async fn import_tar<R: AsyncRead + Unpin>(mut reader: R, dest: &Path) -> Result<()> {
+    let mut header = [0; TAR_BLOCK];
+    let mut long_name = None;
+    loop {
+        // Archives can end without the two empty blocks.
+        let mut filled = 0;
+        while filled < TAR_BLOCK {
+            match reader.read(&mut header[filled..]).await? {
+                0 if filled == 0 => return Ok(()),
+                0 => return Err(invalid("truncated tar archive")),
+                read => filled += read,
+            }
+        }
+        if header.iter().all(|byte| *byte == 0) {
+            return Ok(());
+        }
+        let size = tar_number(&header[124..136])?;
+        let padding = size
+            .checked_next_multiple_of(TAR_BLOCK as u64)
+            .ok_or(invalid("invalid size in a tar header"))?
+            - size;
+        let name = long_name.take().unwrap_or_else(|| tar_name(&header));
+        match header[156] {
+            b'0' | b'\0' | b'7' => {
+                import_file(&mut reader, &dest.join(normalize(&name)?), size).await?;
+            }
+            b'5' => {
+                create_dir_all(dest.join(normalize(&name)?)).await?;
+                skip(&mut reader, size).await?;
+            }
+            kind @ (b'L' | b'x') => {
+                if size > TAR_RECORD_MAX {
+                    return Err(invalid("tar long name or pax header too large"));
+                }
+                let mut data = vec![0; size as usize];
+                reader.read_exact(&mut data).await?;
+                long_name = if kind == b'L' {
+                    Some(tar_str(&data).into_owned())
+                } else {
+                    pax_path(&data)
+                };
+            }
+            _ => skip(&mut reader, size).await?,
+        }
+        skip(&mut reader, padding).await?;
+    }
+}
+
+async fn import_zip<R: AsyncRead + Unpin>(reader: R, dest: &Path) -> Result<()> {
+    // The central directory is at the end, so the archive is spooled to a temp file first.
+    let mut spool = tempfile().await?;
+    let len = futures_lite::io::copy(reader, &mut *spool).await?;
+    spool.flush().await?;
+
+    let tail_start = len.saturating_sub(ZIP_TAIL as u64);
+    let mut tail = vec![0; (len - tail_start) as usize];
+    spool.seek(SeekFrom::Start(tail_start)).await?;
+    spool.read_exact(&mut tail).await?;
+    let end = zip_end(&tail)?;
+    let mut directory = vec![0; end.size as usize];
+    spool.seek(SeekFrom::Start(end.offset as u64)).await?;
+    spool.read_exact(&mut directory).await?;
+
+    for entry in zip_entries(&directory, end.count)? {
+        let path = dest.join(normalize(&entry.name)?);
+        if entry.is_dir() {
+            create_dir_all(&path).await?;
+            continue;
+        }
+        let mut header = [0; 30];
+        spool.seek(SeekFrom::Start(entry.local as u64)).await?;
+        spool.read_exact(&mut header).await?;
+        let data_start = zip_data_start(entry.local as usize, &header)?;
+        spool.seek(SeekFrom::Start(data_start as u64)).await?;
+        if entry.method == 0 {
+            let crc = import_file(&mut *spool, &path, entry.compressed_size as u64).await?;
+            if crc != entry.crc {
+                return Err(invalid("zip entry checksum mismatch"));
+            }
+        } else {
+            // `DecompressionStream` is fed the whole entry.
+            let mut compressed = vec![0; entry.compressed_size as usize];
+            spool.read_exact(&mut compressed).await?;
+            let data = entry.decode(Cow::Owned(compressed)).await?;
+            import_file(&mut &data[..], &path, data.len() as u64).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Extracts a tar or zip archive read from `reader` into `dest`, creating `dest` and its parents if missing.
+///
+/// Existing files are overwritten.
+/// Tar archives are extracted while they are read.
+/// Zip archives are first copied to a temp file, because their index is at the end,
+/// then their stored entries are extracted chunk by chunk, and deflated entries one at a time in memory.
+/// Deflated entries can only be extracted in the browser.
+pub async fn import_archive<R: AsyncRead + Unpin, P: AsRef<Path>>(
+    mut reader: R,
+    dest: P,
+) -> Result<()> {
+    let dest = dest.as_ref();
+    create_dir_all(dest).await?;
+    // Peeked to tell the format.
+    let mut magic = Vec::new();
+    (&mut reader).take(4).read_to_end(&mut magic).await?;
+    let reader = Cursor::new(magic.clone()).chain(reader);
+    if magic.starts_with(GZIP_MAGIC) {
+        return Err(Error::new(
+            ErrorKind::Unsupported,
+            "gzipped archives can't be imported, decompress them first",
+        ));
+    }
+    if magic == ZIP_LOCAL_HEADER.to_le_bytes() || magic == ZIP_END.to_le_bytes() {
+        import_zip(reader, dest).await
+    } else {
+        import_tar(reader, dest).await
+    }
+}
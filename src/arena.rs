@@ -33,4 +33,7 @@ impl<T> Arena<T> {
         }
         self.inner[i].take()
     }
+    pub fn get(&self, i: usize) -> Option<&T> {
+        self.inner.get(i)?.as_ref()
+    }
 }
@@ -0,0 +1,192 @@
+use std::{
+    cell::RefCell,
+    collections::HashSet,
+    io::{Error, ErrorKind, Result},
+    path::{Path, PathBuf},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_lite::{AsyncWrite, AsyncWriteExt, StreamExt};
+
+use crate::{
+    File, TEMP_DIR,
+    locks::lock_manager,
+    read_dir, remove_file, remove_stale_temp_files, rename,
+    temp::{is_session_over, session},
+    util::{spawn_local, unique_suffix},
+};
+
+const TEMP_SUFFIX: &str = ".web-fs-atomic";
+
+thread_local! {
+    /// Directories that were already swept for stale temp files in this session.
+    static SWEPT: RefCell<HashSet<PathBuf>> = RefCell::new(HashSet::new());
+}
+
+/// Returns the session that wrote the temp file `name`, named `.<name>.<session>.<suffix>.web-fs-atomic`.
+fn atomic_temp_session(name: &str) -> Option<&str> {
+    let rest = name.strip_prefix('.')?.strip_suffix(TEMP_SUFFIX)?;
+    let (rest, _suffix) = rest.rsplit_once('.')?;
+    let (_name, session) = rest.rsplit_once('.')?;
+    Some(session)
+}
+
+/// Removes temp files left in `dir` by [`AtomicFile`]s that were never committed,
+/// for example because the tab crashed.
+///
+/// Temp files of tabs and workers that are still alive are kept,
+/// which is told by the web lock of their session as with [`remove_stale_temp_files`].
+/// So this does nothing if the browser doesn't support the *Web Locks API*, as on native targets.
+/// This runs automatically for a directory the first time an [`AtomicFile`] is created in it,
+/// and for every directory with [`remove_stale_files`].
+pub async fn remove_stale_atomic_files<P: AsRef<Path>>(dir: P) -> Result<()> {
+    sweep(dir.as_ref(), false).await
+}
+
+/// Removes what tabs and workers that are gone left behind:
+/// the temp files of uncommitted [`AtomicFile`]s in every directory,
+/// and the entries in [`TEMP_DIR`] removed by [`remove_stale_temp_files`].
+///
+/// It lists the whole tree, so it doesn't run automatically,
+/// call it once at startup, before reading the state saved by a previous session.
+pub async fn remove_stale_files() -> Result<()> {
+    remove_stale_temp_files().await?;
+    sweep(Path::new(""), true).await
+}
+
+async fn sweep(dir: &Path, recursive: bool) -> Result<()> {
+    let Some(manager) = lock_manager() else {
+        return Ok(());
+    };
+    let current = session();
+    let mut stale = Vec::new();
+    let mut children = Vec::new();
+    let mut entries = read_dir(dir).await?;
+    while let Some(entry) = entries.next().await {
+        let entry = entry?;
+        let file_type = entry.file_type().await?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if file_type.is_file() {
+            if let Some(session) = atomic_temp_session(&name)
+                && session != current
+            {
+                stale.push((entry.path(), session.to_string()));
+            }
+        } else if file_type.is_dir() && recursive && entry.path() != Path::new(TEMP_DIR) {
+            children.push(entry.path());
+        }
+    }
+    SWEPT.with_borrow_mut(|swept| swept.insert(dir.to_path_buf()));
+    for (path, session) in stale {
+        if is_session_over(&manager, &session).await {
+            // Failing here means the file is in use, try again next time.
+            let _ = remove_file(path).await;
+        }
+    }
+    for child in children {
+        Box::pin(sweep(&child, true)).await?;
+    }
+    Ok(())
+}
+
+/// A file writer whose content replaces the file at its path only when [`commit`](Self::commit) succeeds.
+///
+/// The content is written to a hidden temp sibling which is then moved over the target,
+/// so after a crash readers see either the old or the new content, never a torn file.
+/// Dropping without committing discards the written content.
+///
+/// Closing it via [`AsyncWriteExt::close`] only flushes, use [`commit`](Self::commit) to finish.
+pub struct AtomicFile {
+    file: Option<File>,
+    path: PathBuf,
+    temp: PathBuf,
+    committed: bool,
+}
+
+impl AtomicFile {
+    pub async fn create<P: AsRef<Path>>(path: P) -> Result<AtomicFile> {
+        let path = path.as_ref().to_path_buf();
+        let name = path
+            .file_name()
+            .ok_or(Error::from(ErrorKind::InvalidInput))?
+            .to_string_lossy()
+            .into_owned();
+        let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        if SWEPT.with_borrow_mut(|swept| swept.insert(dir.clone())) {
+            remove_stale_atomic_files(&dir).await?;
+        }
+        let temp = dir.join(format!(
+            ".{}.{}.{}{}",
+            name,
+            session(),
+            unique_suffix(),
+            TEMP_SUFFIX
+        ));
+        let file = File::create(&temp).await?;
+        Ok(AtomicFile {
+            file: Some(file),
+            path,
+            temp,
+            committed: false,
+        })
+    }
+    /// Flushes the written content and atomically replaces the target file with it.
+    pub async fn commit(mut self) -> Result<()> {
+        let mut file = self
+            .file
+            .take()
+            .expect("The file is only taken when committing.");
+        file.flush().await?;
+        // The sync access handle must be released before the file can be moved.
+        file.close().await?;
+        drop(file);
+        rename(&self.temp, &self.path).await?;
+        self.committed = true;
+        Ok(())
+    }
+    fn file(&mut self) -> Pin<&mut File> {
+        Pin::new(
+            self.file
+                .as_mut()
+                .expect("The file is only taken when committing."),
+        )
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        if !self.committed {
+            let file = self.file.take();
+            let temp = std::mem::take(&mut self.temp);
+            spawn_local(async move {
+                // The sync access handle must be released before the file can be removed.
+                if let Some(mut file) = file {
+                    let _ = file.close().await;
+                }
+                // Left for `remove_stale_atomic_files` if this fails.
+                let _ = remove_file(temp).await;
+            });
+        }
+    }
+}
+
+impl AsyncWrite for AtomicFile {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<Result<usize>> {
+        self.get_mut().file().poll_write(cx, buf)
+    }
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        self.get_mut().file().poll_flush(cx)
+    }
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        self.get_mut().file().poll_flush(cx)
+    }
+}
+
+/// Atomically replaces the content of the file at `path`,
+/// readers see either the old or the new content even if the tab crashes halfway.
+pub async fn write_atomic<P: AsRef<Path>, C: AsRef<[u8]>>(path: P, contents: C) -> Result<()> {
+    let mut file = AtomicFile::create(path).await?;
+    file.write_all(contents.as_ref()).await?;
+    file.commit().await
+}
@@ -0,0 +1,132 @@
+use std::{
+    any::Any,
+    cell::RefCell,
+    io::{Error, ErrorKind, Result},
+    path::Path,
+    rc::Rc,
+};
+
+use futures_lite::future::BoxedLocal;
+use web_sys::Blob;
+
+use crate::{BatchOp, BatchOutput, FileType, Metadata, OpenOptions};
+
+/// A storage engine that [`File`](crate::File), [`OpenOptions`] and the free functions of this crate dispatch to.
+///
+/// Paths handed to a backend are relative to its root and normalized,
+/// they consist only of normal components and the root itself is the empty path.
+/// Opened files are identified by a file descriptor chosen by the backend.
+///
+/// Methods return futures instead of being `async` so that a backend can start the operation eagerly,
+/// and so that the futures don't borrow the backend.
+/// Operations on one file descriptor are never issued before the previous one completes,
+/// except reads if [`concurrent_reads`](Self::concurrent_reads) returns true.
+pub trait Backend: Any {
+    /// Opens the file at `path` following `options`, returning its file descriptor and size.
+    ///
+    /// `create_new` must fail with [`ErrorKind::AlreadyExists`] if the file exists,
+    /// and the file must only be truncated if `truncate` is set.
+    fn open(&self, path: &Path, options: &OpenOptions) -> BoxedLocal<Result<(usize, u64)>>;
+    /// Reads at most `len` bytes at `cursor`, an empty result means the end of the file.
+    fn read(&self, fd: usize, cursor: u64, len: usize) -> BoxedLocal<Result<Vec<u8>>>;
+    /// Writes `buf` at `cursor`, returning how many bytes were written.
+    fn write(&self, fd: usize, cursor: u64, buf: &[u8]) -> BoxedLocal<Result<usize>>;
+    fn flush(&self, fd: usize) -> BoxedLocal<Result<()>>;
+    fn truncate(&self, fd: usize, size: u64) -> BoxedLocal<Result<()>>;
+    /// Closes the file, nothing but [`release`](Self::release) is issued for `fd` afterwards.
+    fn close(&self, fd: usize) -> BoxedLocal<Result<()>>;
+    /// Frees the file descriptor, called when a [`File`](crate::File) is dropped.
+    fn release(&self, fd: usize);
+    fn stat(&self, path: &Path) -> BoxedLocal<Result<Metadata>>;
+    /// Returns the names and types of the entries in the directory at `path`.
+    fn list(&self, path: &Path) -> BoxedLocal<Result<Vec<(String, FileType)>>>;
+    /// Creates the directory at `path`, and its missing parents if `recursive` is set.
+    fn create_dir(&self, path: &Path, recursive: bool) -> BoxedLocal<Result<()>>;
+    /// Removes the file or directory at `path`, and the content of the directory if `recursive` is set.
+    fn remove(&self, path: &Path, recursive: bool) -> BoxedLocal<Result<()>>;
+    /// Moves the file or directory at `from` to `to`, replacing `to` if it is an existing file.
+    fn rename(&self, from: &Path, to: &Path) -> BoxedLocal<Result<()>>;
+    /// Copies the whole content of `src` into `dst`, which is already truncated.
+    ///
+    /// This is an optional fast path,
+    /// the default returns [`ErrorKind::Unsupported`] and the copy falls back to reading and writing.
+    fn copy(&self, src: usize, dst: usize) -> BoxedLocal<Result<u64>> {
+        let _ = (src, dst);
+        unsupported()
+    }
+    /// Writes the content of `blob` at `cursor`, returning how many bytes were written.
+    ///
+    /// This is an optional fast path, the default returns [`ErrorKind::Unsupported`]
+    /// and the blob is read in chunks and written with [`write`](Self::write).
+    fn write_blob(&self, fd: usize, cursor: u64, blob: &Blob) -> BoxedLocal<Result<u64>> {
+        let _ = (fd, cursor, blob);
+        unsupported()
+    }
+    /// Returns the whole content of the file as a `Blob`.
+    ///
+    /// This is an optional fast path, the default returns [`ErrorKind::Unsupported`]
+    /// and the blob is built from chunks read with [`read`](Self::read).
+    fn blob(&self, fd: usize) -> BoxedLocal<Result<Blob>> {
+        let _ = fd;
+        unsupported()
+    }
+    /// Runs the operations of a [`Batch`](crate::Batch), returning a result for each of them in order.
+    ///
+    /// This is an optional fast path,
+    /// the default returns [`ErrorKind::Unsupported`] and the operations are run one after another.
+    fn batch(&self, ops: &[BatchOp]) -> BoxedLocal<Result<Vec<Result<BatchOutput>>>> {
+        let _ = ops;
+        unsupported()
+    }
+    /// Whether reads of one file descriptor may be issued before the previous ones complete,
+    /// each returning the data at its own cursor.
+    ///
+    /// [`ReadAhead`](crate::ReadAhead) keeps several reads in flight if this returns true.
+    /// The default is false.
+    fn concurrent_reads(&self) -> bool {
+        false
+    }
+}
+
+pub(crate) fn unsupported<T: 'static>() -> BoxedLocal<Result<T>> {
+    Box::pin(async { Err(Error::from(ErrorKind::Unsupported)) })
+}
+
+thread_local! {
+    static BACKEND: RefCell<Option<Rc<dyn Backend>>> = const { RefCell::new(None) };
+}
+
+/// Replaces the backend used by everything opened afterwards in this thread.
+///
+/// Files that are already open keep using the backend they were opened with.
+/// [`OpfsBackend`](crate::OpfsBackend) is used if this is never called,
+/// or [`OpfsBackend::main_thread`](crate::OpfsBackend::main_thread) if the browser can't use OPFS sync access handles in a worker,
+/// or [`IdbBackend`](crate::IdbBackend) if it can't use OPFS at all,
+/// or `NativeBackend` rooted in the current directory on native targets.
+pub fn set_backend<B: Backend>(backend: B) {
+    BACKEND.set(Some(Rc::new(backend)));
+}
+
+#[cfg(target_arch = "wasm32")]
+async fn default_backend() -> Rc<dyn Backend> {
+    if crate::opfs::sync_access_handles_usable().await {
+        Rc::new(crate::OpfsBackend::new())
+    } else if crate::opfs::writables_usable().await {
+        Rc::new(crate::OpfsBackend::main_thread())
+    } else {
+        Rc::new(crate::IdbBackend::new())
+    }
+}
+#[cfg(not(target_arch = "wasm32"))]
+async fn default_backend() -> Rc<dyn Backend> {
+    Rc::new(crate::NativeBackend::default())
+}
+
+pub(crate) async fn backend() -> Rc<dyn Backend> {
+    if let Some(backend) = BACKEND.with_borrow(Option::clone) {
+        return backend;
+    }
+    let default = default_backend().await;
+    // `set_backend` may have been called while probing.
+    BACKEND.with_borrow_mut(|backend| backend.get_or_insert(default).clone())
+}
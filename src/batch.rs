@@ -0,0 +1,262 @@
+use std::{
+    io::{Error, ErrorKind, Result},
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use futures_lite::{AsyncReadExt, AsyncWriteExt};
+
+use crate::{Backend, File, Metadata, OpenOptions, backend::backend, util::normalize};
+
+/// An operation of a [`Batch`], as handed to [`Backend::batch`] with a normalized path.
+#[derive(Debug, Clone)]
+pub enum BatchOp {
+    /// Opens the file for the following operations of the batch,
+    /// creating or truncating it as `options` say.
+    Open {
+        path: PathBuf,
+        options: OpenOptions,
+    },
+    /// Reads `len` bytes at `offset`, or up to the end if `len` is `None`.
+    Read {
+        path: PathBuf,
+        offset: u64,
+        len: Option<u64>,
+    },
+    /// Replaces the content of the file, creating it if missing.
+    Write {
+        path: PathBuf,
+        data: Vec<u8>,
+    },
+    /// Writes `data` at `offset` in an existing file.
+    WriteAt {
+        path: PathBuf,
+        offset: u64,
+        data: Vec<u8>,
+    },
+    /// Truncates or extends the file to `size`.
+    SetLen {
+        path: PathBuf,
+        size: u64,
+    },
+    /// Makes the writes to the file so far durable.
+    Flush {
+        path: PathBuf,
+    },
+    Metadata {
+        path: PathBuf,
+    },
+    /// Removes a file or an empty directory.
+    Remove {
+        path: PathBuf,
+    },
+}
+
+impl BatchOp {
+    pub fn path(&self) -> &Path {
+        match self {
+            BatchOp::Open { path, .. }
+            | BatchOp::Read { path, .. }
+            | BatchOp::Write { path, .. }
+            | BatchOp::WriteAt { path, .. }
+            | BatchOp::SetLen { path, .. }
+            | BatchOp::Flush { path }
+            | BatchOp::Metadata { path }
+            | BatchOp::Remove { path } => path,
+        }
+    }
+    /// Whether the operation changes the content of the file.
+    pub(crate) fn is_write(&self) -> bool {
+        match self {
+            BatchOp::Open { options, .. } => {
+                options.is_write() || options.is_append() || options.is_truncate()
+            }
+            BatchOp::Write { .. } | BatchOp::WriteAt { .. } | BatchOp::SetLen { .. } => true,
+            BatchOp::Read { .. }
+            | BatchOp::Flush { .. }
+            | BatchOp::Metadata { .. }
+            | BatchOp::Remove { .. } => false,
+        }
+    }
+}
+
+/// The result of a [`BatchOp`].
+#[derive(Debug)]
+pub enum BatchOutput {
+    /// The bytes read by [`BatchOp::Read`].
+    Data(Vec<u8>),
+    Metadata(Metadata),
+    /// The operation has nothing to return.
+    Done,
+}
+
+impl BatchOutput {
+    /// Returns the bytes read, or an empty slice for other outputs.
+    pub fn data(&self) -> &[u8] {
+        match self {
+            BatchOutput::Data(data) => data,
+            _ => &[],
+        }
+    }
+}
+
+/// A list of operations on files, run one after another in the order they were added.
+///
+/// With [`OpfsBackend`](crate::OpfsBackend) the whole batch is sent to the worker in one message,
+/// instead of a few messages per operation.
+/// Each file is opened once for the batch and closed at its end,
+/// or accessed through the handle of a [`File`] that has it open already.
+/// Other backends open the file for every operation.
+#[derive(Default)]
+pub struct Batch {
+    ops: Vec<Result<BatchOp>>,
+}
+
+impl Batch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    fn push<P: AsRef<Path>>(&mut self, path: P, op: impl FnOnce(PathBuf) -> BatchOp) -> &mut Batch {
+        self.ops.push(normalize(path).map(op));
+        self
+    }
+    /// Opens the file for the following operations, creating or truncating it as `options` say.
+    ///
+    /// Other operations open the file themselves if needed, so this is only required to create it empty or to truncate it.
+    pub fn open<P: AsRef<Path>>(&mut self, path: P, options: &OpenOptions) -> &mut Batch {
+        let options = *options;
+        self.push(path, |path| BatchOp::Open { path, options })
+    }
+    /// Reads the whole file.
+    pub fn read<P: AsRef<Path>>(&mut self, path: P) -> &mut Batch {
+        self.push(path, |path| BatchOp::Read {
+            path,
+            offset: 0,
+            len: None,
+        })
+    }
+    /// Reads at most `len` bytes at `offset`.
+    pub fn read_range<P: AsRef<Path>>(&mut self, path: P, offset: u64, len: u64) -> &mut Batch {
+        self.push(path, |path| BatchOp::Read {
+            path,
+            offset,
+            len: Some(len),
+        })
+    }
+    /// Replaces the content of the file, creating it if missing.
+    pub fn write<P: AsRef<Path>, C: AsRef<[u8]>>(&mut self, path: P, contents: C) -> &mut Batch {
+        let data = contents.as_ref().to_vec();
+        self.push(path, |path| BatchOp::Write { path, data })
+    }
+    /// Writes `contents` at `offset` in an existing file.
+    pub fn write_at<P: AsRef<Path>, C: AsRef<[u8]>>(
+        &mut self,
+        path: P,
+        offset: u64,
+        contents: C,
+    ) -> &mut Batch {
+        let data = contents.as_ref().to_vec();
+        self.push(path, |path| BatchOp::WriteAt { path, offset, data })
+    }
+    pub fn set_len<P: AsRef<Path>>(&mut self, path: P, size: u64) -> &mut Batch {
+        self.push(path, |path| BatchOp::SetLen { path, size })
+    }
+    /// Makes the writes to the file so far durable, they are also flushed at the end of the batch.
+    pub fn flush<P: AsRef<Path>>(&mut self, path: P) -> &mut Batch {
+        self.push(path, |path| BatchOp::Flush { path })
+    }
+    pub fn metadata<P: AsRef<Path>>(&mut self, path: P) -> &mut Batch {
+        self.push(path, |path| BatchOp::Metadata { path })
+    }
+    /// Removes a file or an empty directory.
+    pub fn remove<P: AsRef<Path>>(&mut self, path: P) -> &mut Batch {
+        self.push(path, |path| BatchOp::Remove { path })
+    }
+    /// Runs the operations, returning their results in the order they were added.
+    pub async fn run(&self) -> Vec<Result<BatchOutput>> {
+        let backend = backend().await;
+        let ops: Vec<BatchOp> = self
+            .ops
+            .iter()
+            .filter_map(|op| op.as_ref().ok().cloned())
+            .collect();
+        let results = match backend.batch(&ops).await {
+            Err(e) if e.kind() == ErrorKind::Unsupported => {
+                let mut results = Vec::with_capacity(ops.len());
+                for op in &ops {
+                    results.push(run_op(&backend, op).await);
+                }
+                results
+            }
+            Err(e) => ops.iter().map(|_| Err(copy_error(&e))).collect(),
+            Ok(results) => results,
+        };
+        let mut results = results.into_iter();
+        self.ops
+            .iter()
+            .map(|op| match op {
+                Ok(_) => results
+                    .next()
+                    .expect("The backend returned too few results, this is an error of the crate."),
+                Err(e) => Err(copy_error(e)),
+            })
+            .collect()
+    }
+}
+
+fn copy_error(e: &Error) -> Error {
+    Error::new(e.kind(), e.to_string())
+}
+
+async fn open(backend: &Rc<dyn Backend>, path: &Path, options: &OpenOptions) -> Result<File> {
+    let (fd, size) = backend.open(path, options).await?;
+    Ok(File::new(backend.clone(), fd, size, path.to_path_buf()))
+}
+
+/// Runs an operation through the other methods of the backend.
+async fn run_op(backend: &Rc<dyn Backend>, op: &BatchOp) -> Result<BatchOutput> {
+    match op {
+        BatchOp::Open { path, options } => {
+            let mut file = open(backend, path, options).await?;
+            file.close().await?;
+            Ok(BatchOutput::Done)
+        }
+        BatchOp::Read { path, offset, len } => {
+            let mut file = open(backend, path, OpenOptions::new().read(true)).await?;
+            file.cursor = *offset;
+            let mut buf = Vec::new();
+            match len {
+                Some(len) => (&mut file).take(*len).read_to_end(&mut buf).await?,
+                None => file.read_sized(&mut buf, false).await?,
+            };
+            Ok(BatchOutput::Data(buf))
+        }
+        BatchOp::Write { path, data } => {
+            let options = *OpenOptions::new().write(true).create(true).truncate(true);
+            let mut file = open(backend, path, &options).await?;
+            file.write_all(data).await?;
+            file.close().await?;
+            Ok(BatchOutput::Done)
+        }
+        BatchOp::WriteAt { path, offset, data } => {
+            let mut file = open(backend, path, OpenOptions::new().write(true)).await?;
+            file.cursor = *offset;
+            file.write_all(data).await?;
+            file.close().await?;
+            Ok(BatchOutput::Done)
+        }
+        BatchOp::SetLen { path, size } => {
+            let mut file = open(backend, path, OpenOptions::new().write(true)).await?;
+            file.set_len(*size).await?;
+            file.close().await?;
+            Ok(BatchOutput::Done)
+        }
+        // Every operation closes the file, which flushes it.
+        BatchOp::Flush { .. } => Ok(BatchOutput::Done),
+        BatchOp::Metadata { path } => Ok(BatchOutput::Metadata(backend.stat(path).await?)),
+        BatchOp::Remove { path } => {
+            backend.remove(path, false).await?;
+            Ok(BatchOutput::Done)
+        }
+    }
+}
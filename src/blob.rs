@@ -0,0 +1,126 @@
+use std::{
+    fmt,
+    io::{ErrorKind, Result},
+    path::Path,
+};
+
+use futures_lite::AsyncWriteExt;
+use js_sys::{Array, Uint8Array};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Blob, Url};
+
+use crate::{File, util::js_value_to_error};
+
+/// Size of the chunks a blob is read in when the backend can't handle it directly.
+const BLOB_CHUNK_SIZE: u64 = 1 << 20;
+
+impl File {
+    /// Writes the content of `blob` at the cursor, returning how many bytes were written.
+    ///
+    /// With [`OpfsBackend`](crate::OpfsBackend) the blob is read by the worker,
+    /// so the bytes never pass through wasm memory.
+    /// Other backends read it in chunks.
+    pub async fn write_blob(&mut self, blob: &Blob) -> Result<u64> {
+        match self.backend.write_blob(self.fd, self.cursor, blob).await {
+            Err(e) if e.kind() == ErrorKind::Unsupported => (),
+            result => {
+                let size = result?;
+                self.cursor += size;
+                self.size = self.size.max(self.cursor);
+                return Ok(size);
+            }
+        }
+        let size = blob.size() as u64;
+        let mut offset = 0;
+        while offset < size {
+            let end = (offset + BLOB_CHUNK_SIZE).min(size);
+            let chunk = blob
+                .slice_with_f64_and_f64(offset as f64, end as f64)
+                .map_err(js_value_to_error)?;
+            let buf = JsFuture::from(chunk.array_buffer())
+                .await
+                .map_err(js_value_to_error)?;
+            self.write_all(&Uint8Array::new(&buf).to_vec()).await?;
+            offset = end;
+        }
+        Ok(size)
+    }
+    /// Returns the whole content of the file as a `Blob`, regardless of the cursor.
+    ///
+    /// With [`OpfsBackend`](crate::OpfsBackend) this is the `File` from `getFile()`,
+    /// which can't be read anymore once the file changes.
+    /// The worker flushes the file first, while with [`OpfsBackend::main_thread`](crate::OpfsBackend::main_thread)
+    /// writes that are not flushed are not included.
+    pub async fn to_blob(&self) -> Result<Blob> {
+        match self.backend.blob(self.fd).await {
+            Err(e) if e.kind() == ErrorKind::Unsupported => (),
+            result => return result,
+        }
+        let parts = Array::new();
+        let mut cursor = 0;
+        loop {
+            let chunk = self
+                .backend
+                .read(self.fd, cursor, BLOB_CHUNK_SIZE as usize)
+                .await?;
+            if chunk.is_empty() {
+                break;
+            }
+            cursor += chunk.len() as u64;
+            parts.push(&Uint8Array::from(&chunk[..]));
+        }
+        Blob::new_with_u8_array_sequence(&parts).map_err(js_value_to_error)
+    }
+}
+
+/// Writes the content of `blob` to a file, creating or truncating it, see [`File::write_blob`].
+pub async fn write_blob<P: AsRef<Path>>(path: P, blob: &Blob) -> Result<()> {
+    let mut file = File::create(path).await?;
+    file.write_blob(blob).await?;
+    file.close().await
+}
+
+/// Returns the content of a file as a `Blob`, see [`File::to_blob`].
+pub async fn blob<P: AsRef<Path>>(path: P) -> Result<Blob> {
+    File::open(path).await?.to_blob().await
+}
+
+/// A URL from `URL.createObjectURL`, revoked when dropped.
+///
+/// Keep it alive as long as the URL is used, for example until an image is loaded.
+pub struct ObjectUrl {
+    url: String,
+}
+
+impl ObjectUrl {
+    pub fn new(blob: &Blob) -> Result<Self> {
+        let url = Url::create_object_url_with_blob(blob).map_err(js_value_to_error)?;
+        Ok(Self { url })
+    }
+    pub fn as_str(&self) -> &str {
+        &self.url
+    }
+}
+
+impl AsRef<str> for ObjectUrl {
+    fn as_ref(&self) -> &str {
+        &self.url
+    }
+}
+
+impl fmt::Display for ObjectUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.url)
+    }
+}
+
+impl Drop for ObjectUrl {
+    fn drop(&mut self) {
+        let _ = Url::revoke_object_url(&self.url);
+    }
+}
+
+/// Creates an [`ObjectUrl`] for the content of a file, see [`blob`].
+pub async fn object_url<P: AsRef<Path>>(path: P) -> Result<ObjectUrl> {
+    ObjectUrl::new(&blob(path).await?)
+}
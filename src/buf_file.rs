@@ -0,0 +1,210 @@
+use std::{
+    io::{Error, ErrorKind, Result, SeekFrom},
+    mem,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_lite::{AsyncRead, AsyncSeek, AsyncWrite, AsyncWriteExt, future::BoxedLocal};
+
+use crate::{File, util::spawn_local};
+
+const DEFAULT_CAPACITY: usize = 64 * 1024;
+
+const CLOSED_FILE_ERROR: &str = "Using a closed file descriptor, this is an error of the crate.";
+
+/// A write that was issued to the backend and not completed yet.
+struct PendingWrite {
+    task: BoxedLocal<Result<usize>>,
+    data: Vec<u8>,
+    written: usize,
+    cursor: u64,
+    /// The size of the file before the write, restored if it fails.
+    size: u64,
+}
+
+/// A file that buffers writes and sends them to the backend in the background.
+///
+/// Writes are collected until the buffer reaches its capacity,
+/// then sent as one write while the following writes fill another buffer,
+/// so many small writes cost a few large worker messages.
+/// Buffered writes are also sent by `flush`, `close`, reads and seeks, and when the file is dropped.
+///
+/// A write only fails when the data is buffered if the buffer was full,
+/// otherwise the error of a background write is returned by the next operation.
+/// The cursor is then left after the last byte written, and the data buffered after the failed write is discarded.
+/// Errors of writes sent when dropping are lost, call `flush` or `close` to see them.
+pub struct BufFile {
+    /// Only taken when dropping.
+    file: Option<File>,
+    capacity: usize,
+    buf: Vec<u8>,
+    pending: Option<PendingWrite>,
+}
+
+impl BufFile {
+    /// Buffers up to 64 KiB.
+    pub fn new(file: File) -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY, file)
+    }
+    pub fn with_capacity(capacity: usize, file: File) -> Self {
+        Self {
+            file: Some(file),
+            capacity,
+            buf: Vec::with_capacity(capacity),
+            pending: None,
+        }
+    }
+    pub fn get_ref(&self) -> &File {
+        self.file.as_ref().expect(CLOSED_FILE_ERROR)
+    }
+    fn file(&mut self) -> &mut File {
+        self.file.as_mut().expect(CLOSED_FILE_ERROR)
+    }
+    /// Sends the buffered writes and returns the file.
+    pub async fn into_inner(mut self) -> Result<File> {
+        futures_lite::future::poll_fn(|cx| self.poll_drain(cx)).await?;
+        Ok(self.file.take().expect(CLOSED_FILE_ERROR))
+    }
+    /// Sends the buffer as one write at the cursor, if nothing is in flight.
+    fn start_write(&mut self) {
+        if self.buf.is_empty() || self.pending.is_some() {
+            return;
+        }
+        let data = mem::replace(&mut self.buf, Vec::with_capacity(self.capacity));
+        let file = self.file();
+        let cursor = file.cursor;
+        let size = file.size;
+        let task = file.backend.write(file.fd, cursor, &data);
+        file.cursor += data.len() as u64;
+        file.size = file.size.max(file.cursor);
+        self.pending = Some(PendingWrite {
+            task,
+            data,
+            written: 0,
+            cursor,
+            size,
+        });
+    }
+    /// Polls the write in flight to completion.
+    fn poll_pending(&mut self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        while let Some(pending) = &mut self.pending {
+            match std::task::ready!(pending.task.as_mut().poll(cx)) {
+                Ok(written) if written > 0 => pending.written += written,
+                result => {
+                    // The cursor and size only cover what was written,
+                    // and the writes buffered after the failed one are dropped so they leave no hole.
+                    let end = pending.cursor + pending.written as u64;
+                    let size = pending.size.max(end);
+                    self.pending = None;
+                    self.buf.clear();
+                    let file = self.file();
+                    file.cursor = end;
+                    file.size = size;
+                    let e = result.err().unwrap_or(Error::from(ErrorKind::WriteZero));
+                    return Poll::Ready(Err(e));
+                }
+            }
+            if pending.written == pending.data.len() {
+                self.pending = None;
+            } else {
+                let file = self.file.as_ref().expect(CLOSED_FILE_ERROR);
+                pending.task = file.backend.write(
+                    file.fd,
+                    pending.cursor + pending.written as u64,
+                    &pending.data[pending.written..],
+                );
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+    /// Sends all the buffered writes and waits for them.
+    fn poll_drain(&mut self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        loop {
+            std::task::ready!(self.poll_pending(cx))?;
+            if self.buf.is_empty() {
+                return Poll::Ready(Ok(()));
+            }
+            self.start_write();
+        }
+    }
+}
+
+impl AsyncWrite for BufFile {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize>> {
+        // Report the error of a background write as soon as it is known.
+        if let Poll::Ready(Err(e)) = self.poll_pending(cx) {
+            return Poll::Ready(Err(e));
+        }
+        if self.buf.len() >= self.capacity {
+            std::task::ready!(self.poll_pending(cx))?;
+            self.start_write();
+        }
+        self.buf.extend_from_slice(buf);
+        if self.buf.len() >= self.capacity {
+            self.start_write();
+        }
+        Poll::Ready(Ok(buf.len()))
+    }
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        std::task::ready!(self.poll_drain(cx))?;
+        Pin::new(self.file()).poll_flush(cx)
+    }
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        std::task::ready!(self.poll_drain(cx))?;
+        Pin::new(self.file()).poll_close(cx)
+    }
+}
+
+impl AsyncRead for BufFile {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize>> {
+        std::task::ready!(self.poll_drain(cx))?;
+        Pin::new(self.file()).poll_read(cx, buf)
+    }
+}
+
+impl AsyncSeek for BufFile {
+    fn poll_seek(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        pos: SeekFrom,
+    ) -> Poll<Result<u64>> {
+        std::task::ready!(self.poll_drain(cx))?;
+        Pin::new(self.file()).poll_seek(cx, pos)
+    }
+}
+
+impl Drop for BufFile {
+    fn drop(&mut self) {
+        let Some(mut file) = self.file.take() else {
+            return;
+        };
+        let pending = self.pending.take();
+        let buf = mem::take(&mut self.buf);
+        if pending.is_none() && buf.is_empty() {
+            return;
+        }
+        // The file is released once the writes are done.
+        spawn_local(async move {
+            if let Some(pending) = pending {
+                let Ok(written) = pending.task.await else {
+                    return;
+                };
+                let written = pending.written + written;
+                file.cursor = pending.cursor + written as u64;
+                if file.write_all(&pending.data[written..]).await.is_err() {
+                    return;
+                }
+            }
+            let _ = file.write_all(&buf).await;
+        });
+    }
+}
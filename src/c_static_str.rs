@@ -18,6 +18,16 @@ extern "C" {
     pub static TRUNCATE: JsString = "Truncate";
     #[wasm_bindgen(thread_local_v2, static_string)]
     pub static DROP: JsString = "Drop";
+    #[wasm_bindgen(thread_local_v2, static_string)]
+    pub static COPY: JsString = "Copy";
+    #[wasm_bindgen(thread_local_v2, static_string)]
+    pub static PROBE: JsString = "Probe";
+    #[wasm_bindgen(thread_local_v2, static_string)]
+    pub static WRITE_BLOB: JsString = "WriteBlob";
+    #[wasm_bindgen(thread_local_v2, static_string)]
+    pub static TO_BLOB: JsString = "ToBlob";
+    #[wasm_bindgen(thread_local_v2, static_string)]
+    pub static BATCH: JsString = "Batch";
 
     #[wasm_bindgen(thread_local_v2, static_string)]
     pub static INDEX: JsString = "index";
@@ -35,4 +45,50 @@ extern "C" {
     pub static HANDLE: JsString = "handle";
     #[wasm_bindgen(thread_local_v2, static_string)]
     pub static CURSOR: JsString = "cursor";
+    #[wasm_bindgen(thread_local_v2, static_string)]
+    pub static SRC: JsString = "src";
+    #[wasm_bindgen(thread_local_v2, static_string)]
+    pub static DST: JsString = "dst";
+    #[wasm_bindgen(thread_local_v2, static_string)]
+    pub static BLOB: JsString = "blob";
+    #[wasm_bindgen(thread_local_v2, static_string)]
+    pub static OPS: JsString = "ops";
+    #[wasm_bindgen(thread_local_v2, static_string)]
+    pub static RESULTS: JsString = "results";
+    #[wasm_bindgen(thread_local_v2, static_string)]
+    pub static KIND: JsString = "kind";
+    #[wasm_bindgen(thread_local_v2, static_string)]
+    pub static DIR: JsString = "dir";
+    #[wasm_bindgen(thread_local_v2, static_string)]
+    pub static NAME: JsString = "name";
+    #[wasm_bindgen(thread_local_v2, static_string)]
+    pub static FILE: JsString = "file";
+    #[wasm_bindgen(thread_local_v2, static_string)]
+    pub static WRITABLE: JsString = "writable";
+    #[wasm_bindgen(thread_local_v2, static_string)]
+    pub static ERROR_NAME: JsString = "errorName";
+    #[wasm_bindgen(thread_local_v2, static_string)]
+    pub static IS_DIR: JsString = "isDir";
+    #[wasm_bindgen(thread_local_v2, static_string)]
+    pub static MODIFIED: JsString = "modified";
+    #[wasm_bindgen(thread_local_v2, static_string)]
+    pub static DONE: JsString = "done";
+    #[wasm_bindgen(thread_local_v2, static_string)]
+    pub static VALUE: JsString = "value";
+    #[wasm_bindgen(thread_local_v2, static_string)]
+    pub static PULL: JsString = "pull";
+    #[wasm_bindgen(thread_local_v2, static_string)]
+    pub static CANCEL: JsString = "cancel";
+    #[wasm_bindgen(thread_local_v2, static_string)]
+    pub static SINK_WRITE: JsString = "write";
+    #[wasm_bindgen(thread_local_v2, static_string)]
+    pub static SINK_CLOSE: JsString = "close";
+    #[wasm_bindgen(thread_local_v2, static_string)]
+    pub static ABORT: JsString = "abort";
+    #[wasm_bindgen(thread_local_v2, static_string)]
+    pub static BUFFER: JsString = "buffer";
+    #[wasm_bindgen(thread_local_v2, static_string)]
+    pub static BYTE_OFFSET: JsString = "byteOffset";
+    #[wasm_bindgen(thread_local_v2, static_string)]
+    pub static BYTE_LENGTH: JsString = "byteLength";
 }
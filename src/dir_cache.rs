@@ -0,0 +1,93 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use web_sys::FileSystemDirectoryHandle;
+
+use crate::mount::lookup;
+
+/// Number of directory handles kept by [`OpfsBackend`](crate::OpfsBackend) in each thread.
+const CAPACITY: usize = 256;
+
+/// A least recently used cache of directory handles, keyed by normalized path.
+pub(crate) struct DirCache {
+    entries: HashMap<PathBuf, (FileSystemDirectoryHandle, u64)>,
+    /// Incremented on every use, the entry with the smallest tick is evicted first.
+    tick: u64,
+}
+
+impl DirCache {
+    fn new() -> Self {
+        Self {
+            entries: HashMap::new(),
+            tick: 0,
+        }
+    }
+    fn get(&mut self, path: &Path) -> Option<FileSystemDirectoryHandle> {
+        self.tick += 1;
+        let (handle, used) = self.entries.get_mut(path)?;
+        *used = self.tick;
+        Some(handle.clone())
+    }
+    fn insert(&mut self, path: PathBuf, handle: FileSystemDirectoryHandle) {
+        self.tick += 1;
+        if self.entries.len() >= CAPACITY && !self.entries.contains_key(&path) {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(path, _)| path.clone());
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.entries.insert(path, (handle, self.tick));
+    }
+    fn remove(&mut self, path: &Path) {
+        self.entries.remove(path);
+    }
+    /// Forgets `path` and everything under it.
+    fn invalidate(&mut self, path: &Path) {
+        self.entries.retain(|cached, _| !cached.starts_with(path));
+    }
+}
+
+thread_local! {
+    static DIR_CACHE: RefCell<DirCache> = RefCell::new(DirCache::new());
+}
+
+/// Returns the cached handle of the closest ancestor of `path` not above `base`, with its path.
+pub(crate) fn closest(path: &Path, base: &Path) -> Option<(PathBuf, FileSystemDirectoryHandle)> {
+    DIR_CACHE.with_borrow_mut(|cache| {
+        path.ancestors()
+            .take_while(|ancestor| ancestor.starts_with(base))
+            .find_map(|ancestor| Some((ancestor.to_path_buf(), cache.get(ancestor)?)))
+    })
+}
+
+pub(crate) fn insert(path: PathBuf, handle: FileSystemDirectoryHandle) {
+    DIR_CACHE.with_borrow_mut(|cache| cache.insert(path, handle));
+}
+
+/// Forgets the handles of `path` and its ancestors below its mount point.
+pub(crate) fn forget_ancestors(path: &Path) {
+    let point = lookup(path).map(|(point, _)| point).unwrap_or_default();
+    DIR_CACHE.with_borrow_mut(|cache| {
+        for ancestor in path.ancestors().take_while(|ancestor| *ancestor != point) {
+            cache.remove(ancestor);
+        }
+    })
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+pub(crate) fn contains(path: &Path) -> bool {
+    DIR_CACHE.with_borrow(|cache| cache.entries.contains_key(path))
+}
+
+/// Forgets the handles of `path` and everything under it,
+/// called when it is removed, renamed or mounted over.
+pub(crate) fn invalidate(path: &Path) {
+    DIR_CACHE.with_borrow_mut(|cache| cache.invalidate(path));
+}
@@ -2,35 +2,40 @@ use std::{
     cell::RefCell,
     future::Future,
     io::{Error, ErrorKind, Result},
-    path::Path,
+    path::{Path, PathBuf},
     pin::Pin,
     rc::Rc,
     task::{Context, Poll},
 };
 
-use futures_lite::AsyncWriteExt;
-use js_sys::Object;
-use wasm_bindgen::JsValue;
+use futures_lite::{AsyncWriteExt, future::BoxedLocal};
 
 use crate::{
-    open_options::OpenFileFuture, read::ReadResult, util::set_value, FileType, Fs, Metadata,
-    OpenOptions, Permissions, Task, FD, FS, INDEX, SIZE, TRUNCATE,
+    Backend, FileType, Metadata, OpenOptions, Permissions, locks::HeldLock,
+    open_options::OpenFileFuture, util::spawn_local,
 };
 
 pub struct File {
+    pub(crate) backend: Rc<dyn Backend>,
     pub(crate) fd: usize,
     pub(crate) cursor: u64,
     pub(crate) size: u64,
-    pub(crate) read_task: Option<Rc<RefCell<Task<Result<ReadResult>>>>>,
-    pub(crate) write_task: Option<Rc<RefCell<Task<Result<usize>>>>>,
-    pub(crate) flush_task: Option<Rc<RefCell<Task<Result<()>>>>>,
-    pub(crate) close_task: Option<Rc<RefCell<Task<Result<()>>>>>,
+    pub(crate) read_task: Option<BoxedLocal<Result<Vec<u8>>>>,
+    pub(crate) write_task: Option<BoxedLocal<Result<usize>>>,
+    pub(crate) flush_task: Option<BoxedLocal<Result<()>>>,
+    pub(crate) close_task: Option<BoxedLocal<Result<()>>>,
+    /// The normalized path the file was opened at, naming its lock.
+    pub(crate) path: PathBuf,
+    pub(crate) lock: RefCell<Option<HeldLock>>,
 }
 impl File {
-    pub(crate) fn new(fd: usize, size: u64) -> Self {
+    pub(crate) fn new(backend: Rc<dyn Backend>, fd: usize, size: u64, path: PathBuf) -> Self {
         Self {
+            backend,
             fd,
             size,
+            path,
+            lock: RefCell::new(None),
             cursor: 0,
             read_task: None,
             write_task: None,
@@ -42,7 +47,11 @@ impl File {
         OpenOptions::new().read(true).open(path)
     }
     pub fn create<P: AsRef<Path>>(path: P) -> OpenFileFuture {
-        OpenOptions::new().create(true).write(true).open(path)
+        OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(path)
     }
     /// Currenly this is only available in [`std::fs::File`] not [`async-fs::File`]
     pub fn create_new<P: AsRef<Path>>(path: P) -> OpenFileFuture {
@@ -60,12 +69,7 @@ impl File {
     }
     /// This is a different implementation that require `&mut self` while [`async-fs::File`] and [`std::fs::File`] doesn't.
     pub fn set_len<'a>(&'a mut self, size: u64) -> TruncateFuture<'a> {
-        let task = Rc::new(RefCell::new(Task {
-            waker: None,
-            result: None,
-        }));
-        let task_clone = task.clone();
-        FS.with_borrow(|fs| fs.truncate(self.fd, size, task_clone));
+        let task = self.backend.truncate(self.fd, size);
         TruncateFuture {
             task,
             size,
@@ -73,28 +77,37 @@ impl File {
         }
     }
     pub async fn metadata(&self) -> Result<Metadata> {
-        Ok(Metadata {
-            ty: FileType::File,
-            len: self.size,
-        })
+        Ok(Metadata::new(FileType::File, self.size, None))
     }
     /// Currently always returns Err,
     /// because the permission of a file in *File System API* is determined when opening the file
     /// and can't be changed afterwards.
     pub async fn set_permissions(&self, perm: Permissions) -> Result<()> {
-        drop(perm);
+        let _ = perm;
         Err(Error::from(ErrorKind::Other))
     }
 }
 
 impl Drop for File {
     fn drop(&mut self) {
-        FS.with_borrow(|fs| fs.drop_file(self.fd));
+        match self.lock.get_mut().take() {
+            // Released once the file is closed, so whoever waits for the lock can open it.
+            Some(lock) => {
+                let backend = self.backend.clone();
+                let fd = self.fd;
+                spawn_local(async move {
+                    let _ = backend.close(fd).await;
+                    backend.release(fd);
+                    drop(lock);
+                });
+            }
+            None => self.backend.release(self.fd),
+        }
     }
 }
 
 pub struct TruncateFuture<'a> {
-    task: Rc<RefCell<Task<Result<()>>>>,
+    task: BoxedLocal<Result<()>>,
     size: u64,
     file: &'a mut File,
 }
@@ -102,30 +115,13 @@ impl Future for TruncateFuture<'_> {
     type Output = Result<()>;
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let inner_self = self.get_mut();
-        let mut inner = inner_self.task.borrow_mut();
 
-        if let Some(val) = inner.result.take() {
+        if let Poll::Ready(val) = inner_self.task.as_mut().poll(cx) {
             if let Ok(()) = val {
                 inner_self.file.size = inner_self.size;
             }
             return Poll::Ready(val);
         }
-        inner.waker = Some(cx.waker().clone());
         Poll::Pending
     }
 }
-
-impl Fs {
-    fn truncate(&self, fd: usize, size: u64, task: Rc<RefCell<Task<Result<()>>>>) {
-        let index = self.inner.borrow_mut().truncating_tasks.insert(task);
-
-        let msg = Object::new();
-        let truncate = Object::new();
-        set_value(&truncate, &INDEX, &JsValue::from_f64(index as f64));
-        set_value(&truncate, &FD, &JsValue::from_f64(fd as f64));
-        set_value(&truncate, &SIZE, &JsValue::from_f64(size as f64));
-        set_value(&msg, &TRUNCATE, &truncate);
-
-        self.worker.post_message(&msg).unwrap();
-    }
-}
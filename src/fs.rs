@@ -0,0 +1,699 @@
+use std::{
+    cell::RefCell,
+    io::{Error, ErrorKind, Result},
+    rc::Rc,
+};
+
+use js_sys::{Array, ArrayBuffer, Object, Reflect, Uint8Array};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    Blob, FileSystemCreateWritableOptions, FileSystemFileHandle, FileSystemWritableFileStream,
+    MessageEvent, Worker,
+};
+
+use crate::{
+    ARENA_REMOVE_ERROR, BATCH, BLOB, BUF, CLOSE, COPY, CURSOR, DROP, DST, DYN_INTO_ERROR, ERROR,
+    FD, FLUSH, GETTING_JS_FIELD_ERROR, HANDLE, INDEX, OPEN, OPS, OPTIONS, OpenOptions, POST_ERROR,
+    PROBE, READ, RESULTS, SIZE, SRC, TO_BLOB, TRUNCATE, WRITE, WRITE_BLOB,
+    arena::Arena,
+    util::{SharedTask, get_value, get_value_as_f64, js_value_to_error, set_value},
+};
+
+pub(crate) struct ReadResult {
+    pub buf: ArrayBuffer,
+    pub size: usize,
+}
+
+pub(crate) struct FsInner {
+    opening_tasks: Arena<SharedTask<Result<(usize, u64)>>>,
+    reading_tasks: Arena<SharedTask<Result<ReadResult>>>,
+    writing_tasks: Arena<SharedTask<Result<usize>>>,
+    flushing_tasks: Arena<SharedTask<Result<()>>>,
+    closing_tasks: Arena<SharedTask<Result<()>>>,
+    truncating_tasks: Arena<SharedTask<Result<()>>>,
+    copying_tasks: Arena<SharedTask<Result<u64>>>,
+    probing_tasks: Arena<SharedTask<Result<()>>>,
+    writing_blob_tasks: Arena<SharedTask<Result<u64>>>,
+    blob_tasks: Arena<SharedTask<Result<Blob>>>,
+    batch_tasks: Arena<SharedTask<Result<Array>>>,
+}
+pub(crate) struct Fs {
+    inner: Rc<RefCell<FsInner>>,
+    _closure: Closure<dyn FnMut(MessageEvent)>,
+    worker: Worker,
+}
+impl Fs {
+    fn new() -> Self {
+        let worker = Worker::new(&wasm_bindgen::link_to!(module = "/src/worker.js"))
+            .expect("Creating web worker failed. This crate relies on web worker to work.");
+
+        let inner = FsInner {
+            opening_tasks: Arena::new(),
+            reading_tasks: Arena::new(),
+            writing_tasks: Arena::new(),
+            flushing_tasks: Arena::new(),
+            closing_tasks: Arena::new(),
+            truncating_tasks: Arena::new(),
+            copying_tasks: Arena::new(),
+            probing_tasks: Arena::new(),
+            writing_blob_tasks: Arena::new(),
+            blob_tasks: Arena::new(),
+            batch_tasks: Arena::new(),
+        };
+        let inner = Rc::new(RefCell::new(inner));
+        let inner_clone = inner.clone();
+        #[repr(u32)]
+        enum InMsgType {
+            Open = 0,
+            Read,
+            Write,
+            Flush,
+            Close,
+            Truncate,
+            Copy,
+            Probe,
+            WriteBlob,
+            ToBlob,
+            Batch,
+        }
+        let on_message: Closure<dyn FnMut(MessageEvent)> =
+            Closure::new(move |msg: MessageEvent| {
+                let received = msg.data();
+                let error = get_value(&received, &ERROR);
+                let error = if !error.is_undefined() {
+                    error.as_string()
+                } else {
+                    None
+                };
+
+                let open_msg = Reflect::get_u32(&received, InMsgType::Open as u32)
+                    .expect(GETTING_JS_FIELD_ERROR);
+                if !open_msg.is_undefined() {
+                    let index = get_value_as_f64(&open_msg, &INDEX) as usize;
+                    let task = inner_clone
+                        .borrow_mut()
+                        .opening_tasks
+                        .remove(index)
+                        .expect(ARENA_REMOVE_ERROR);
+                    let mut state = task.borrow_mut();
+                    if let Some(error) = error {
+                        state.result = Some(Err(Error::other(error)));
+                    } else {
+                        let fd = get_value_as_f64(&open_msg, &FD) as usize;
+                        let size = get_value_as_f64(&open_msg, &SIZE) as u64;
+                        state.result = Some(Ok((fd, size)));
+                    }
+                    if let Some(waker) = state.waker.take() {
+                        waker.wake();
+                    }
+                    return;
+                }
+                let read_msg = Reflect::get_u32(&received, InMsgType::Read as u32)
+                    .expect(GETTING_JS_FIELD_ERROR);
+                if !read_msg.is_undefined() {
+                    let index = get_value_as_f64(&read_msg, &INDEX) as usize;
+                    let task = inner_clone
+                        .borrow_mut()
+                        .reading_tasks
+                        .remove(index)
+                        .expect(ARENA_REMOVE_ERROR);
+                    let mut state = task.borrow_mut();
+                    if let Some(error) = error {
+                        state.result = Some(Err(Error::other(error)));
+                    } else {
+                        let size = get_value_as_f64(&read_msg, &SIZE) as usize;
+                        let array_buffer = get_value(&read_msg, &BUF)
+                            .dyn_into::<ArrayBuffer>()
+                            .expect(DYN_INTO_ERROR);
+                        state.result = Some(Ok(ReadResult {
+                            buf: array_buffer,
+                            size,
+                        }));
+                    }
+                    if let Some(waker) = state.waker.take() {
+                        waker.wake();
+                    }
+                    return;
+                }
+                let write_msg = Reflect::get_u32(&received, InMsgType::Write as u32)
+                    .expect(GETTING_JS_FIELD_ERROR);
+                if !write_msg.is_undefined() {
+                    let index = get_value_as_f64(&write_msg, &INDEX) as usize;
+                    let task = inner_clone
+                        .borrow_mut()
+                        .writing_tasks
+                        .remove(index)
+                        .expect(ARENA_REMOVE_ERROR);
+                    let mut state = task.borrow_mut();
+
+                    if let Some(error) = error {
+                        state.result = Some(Err(Error::other(error)));
+                    } else {
+                        let size = get_value_as_f64(&write_msg, &SIZE) as usize;
+                        state.result = Some(Ok(size));
+                    }
+
+                    if let Some(waker) = state.waker.take() {
+                        waker.wake();
+                    }
+                    return;
+                }
+                let flush_msg = Reflect::get_u32(&received, InMsgType::Flush as u32)
+                    .expect(GETTING_JS_FIELD_ERROR);
+                if !flush_msg.is_undefined() {
+                    let index = get_value_as_f64(&flush_msg, &INDEX) as usize;
+                    let task = inner_clone
+                        .borrow_mut()
+                        .flushing_tasks
+                        .remove(index)
+                        .expect(ARENA_REMOVE_ERROR);
+                    let mut state = task.borrow_mut();
+
+                    if let Some(error) = error {
+                        state.result = Some(Err(Error::other(error)));
+                    } else {
+                        state.result = Some(Ok(()))
+                    }
+
+                    if let Some(waker) = state.waker.take() {
+                        waker.wake();
+                    }
+                    return;
+                }
+                let close_msg = Reflect::get_u32(&received, InMsgType::Close as u32)
+                    .expect(GETTING_JS_FIELD_ERROR);
+                if !close_msg.is_undefined() {
+                    let index = get_value_as_f64(&close_msg, &INDEX) as usize;
+                    let task = inner_clone
+                        .borrow_mut()
+                        .closing_tasks
+                        .remove(index)
+                        .expect(ARENA_REMOVE_ERROR);
+                    let mut state = task.borrow_mut();
+
+                    if let Some(error) = error {
+                        state.result = Some(Err(Error::other(error)));
+                    } else {
+                        state.result = Some(Ok(()))
+                    }
+
+                    if let Some(waker) = state.waker.take() {
+                        waker.wake();
+                    }
+                    return;
+                }
+                let truncate_msg = Reflect::get_u32(&received, InMsgType::Truncate as u32)
+                    .expect(GETTING_JS_FIELD_ERROR);
+                if !truncate_msg.is_undefined() {
+                    let index = get_value_as_f64(&truncate_msg, &INDEX) as usize;
+                    let task = inner_clone
+                        .borrow_mut()
+                        .truncating_tasks
+                        .remove(index)
+                        .expect(ARENA_REMOVE_ERROR);
+                    let mut state = task.borrow_mut();
+
+                    if let Some(error) = error {
+                        state.result = Some(Err(Error::other(error)));
+                    } else {
+                        state.result = Some(Ok(()))
+                    }
+
+                    if let Some(waker) = state.waker.take() {
+                        waker.wake();
+                    }
+                    return;
+                }
+                let copy_msg = Reflect::get_u32(&received, InMsgType::Copy as u32)
+                    .expect(GETTING_JS_FIELD_ERROR);
+                if !copy_msg.is_undefined() {
+                    let index = get_value_as_f64(&copy_msg, &INDEX) as usize;
+                    let task = inner_clone
+                        .borrow_mut()
+                        .copying_tasks
+                        .remove(index)
+                        .expect(ARENA_REMOVE_ERROR);
+                    let mut state = task.borrow_mut();
+
+                    if let Some(error) = error {
+                        state.result = Some(Err(Error::other(error)));
+                    } else {
+                        let size = get_value_as_f64(&copy_msg, &SIZE) as u64;
+                        state.result = Some(Ok(size));
+                    }
+
+                    if let Some(waker) = state.waker.take() {
+                        waker.wake();
+                    }
+                    return;
+                }
+                let probe_msg = Reflect::get_u32(&received, InMsgType::Probe as u32)
+                    .expect(GETTING_JS_FIELD_ERROR);
+                if !probe_msg.is_undefined() {
+                    let index = get_value_as_f64(&probe_msg, &INDEX) as usize;
+                    let task = inner_clone
+                        .borrow_mut()
+                        .probing_tasks
+                        .remove(index)
+                        .expect(ARENA_REMOVE_ERROR);
+                    let mut state = task.borrow_mut();
+
+                    if let Some(error) = error {
+                        state.result = Some(Err(Error::other(error)));
+                    } else {
+                        state.result = Some(Ok(()))
+                    }
+
+                    if let Some(waker) = state.waker.take() {
+                        waker.wake();
+                    }
+                    return;
+                }
+                let write_blob_msg = Reflect::get_u32(&received, InMsgType::WriteBlob as u32)
+                    .expect(GETTING_JS_FIELD_ERROR);
+                if !write_blob_msg.is_undefined() {
+                    let index = get_value_as_f64(&write_blob_msg, &INDEX) as usize;
+                    let task = inner_clone
+                        .borrow_mut()
+                        .writing_blob_tasks
+                        .remove(index)
+                        .expect(ARENA_REMOVE_ERROR);
+                    let mut state = task.borrow_mut();
+
+                    if let Some(error) = error {
+                        state.result = Some(Err(Error::other(error)));
+                    } else {
+                        let size = get_value_as_f64(&write_blob_msg, &SIZE) as u64;
+                        state.result = Some(Ok(size));
+                    }
+
+                    if let Some(waker) = state.waker.take() {
+                        waker.wake();
+                    }
+                    return;
+                }
+                let to_blob_msg = Reflect::get_u32(&received, InMsgType::ToBlob as u32)
+                    .expect(GETTING_JS_FIELD_ERROR);
+                if !to_blob_msg.is_undefined() {
+                    let index = get_value_as_f64(&to_blob_msg, &INDEX) as usize;
+                    let task = inner_clone
+                        .borrow_mut()
+                        .blob_tasks
+                        .remove(index)
+                        .expect(ARENA_REMOVE_ERROR);
+                    let mut state = task.borrow_mut();
+
+                    if let Some(error) = error {
+                        state.result = Some(Err(Error::other(error)));
+                    } else {
+                        let blob = get_value(&to_blob_msg, &BLOB)
+                            .dyn_into::<Blob>()
+                            .expect(DYN_INTO_ERROR);
+                        state.result = Some(Ok(blob));
+                    }
+
+                    if let Some(waker) = state.waker.take() {
+                        waker.wake();
+                    }
+                    return;
+                }
+                let batch_msg = Reflect::get_u32(&received, InMsgType::Batch as u32)
+                    .expect(GETTING_JS_FIELD_ERROR);
+                if !batch_msg.is_undefined() {
+                    let index = get_value_as_f64(&batch_msg, &INDEX) as usize;
+                    let task = inner_clone
+                        .borrow_mut()
+                        .batch_tasks
+                        .remove(index)
+                        .expect(ARENA_REMOVE_ERROR);
+                    let mut state = task.borrow_mut();
+
+                    // Errors are reported per operation.
+                    let results = get_value(&batch_msg, &RESULTS)
+                        .dyn_into::<Array>()
+                        .expect(DYN_INTO_ERROR);
+                    state.result = Some(Ok(results));
+
+                    if let Some(waker) = state.waker.take() {
+                        waker.wake();
+                    }
+                }
+            });
+        worker.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+        Self {
+            inner,
+            _closure: on_message,
+            worker,
+        }
+    }
+    pub(crate) fn drop_file(&self, fd: usize) {
+        let msg = Object::new();
+        let drop = Object::new();
+        set_value(&drop, &FD, &JsValue::from(fd));
+        set_value(&msg, &DROP, &drop);
+
+        self.worker.post_message(&msg).expect(POST_ERROR);
+    }
+}
+thread_local! {
+    pub(crate) static FS: RefCell<Fs> = RefCell::new(Fs::new());
+}
+
+impl Fs {
+    pub(crate) fn open(
+        &self,
+        handle: FileSystemFileHandle,
+        options: u8,
+        task: SharedTask<Result<(usize, u64)>>,
+    ) {
+        let index = self.inner.borrow_mut().opening_tasks.insert(task);
+
+        let open = Object::new();
+        set_value(&open, &INDEX, &JsValue::from(index));
+        set_value(&open, &HANDLE, &handle);
+        set_value(&open, &OPTIONS, &JsValue::from(options));
+        let msg = Object::new();
+        set_value(&msg, &OPEN, &open);
+
+        self.worker.post_message(&msg).expect(POST_ERROR);
+    }
+    pub(crate) fn read(
+        &self,
+        fd: usize,
+        size: usize,
+        cursor: u64,
+        task: SharedTask<Result<ReadResult>>,
+    ) {
+        let index = self.inner.borrow_mut().reading_tasks.insert(task);
+
+        let msg = Object::new();
+        let read = Object::new();
+        set_value(&read, &FD, &JsValue::from(fd));
+        set_value(&read, &SIZE, &JsValue::from(size));
+        set_value(&read, &INDEX, &JsValue::from(index));
+        set_value(&read, &CURSOR, &JsValue::from_f64(cursor as f64));
+        set_value(&msg, &READ, &read);
+
+        self.worker.post_message(&msg).unwrap()
+    }
+    pub(crate) fn write(
+        &self,
+        fd: usize,
+        buf: &[u8],
+        cursor: u64,
+        task: SharedTask<Result<usize>>,
+    ) {
+        let write_obj = Object::new();
+        let index = self.inner.borrow_mut().writing_tasks.insert(task);
+        let typed_array = Uint8Array::new_with_length(buf.len() as u32);
+        typed_array.copy_from(buf);
+
+        set_value(&write_obj, &INDEX, &JsValue::from(index));
+        set_value(&write_obj, &FD, &JsValue::from(fd));
+        set_value(&write_obj, &BUF, &typed_array.buffer());
+        set_value(&write_obj, &CURSOR, &JsValue::from_f64(cursor as f64));
+        let msg = Object::new();
+        set_value(&msg, &WRITE, &write_obj);
+
+        self.worker.post_message(&msg).expect(POST_ERROR);
+    }
+    pub(crate) fn flush(&self, fd: usize, task: SharedTask<Result<()>>) {
+        let index = self.inner.borrow_mut().flushing_tasks.insert(task);
+
+        let msg = Object::new();
+        let flust = Object::new();
+        set_value(&flust, &FD, &JsValue::from(fd));
+        set_value(&flust, &INDEX, &JsValue::from(index));
+        set_value(&msg, &FLUSH, &flust);
+
+        self.worker.post_message(&msg).expect(POST_ERROR);
+    }
+    pub(crate) fn close(&self, fd: usize, task: SharedTask<Result<()>>) {
+        let index = self.inner.borrow_mut().closing_tasks.insert(task);
+
+        let msg = Object::new();
+        let close = Object::new();
+        set_value(&close, &FD, &JsValue::from(fd));
+        set_value(&close, &INDEX, &JsValue::from(index));
+        set_value(&msg, &CLOSE, &close);
+
+        self.worker.post_message(&msg).expect(POST_ERROR);
+    }
+    pub(crate) fn truncate(&self, fd: usize, size: u64, task: SharedTask<Result<()>>) {
+        let index = self.inner.borrow_mut().truncating_tasks.insert(task);
+
+        let msg = Object::new();
+        let truncate = Object::new();
+        set_value(&truncate, &INDEX, &JsValue::from_f64(index as f64));
+        set_value(&truncate, &FD, &JsValue::from_f64(fd as f64));
+        set_value(&truncate, &SIZE, &JsValue::from_f64(size as f64));
+        set_value(&msg, &TRUNCATE, &truncate);
+
+        self.worker.post_message(&msg).unwrap();
+    }
+    pub(crate) fn copy(&self, src: usize, dst: usize, task: SharedTask<Result<u64>>) {
+        let index = self.inner.borrow_mut().copying_tasks.insert(task);
+
+        let msg = Object::new();
+        let copy = Object::new();
+        set_value(&copy, &INDEX, &JsValue::from(index));
+        set_value(&copy, &SRC, &JsValue::from(src));
+        set_value(&copy, &DST, &JsValue::from(dst));
+        set_value(&msg, &COPY, &copy);
+
+        self.worker.post_message(&msg).expect(POST_ERROR);
+    }
+    /// Posts `blob` to the worker, which reads it and writes it at `cursor`.
+    pub(crate) fn write_blob(
+        &self,
+        fd: usize,
+        blob: &Blob,
+        cursor: u64,
+        task: SharedTask<Result<u64>>,
+    ) {
+        let index = self.inner.borrow_mut().writing_blob_tasks.insert(task);
+
+        let msg = Object::new();
+        let write_blob = Object::new();
+        set_value(&write_blob, &INDEX, &JsValue::from(index));
+        set_value(&write_blob, &FD, &JsValue::from(fd));
+        set_value(&write_blob, &BLOB, blob);
+        set_value(&write_blob, &CURSOR, &JsValue::from_f64(cursor as f64));
+        set_value(&msg, &WRITE_BLOB, &write_blob);
+
+        self.worker.post_message(&msg).expect(POST_ERROR);
+    }
+    pub(crate) fn to_blob(&self, fd: usize, task: SharedTask<Result<Blob>>) {
+        let index = self.inner.borrow_mut().blob_tasks.insert(task);
+
+        let msg = Object::new();
+        let to_blob = Object::new();
+        set_value(&to_blob, &INDEX, &JsValue::from(index));
+        set_value(&to_blob, &FD, &JsValue::from(fd));
+        set_value(&msg, &TO_BLOB, &to_blob);
+
+        self.worker.post_message(&msg).expect(POST_ERROR);
+    }
+    /// Posts operations described by objects with a `kind`, run by the worker one after another.
+    pub(crate) fn batch(&self, ops: &Array, task: SharedTask<Result<Array>>) {
+        let index = self.inner.borrow_mut().batch_tasks.insert(task);
+
+        let msg = Object::new();
+        let batch = Object::new();
+        set_value(&batch, &INDEX, &JsValue::from(index));
+        set_value(&batch, &OPS, ops);
+        set_value(&msg, &BATCH, &batch);
+
+        self.worker.post_message(&msg).expect(POST_ERROR);
+    }
+    /// Asks the worker to open and close a sync access handle of `handle`.
+    ///
+    /// Fails without waiting for the worker if the handle can't be posted to it, as in Safari.
+    // Only needed to pick the default backend in the browser.
+    #[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+    pub(crate) fn probe(
+        &self,
+        handle: FileSystemFileHandle,
+        task: SharedTask<Result<()>>,
+    ) -> Result<()> {
+        let index = self.inner.borrow_mut().probing_tasks.insert(task);
+
+        let msg = Object::new();
+        let probe = Object::new();
+        set_value(&probe, &INDEX, &JsValue::from(index));
+        set_value(&probe, &HANDLE, &handle);
+        set_value(&msg, &PROBE, &probe);
+
+        self.worker.post_message(&msg).map_err(|e| {
+            self.inner.borrow_mut().probing_tasks.remove(index);
+            js_value_to_error(e)
+        })
+    }
+}
+
+/// A file opened by [`DirectFs`].
+struct DirectFile {
+    handle: FileSystemFileHandle,
+    write: bool,
+    /// Only set if the file is opened for writing, replaced on every flush.
+    /// Unset if reopening it failed, then the next write tries again.
+    writable: RefCell<Option<FileSystemWritableFileStream>>,
+}
+
+async fn create_writable(
+    handle: &FileSystemFileHandle,
+    keep_existing_data: bool,
+) -> Result<FileSystemWritableFileStream> {
+    let options = FileSystemCreateWritableOptions::new();
+    options.set_keep_existing_data(keep_existing_data);
+    Ok(
+        JsFuture::from(handle.create_writable_with_options(&options))
+            .await
+            .map_err(js_value_to_error)?
+            .unchecked_into(),
+    )
+}
+
+async fn wait(promise: std::result::Result<js_sys::Promise, JsValue>) -> Result<JsValue> {
+    JsFuture::from(promise.map_err(js_value_to_error)?)
+        .await
+        .map_err(js_value_to_error)
+}
+
+/// The data path used instead of the worker when it can't be, accessing files from the current thread.
+///
+/// Reads slice the `File` from `getFile()`, writes go through a `FileSystemWritableFileStream`.
+/// The stream writes to a swap file that replaces the file only when the stream is closed,
+/// so a flush closes the stream and opens a new one, and reads see the content as of the last flush.
+pub(crate) struct DirectFs {
+    opened: RefCell<Arena<Rc<DirectFile>>>,
+}
+
+impl DirectFs {
+    pub(crate) fn new() -> Self {
+        Self {
+            opened: RefCell::new(Arena::new()),
+        }
+    }
+    fn file(&self, fd: usize) -> Rc<DirectFile> {
+        self.opened
+            .borrow()
+            .get(fd)
+            .expect("Using a closed file descriptor, this is an error of the crate.")
+            .clone()
+    }
+    async fn writable(&self, fd: usize) -> Result<FileSystemWritableFileStream> {
+        let file = self.file(fd);
+        if !file.write {
+            return Err(Error::new(
+                ErrorKind::PermissionDenied,
+                "the file is not opened for writing",
+            ));
+        }
+        if let Some(writable) = file.writable.borrow().clone() {
+            return Ok(writable);
+        }
+        let writable = create_writable(&file.handle, true).await?;
+        *file.writable.borrow_mut() = Some(writable.clone());
+        Ok(writable)
+    }
+    pub(crate) async fn open(
+        &self,
+        handle: FileSystemFileHandle,
+        options: OpenOptions,
+    ) -> Result<(usize, u64)> {
+        let writable = if options.is_write() {
+            Some(create_writable(&handle, !options.is_truncate()).await?)
+        } else {
+            None
+        };
+        let size = if options.is_truncate() {
+            0
+        } else {
+            let file = JsFuture::from(handle.get_file())
+                .await
+                .map_err(js_value_to_error)?
+                .unchecked_into::<web_sys::File>();
+            file.size() as u64
+        };
+        let file = Rc::new(DirectFile {
+            handle,
+            write: options.is_write(),
+            writable: RefCell::new(writable),
+        });
+        Ok((self.opened.borrow_mut().insert(file), size))
+    }
+    pub(crate) async fn read(&self, fd: usize, cursor: u64, len: usize) -> Result<Vec<u8>> {
+        let handle = self.file(fd).handle.clone();
+        let file = JsFuture::from(handle.get_file())
+            .await
+            .map_err(js_value_to_error)?
+            .unchecked_into::<web_sys::File>();
+        let slice = file
+            .slice_with_f64_and_f64(cursor as f64, cursor as f64 + len as f64)
+            .map_err(js_value_to_error)?;
+        let buf = JsFuture::from(slice.array_buffer())
+            .await
+            .map_err(js_value_to_error)?;
+        Ok(Uint8Array::new(&buf).to_vec())
+    }
+    pub(crate) async fn write(&self, fd: usize, cursor: u64, buf: Vec<u8>) -> Result<usize> {
+        let writable = self.writable(fd).await?;
+        wait(writable.seek_with_f64(cursor as f64)).await?;
+        wait(writable.write_with_u8_array(&buf)).await?;
+        Ok(buf.len())
+    }
+    pub(crate) async fn write_blob(&self, fd: usize, cursor: u64, blob: Blob) -> Result<u64> {
+        let writable = self.writable(fd).await?;
+        wait(writable.seek_with_f64(cursor as f64)).await?;
+        wait(writable.write_with_blob(&blob)).await?;
+        Ok(blob.size() as u64)
+    }
+    /// The `File` from `getFile()`, which fails to be read once the file is changed.
+    pub(crate) async fn blob(&self, fd: usize) -> Result<Blob> {
+        let handle = self.file(fd).handle.clone();
+        Ok(JsFuture::from(handle.get_file())
+            .await
+            .map_err(js_value_to_error)?
+            .unchecked_into())
+    }
+    /// Commits the writes so far by closing the stream and reopening it.
+    ///
+    /// If closing fails the stream is kept, so later writes fail with its error.
+    pub(crate) async fn flush(&self, fd: usize) -> Result<()> {
+        let file = self.file(fd);
+        let Some(writable) = file.writable.borrow().clone() else {
+            return Ok(());
+        };
+        JsFuture::from(writable.close())
+            .await
+            .map_err(js_value_to_error)?;
+        // Until the new stream exists writes have to reopen it themselves.
+        file.writable.borrow_mut().take();
+        let writable = create_writable(&file.handle, true).await?;
+        *file.writable.borrow_mut() = Some(writable);
+        Ok(())
+    }
+    pub(crate) async fn truncate(&self, fd: usize, size: u64) -> Result<()> {
+        let writable = self.writable(fd).await?;
+        wait(writable.truncate_with_f64(size as f64)).await?;
+        Ok(())
+    }
+    pub(crate) async fn close(&self, fd: usize) -> Result<()> {
+        let writable = self.file(fd).writable.borrow_mut().take();
+        if let Some(writable) = writable {
+            JsFuture::from(writable.close())
+                .await
+                .map_err(js_value_to_error)?;
+        }
+        Ok(())
+    }
+    pub(crate) fn release(&self, fd: usize) {
+        let file = self.opened.borrow_mut().remove(fd);
+        // Dropping without closing still commits, as with the worker.
+        if let Some(writable) = file.and_then(|file| file.writable.borrow_mut().take()) {
+            let _ = writable.close();
+        }
+    }
+}
@@ -0,0 +1,787 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    io::{Error, ErrorKind, Result},
+    path::Path,
+    rc::Rc,
+    time::{Duration, SystemTime},
+};
+
+use futures_lite::future::BoxedLocal;
+use js_sys::{Array, Object, Promise, Reflect, Uint8Array};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    Event, IdbDatabase, IdbFactory, IdbKeyRange, IdbObjectStore, IdbRequest, IdbTransaction,
+    IdbTransactionMode,
+};
+
+use crate::{
+    Backend, FileType, GETTING_JS_FIELD_ERROR, Metadata, OpenOptions,
+    arena::Arena,
+    util::{js_value_to_error, now},
+};
+
+const DB_VERSION: u32 = 1;
+/// Maps a path to an [`Entry`].
+const ENTRIES: &str = "entries";
+/// Maps `[path, index]` to the `index`th [`CHUNK_SIZE`] bytes of the file at `path`.
+const CHUNKS: &str = "chunks";
+/// Every chunk but the last one of a file has exactly this size.
+const CHUNK_SIZE: u64 = 64 * 1024;
+
+const SETTING_JS_FIELD_ERROR: &str = "Setting js field error, this is an error of the crate.";
+
+/// Paths are stored as their components joined by `/`, the root is the empty string.
+fn key_of(path: &Path) -> String {
+    let components: Vec<_> = path.iter().map(|name| name.to_string_lossy()).collect();
+    components.join("/")
+}
+
+fn parent_of(key: &str) -> &str {
+    key.rsplit_once('/').map(|(parent, _)| parent).unwrap_or("")
+}
+
+fn chunk_key(key: &str, index: u64) -> JsValue {
+    Array::of2(&JsValue::from_str(key), &JsValue::from_f64(index as f64)).into()
+}
+
+/// The chunks of `key` from `from` on.
+fn chunk_range(key: &str, from: u64, to: f64) -> Result<IdbKeyRange> {
+    let lower = chunk_key(key, from);
+    let upper = Array::of2(&JsValue::from_str(key), &JsValue::from_f64(to));
+    IdbKeyRange::bound(&lower, &upper).map_err(js_value_to_error)
+}
+
+/// Everything below the directory `key`.
+fn descendant_range(key: &str) -> Result<Option<IdbKeyRange>> {
+    if key.is_empty() {
+        return Ok(None);
+    }
+    let lower = format!("{}/", key);
+    let upper = format!("{}/\u{ffff}", key);
+    IdbKeyRange::bound(&JsValue::from_str(&lower), &JsValue::from_str(&upper))
+        .map(Some)
+        .map_err(js_value_to_error)
+}
+
+fn millis(time: SystemTime) -> f64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as f64
+}
+
+#[derive(Clone, Copy)]
+struct Entry {
+    dir: bool,
+    size: u64,
+    modified: f64,
+}
+
+impl Entry {
+    fn file() -> Self {
+        Self {
+            dir: false,
+            size: 0,
+            modified: millis(now()),
+        }
+    }
+    fn dir() -> Self {
+        Self {
+            dir: true,
+            size: 0,
+            modified: millis(now()),
+        }
+    }
+    fn to_js(self) -> JsValue {
+        let object = Object::new();
+        for (name, value) in [
+            ("dir", JsValue::from_bool(self.dir)),
+            ("size", JsValue::from_f64(self.size as f64)),
+            ("modified", JsValue::from_f64(self.modified)),
+        ] {
+            Reflect::set(&object, &JsValue::from_str(name), &value).expect(SETTING_JS_FIELD_ERROR);
+        }
+        object.into()
+    }
+    fn from_js(value: &JsValue) -> Option<Self> {
+        if value.is_undefined() {
+            return None;
+        }
+        let field = |name: &str| {
+            Reflect::get(value, &JsValue::from_str(name)).expect(GETTING_JS_FIELD_ERROR)
+        };
+        Some(Self {
+            dir: field("dir").is_truthy(),
+            size: field("size").as_f64().unwrap_or(0.0) as u64,
+            modified: field("modified").as_f64().unwrap_or(0.0),
+        })
+    }
+    fn metadata(&self) -> Metadata {
+        if self.dir {
+            Metadata::new(FileType::Dir, 0, None)
+        } else {
+            let modified = SystemTime::UNIX_EPOCH + Duration::from_millis(self.modified as u64);
+            Metadata::new(FileType::File, self.size, Some(modified))
+        }
+    }
+}
+
+/// Resolves with the result of `request`.
+async fn wait(request: &IdbRequest) -> Result<JsValue> {
+    let promise = Promise::new(&mut |resolve, reject| {
+        let request_clone = request.clone();
+        // Exactly one of `success` and `error` fires, so the closure is always freed.
+        let callback = Closure::once_into_js(move |event: Event| {
+            if event.type_() == "success" {
+                let result = request_clone.result().unwrap_or(JsValue::UNDEFINED);
+                let _ = resolve.call1(&JsValue::UNDEFINED, &result);
+            } else {
+                let error = request_clone.error().ok().flatten();
+                let _ = reject.call1(&JsValue::UNDEFINED, &error.into());
+            }
+        });
+        request.set_onsuccess(Some(callback.unchecked_ref()));
+        request.set_onerror(Some(callback.unchecked_ref()));
+    });
+    JsFuture::from(promise).await.map_err(js_value_to_error)
+}
+
+/// Resolves once everything done in `transaction` is committed.
+async fn complete(transaction: &IdbTransaction) -> Result<()> {
+    let promise = Promise::new(&mut |resolve, reject| {
+        let transaction_clone = transaction.clone();
+        // Exactly one of `complete` and `abort` fires, an `error` is always followed by `abort`.
+        let callback = Closure::once_into_js(move |event: Event| {
+            if event.type_() == "complete" {
+                let _ = resolve.call0(&JsValue::UNDEFINED);
+            } else {
+                let error = transaction_clone.error();
+                let _ = reject.call1(&JsValue::UNDEFINED, &error.into());
+            }
+        });
+        transaction.set_oncomplete(Some(callback.unchecked_ref()));
+        transaction.set_onabort(Some(callback.unchecked_ref()));
+    });
+    JsFuture::from(promise).await.map(|_| ()).map_err(|e| {
+        if e.is_null() {
+            Error::other("the IndexedDB transaction was aborted")
+        } else {
+            js_value_to_error(e)
+        }
+    })
+}
+
+struct Stores {
+    transaction: IdbTransaction,
+    entries: IdbObjectStore,
+    chunks: IdbObjectStore,
+}
+
+impl Stores {
+    fn new(db: &IdbDatabase, mode: IdbTransactionMode) -> Result<Self> {
+        let names = Array::of2(&JsValue::from_str(ENTRIES), &JsValue::from_str(CHUNKS));
+        let transaction = db
+            .transaction_with_str_sequence_and_mode(&names, mode)
+            .map_err(js_value_to_error)?;
+        let entries = transaction
+            .object_store(ENTRIES)
+            .map_err(js_value_to_error)?;
+        let chunks = transaction
+            .object_store(CHUNKS)
+            .map_err(js_value_to_error)?;
+        Ok(Self {
+            transaction,
+            entries,
+            chunks,
+        })
+    }
+    async fn entry(&self, key: &str) -> Result<Option<Entry>> {
+        if key.is_empty() {
+            return Ok(Some(Entry::dir()));
+        }
+        let request = self
+            .entries
+            .get(&JsValue::from_str(key))
+            .map_err(js_value_to_error)?;
+        Ok(Entry::from_js(&wait(&request).await?))
+    }
+    fn put_entry(&self, key: &str, entry: Entry) -> Result<()> {
+        self.entries
+            .put_with_key(&entry.to_js(), &JsValue::from_str(key))
+            .map_err(js_value_to_error)?;
+        Ok(())
+    }
+    fn delete_entry(&self, key: &str) -> Result<()> {
+        self.entries
+            .delete(&JsValue::from_str(key))
+            .map_err(js_value_to_error)?;
+        Ok(())
+    }
+    /// Fails if the parent of `key` is not an existing directory.
+    async fn check_parent(&self, key: &str) -> Result<()> {
+        match self.entry(parent_of(key)).await? {
+            Some(entry) if entry.dir => Ok(()),
+            Some(_) => Err(Error::from(ErrorKind::NotADirectory)),
+            None => Err(Error::from(ErrorKind::NotFound)),
+        }
+    }
+    /// The keys and entries below the directory `key`.
+    async fn descendants(&self, key: &str) -> Result<Vec<(String, Entry)>> {
+        let (keys, values) = match descendant_range(key)? {
+            Some(range) => (
+                self.entries.get_all_keys_with_key(&range),
+                self.entries.get_all_with_key(&range),
+            ),
+            None => (self.entries.get_all_keys(), self.entries.get_all()),
+        };
+        let keys = keys.map_err(js_value_to_error)?;
+        let values = values.map_err(js_value_to_error)?;
+        let keys = Array::from(&wait(&keys).await?);
+        let values = Array::from(&wait(&values).await?);
+        Ok(keys
+            .iter()
+            .zip(values.iter())
+            .filter_map(|(key, value)| Some((key.as_string()?, Entry::from_js(&value)?)))
+            .collect())
+    }
+    async fn chunk(&self, key: &str, index: u64) -> Result<Vec<u8>> {
+        let request = self
+            .chunks
+            .get(&chunk_key(key, index))
+            .map_err(js_value_to_error)?;
+        let chunk = wait(&request).await?;
+        if chunk.is_undefined() {
+            Ok(Vec::new())
+        } else {
+            Ok(Uint8Array::new(&chunk).to_vec())
+        }
+    }
+    fn put_chunk(&self, key: &str, index: u64, data: &[u8]) -> Result<()> {
+        self.chunks
+            .put_with_key(&Uint8Array::from(data), &chunk_key(key, index))
+            .map_err(js_value_to_error)?;
+        Ok(())
+    }
+    /// Removes the chunks of `key` from `from` on.
+    fn delete_chunks(&self, key: &str, from: u64) -> Result<()> {
+        let range = chunk_range(key, from, f64::INFINITY)?;
+        self.chunks.delete(&range).map_err(js_value_to_error)?;
+        Ok(())
+    }
+    /// Writes `data` at `start` of the file `key` of `size` bytes, `start` must not be past `size`.
+    async fn write_at(&self, key: &str, size: u64, start: u64, data: &[u8]) -> Result<()> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        let end = start + data.len() as u64;
+        let first = start / CHUNK_SIZE;
+        let last = (end - 1) / CHUNK_SIZE;
+        let mut buf = Vec::with_capacity((end - first * CHUNK_SIZE) as usize);
+        // Keep the bytes around the written range in the first and last chunks.
+        let head = (start - first * CHUNK_SIZE) as usize;
+        if head > 0 {
+            buf.extend_from_slice(&self.chunk(key, first).await?[..head]);
+        }
+        buf.extend_from_slice(data);
+        if end < size {
+            let tail = self.chunk(key, last).await?;
+            let tail_start = (end - last * CHUNK_SIZE) as usize;
+            if tail.len() > tail_start {
+                buf.extend_from_slice(&tail[tail_start..]);
+            }
+        }
+        for (index, chunk) in (first..).zip(buf.chunks(CHUNK_SIZE as usize)) {
+            self.put_chunk(key, index, chunk)?;
+        }
+        Ok(())
+    }
+    /// Writes zeros from `size`, the current size of `key`, up to `end`, one chunk at a time.
+    async fn fill_zeros(&self, key: &str, size: u64, end: u64) -> Result<()> {
+        let zeros = vec![0; CHUNK_SIZE as usize];
+        let mut cursor = size;
+        while cursor < end {
+            let len = (CHUNK_SIZE - cursor % CHUNK_SIZE).min(end - cursor);
+            self.write_at(key, cursor, cursor, &zeros[..len as usize])
+                .await?;
+            cursor += len;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct Lock {
+    readers: usize,
+    writer: bool,
+}
+
+struct Opened {
+    key: String,
+    writable: bool,
+}
+
+struct IdbInner {
+    name: String,
+    db: RefCell<Option<IdbDatabase>>,
+    opened: RefCell<Arena<Opened>>,
+    locks: RefCell<HashMap<String, Lock>>,
+}
+
+fn busy_error() -> Error {
+    Error::new(
+        ErrorKind::PermissionDenied,
+        "the file is open, like with a locked sync access handle",
+    )
+}
+
+impl IdbInner {
+    async fn db(&self) -> Result<IdbDatabase> {
+        if let Some(db) = self.db.borrow().clone() {
+            return Ok(db);
+        }
+        let factory = Reflect::get(&js_sys::global(), &JsValue::from_str("indexedDB"))
+            .expect(GETTING_JS_FIELD_ERROR);
+        if factory.is_undefined() {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                "IndexedDB is not supported by this browser",
+            ));
+        }
+        let request = factory
+            .unchecked_into::<IdbFactory>()
+            .open_with_u32(&self.name, DB_VERSION)
+            .map_err(js_value_to_error)?;
+        let request_clone = request.clone();
+        let upgrade = Closure::once_into_js(move |_: Event| {
+            let db = request_clone
+                .result()
+                .expect(GETTING_JS_FIELD_ERROR)
+                .unchecked_into::<IdbDatabase>();
+            let _ = db.create_object_store(ENTRIES);
+            let _ = db.create_object_store(CHUNKS);
+        });
+        request.set_onupgradeneeded(Some(upgrade.unchecked_ref()));
+        let db = wait(&request).await?.unchecked_into::<IdbDatabase>();
+        *self.db.borrow_mut() = Some(db.clone());
+        Ok(db)
+    }
+    async fn stores(&self, mode: IdbTransactionMode) -> Result<Stores> {
+        Stores::new(&self.db().await?, mode)
+    }
+    fn opened(&self, fd: usize) -> (String, bool) {
+        let opened = self.opened.borrow();
+        let opened = opened
+            .get(fd)
+            .expect("Using a closed file descriptor, this is an error of the crate.");
+        (opened.key.clone(), opened.writable)
+    }
+    fn writable_key(&self, fd: usize) -> Result<String> {
+        match self.opened(fd) {
+            (key, true) => Ok(key),
+            (_, false) => Err(Error::new(
+                ErrorKind::PermissionDenied,
+                "the file is not opened for writing",
+            )),
+        }
+    }
+
+    // Several read-only handles or one writable handle, as with OPFS in one tab.
+    fn lock(&self, key: &str, writable: bool) -> Result<()> {
+        let mut locks = self.locks.borrow_mut();
+        let lock = locks.entry(key.to_string()).or_default();
+        if lock.writer || (writable && lock.readers > 0) {
+            return Err(busy_error());
+        }
+        if writable {
+            lock.writer = true;
+        } else {
+            lock.readers += 1;
+        }
+        Ok(())
+    }
+    fn unlock(&self, key: &str, writable: bool) {
+        let mut locks = self.locks.borrow_mut();
+        if let Some(lock) = locks.get_mut(key) {
+            if writable {
+                lock.writer = false;
+            } else {
+                lock.readers -= 1;
+            }
+            if !lock.writer && lock.readers == 0 {
+                locks.remove(key);
+            }
+        }
+    }
+    /// Whether `key` or anything below it is open.
+    fn is_busy(&self, key: &str) -> bool {
+        self.locks.borrow().keys().any(|locked| {
+            locked == key
+                || key.is_empty()
+                || (locked.starts_with(key) && locked[key.len()..].starts_with('/'))
+        })
+    }
+
+    async fn open(&self, key: String, options: OpenOptions) -> Result<(usize, u64)> {
+        if key.is_empty() {
+            return Err(Error::from(ErrorKind::IsADirectory));
+        }
+        if options.is_truncate() && !options.is_write() {
+            return Err(Error::from(ErrorKind::InvalidInput));
+        }
+        let writable = options.is_write();
+        self.lock(&key, writable)?;
+        match self.open_locked(&key, options).await {
+            Ok(size) => {
+                let fd = self.opened.borrow_mut().insert(Opened { key, writable });
+                Ok((fd, size))
+            }
+            Err(e) => {
+                self.unlock(&key, writable);
+                Err(e)
+            }
+        }
+    }
+    async fn open_locked(&self, key: &str, options: OpenOptions) -> Result<u64> {
+        let stores = self.stores(IdbTransactionMode::Readwrite).await?;
+        stores.check_parent(key).await?;
+        let mut entry = match stores.entry(key).await? {
+            Some(entry) if entry.dir => return Err(Error::from(ErrorKind::IsADirectory)),
+            Some(_) if options.is_create_new() => {
+                return Err(Error::from(ErrorKind::AlreadyExists));
+            }
+            Some(entry) => entry,
+            None if options.is_create() || options.is_create_new() => {
+                let entry = Entry::file();
+                stores.put_entry(key, entry)?;
+                entry
+            }
+            None => return Err(Error::from(ErrorKind::NotFound)),
+        };
+        if options.is_truncate() && entry.size > 0 {
+            entry.size = 0;
+            entry.modified = millis(now());
+            stores.delete_chunks(key, 0)?;
+            stores.put_entry(key, entry)?;
+        }
+        complete(&stores.transaction).await?;
+        Ok(entry.size)
+    }
+    async fn read(&self, fd: usize, cursor: u64, len: usize) -> Result<Vec<u8>> {
+        let (key, _) = self.opened(fd);
+        let stores = self.stores(IdbTransactionMode::Readonly).await?;
+        let size = stores
+            .entry(&key)
+            .await?
+            .ok_or(Error::from(ErrorKind::NotFound))?
+            .size;
+        let end = cursor.saturating_add(len as u64).min(size);
+        if cursor >= end {
+            return Ok(Vec::new());
+        }
+        let first = cursor / CHUNK_SIZE;
+        let last = (end - 1) / CHUNK_SIZE;
+        let range = chunk_range(&key, first, last as f64)?;
+        let request = stores
+            .chunks
+            .get_all_with_key(&range)
+            .map_err(js_value_to_error)?;
+        let chunks = Array::from(&wait(&request).await?);
+        let mut buf = Vec::with_capacity((end - first * CHUNK_SIZE) as usize);
+        for chunk in chunks.iter() {
+            buf.extend_from_slice(&Uint8Array::new(&chunk).to_vec());
+        }
+        let start = (cursor - first * CHUNK_SIZE) as usize;
+        let end = ((end - first * CHUNK_SIZE) as usize).min(buf.len());
+        Ok(buf[start.min(end)..end].to_vec())
+    }
+    async fn write(&self, fd: usize, cursor: u64, data: Vec<u8>) -> Result<usize> {
+        let key = self.writable_key(fd)?;
+        let stores = self.stores(IdbTransactionMode::Readwrite).await?;
+        let mut entry = stores
+            .entry(&key)
+            .await?
+            .ok_or(Error::from(ErrorKind::NotFound))?;
+        // Writing past the end fills the gap with zeros, so chunks are never missing.
+        if cursor > entry.size {
+            stores.fill_zeros(&key, entry.size, cursor).await?;
+            entry.size = cursor;
+        }
+        stores.write_at(&key, entry.size, cursor, &data).await?;
+        entry.size = entry.size.max(cursor + data.len() as u64);
+        entry.modified = millis(now());
+        stores.put_entry(&key, entry)?;
+        complete(&stores.transaction).await?;
+        Ok(data.len())
+    }
+    async fn truncate(&self, fd: usize, size: u64) -> Result<()> {
+        let key = self.writable_key(fd)?;
+        let stores = self.stores(IdbTransactionMode::Readwrite).await?;
+        let mut entry = stores
+            .entry(&key)
+            .await?
+            .ok_or(Error::from(ErrorKind::NotFound))?;
+        if size < entry.size {
+            stores.delete_chunks(&key, size.div_ceil(CHUNK_SIZE))?;
+            let kept = (size % CHUNK_SIZE) as usize;
+            if kept > 0 {
+                let index = size / CHUNK_SIZE;
+                let chunk = stores.chunk(&key, index).await?;
+                stores.put_chunk(&key, index, &chunk[..kept.min(chunk.len())])?;
+            }
+        } else {
+            stores.fill_zeros(&key, entry.size, size).await?;
+        }
+        entry.size = size;
+        entry.modified = millis(now());
+        stores.put_entry(&key, entry)?;
+        complete(&stores.transaction).await
+    }
+    async fn stat(&self, key: String) -> Result<Metadata> {
+        let stores = self.stores(IdbTransactionMode::Readonly).await?;
+        match stores.entry(&key).await? {
+            Some(entry) => Ok(entry.metadata()),
+            None => Err(Error::from(ErrorKind::NotFound)),
+        }
+    }
+    async fn list(&self, key: String) -> Result<Vec<(String, FileType)>> {
+        let stores = self.stores(IdbTransactionMode::Readonly).await?;
+        match stores.entry(&key).await? {
+            Some(entry) if entry.dir => (),
+            Some(_) => return Err(Error::from(ErrorKind::NotADirectory)),
+            None => return Err(Error::from(ErrorKind::NotFound)),
+        }
+        let prefix_len = if key.is_empty() { 0 } else { key.len() + 1 };
+        Ok(stores
+            .descendants(&key)
+            .await?
+            .into_iter()
+            .filter_map(|(child, entry)| {
+                let name = &child[prefix_len..];
+                if name.contains('/') {
+                    return None;
+                }
+                let file_type = if entry.dir {
+                    FileType::Dir
+                } else {
+                    FileType::File
+                };
+                Some((name.to_string(), file_type))
+            })
+            .collect())
+    }
+    async fn create_dir(&self, key: String, recursive: bool) -> Result<()> {
+        if key.is_empty() {
+            return Ok(());
+        }
+        let stores = self.stores(IdbTransactionMode::Readwrite).await?;
+        if recursive {
+            let mut ancestor = String::new();
+            for name in key.split('/') {
+                if !ancestor.is_empty() {
+                    ancestor.push('/');
+                }
+                ancestor.push_str(name);
+                match stores.entry(&ancestor).await? {
+                    Some(entry) if entry.dir => (),
+                    Some(_) => return Err(Error::from(ErrorKind::NotADirectory)),
+                    None => stores.put_entry(&ancestor, Entry::dir())?,
+                }
+            }
+        } else {
+            stores.check_parent(&key).await?;
+            match stores.entry(&key).await? {
+                Some(entry) if entry.dir => (),
+                Some(_) => return Err(Error::from(ErrorKind::AlreadyExists)),
+                None => stores.put_entry(&key, Entry::dir())?,
+            }
+        }
+        complete(&stores.transaction).await
+    }
+    async fn remove(&self, key: String, recursive: bool) -> Result<()> {
+        if key.is_empty() {
+            return Err(Error::new(
+                ErrorKind::PermissionDenied,
+                "the root can't be removed",
+            ));
+        }
+        let stores = self.stores(IdbTransactionMode::Readwrite).await?;
+        let entry = stores
+            .entry(&key)
+            .await?
+            .ok_or(Error::from(ErrorKind::NotFound))?;
+        if self.is_busy(&key) {
+            return Err(busy_error());
+        }
+        if entry.dir {
+            let descendants = stores.descendants(&key).await?;
+            if !descendants.is_empty() && !recursive {
+                return Err(Error::from(ErrorKind::DirectoryNotEmpty));
+            }
+            for (descendant, entry) in descendants {
+                stores.delete_entry(&descendant)?;
+                if !entry.dir {
+                    stores.delete_chunks(&descendant, 0)?;
+                }
+            }
+        } else {
+            stores.delete_chunks(&key, 0)?;
+        }
+        stores.delete_entry(&key)?;
+        complete(&stores.transaction).await
+    }
+    async fn rename(&self, from: String, to: String) -> Result<()> {
+        if from.is_empty() || to.is_empty() {
+            return Err(Error::new(
+                ErrorKind::PermissionDenied,
+                "the root can't be moved",
+            ));
+        }
+        if to.starts_with(&from) && to[from.len()..].starts_with('/') {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "can't move a directory into itself",
+            ));
+        }
+        let stores = self.stores(IdbTransactionMode::Readwrite).await?;
+        let entry = stores
+            .entry(&from)
+            .await?
+            .ok_or(Error::from(ErrorKind::NotFound))?;
+        if from == to {
+            return Ok(());
+        }
+        if self.is_busy(&from) || self.is_busy(&to) {
+            return Err(busy_error());
+        }
+        stores.check_parent(&to).await?;
+        match stores.entry(&to).await? {
+            Some(existing) if existing.dir => return Err(Error::from(ErrorKind::IsADirectory)),
+            Some(_) => stores.delete_chunks(&to, 0)?,
+            None => (),
+        }
+        let mut moved = vec![(from.clone(), entry)];
+        if entry.dir {
+            moved.extend(stores.descendants(&from).await?);
+        }
+        for (old, entry) in moved {
+            let new = format!("{}{}", to, &old[from.len()..]);
+            stores.delete_entry(&old)?;
+            stores.put_entry(&new, entry)?;
+            if !entry.dir {
+                let range = chunk_range(&old, 0, f64::INFINITY)?;
+                let request = stores
+                    .chunks
+                    .get_all_with_key(&range)
+                    .map_err(js_value_to_error)?;
+                let chunks = Array::from(&wait(&request).await?);
+                stores.delete_chunks(&old, 0)?;
+                for (index, chunk) in chunks.iter().enumerate() {
+                    stores
+                        .chunks
+                        .put_with_key(&chunk, &chunk_key(&new, index as u64))
+                        .map_err(js_value_to_error)?;
+                }
+            }
+        }
+        complete(&stores.transaction).await
+    }
+}
+
+/// A [`Backend`] storing files in *IndexedDB*, for browsers where [`OpfsBackend`](crate::OpfsBackend) can't work.
+///
+/// It is selected automatically when neither a `FileSystemSyncAccessHandle` in the worker
+/// nor a `FileSystemWritableFileStream` in the current thread can be used,
+/// for example in older Safari where a `FileSystemFileHandle` can't be posted to a worker.
+/// File content is stored in chunks of 64 KiB next to an index of the entries.
+/// Every write is committed in its own transaction, so [`flush`](futures_lite::AsyncWriteExt::flush) has nothing to do.
+///
+/// Files are locked like in OPFS, but only within one tab or worker.
+pub struct IdbBackend {
+    inner: Rc<IdbInner>,
+}
+
+impl IdbBackend {
+    /// Uses the database named `web-fs`.
+    pub fn new() -> Self {
+        Self::with_name("web-fs")
+    }
+    /// Uses the database named `name`, so that separate file systems can coexist.
+    pub fn with_name(name: &str) -> Self {
+        Self {
+            inner: Rc::new(IdbInner {
+                name: name.to_string(),
+                db: RefCell::new(None),
+                opened: RefCell::new(Arena::new()),
+                locks: RefCell::new(HashMap::new()),
+            }),
+        }
+    }
+}
+
+impl Default for IdbBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Backend for IdbBackend {
+    fn open(&self, path: &Path, options: &OpenOptions) -> BoxedLocal<Result<(usize, u64)>> {
+        let inner = self.inner.clone();
+        let (key, options) = (key_of(path), *options);
+        Box::pin(async move { inner.open(key, options).await })
+    }
+    fn read(&self, fd: usize, cursor: u64, len: usize) -> BoxedLocal<Result<Vec<u8>>> {
+        let inner = self.inner.clone();
+        Box::pin(async move { inner.read(fd, cursor, len).await })
+    }
+    fn concurrent_reads(&self) -> bool {
+        true
+    }
+    fn write(&self, fd: usize, cursor: u64, buf: &[u8]) -> BoxedLocal<Result<usize>> {
+        let inner = self.inner.clone();
+        let buf = buf.to_vec();
+        Box::pin(async move { inner.write(fd, cursor, buf).await })
+    }
+    fn flush(&self, _fd: usize) -> BoxedLocal<Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+    fn truncate(&self, fd: usize, size: u64) -> BoxedLocal<Result<()>> {
+        let inner = self.inner.clone();
+        Box::pin(async move { inner.truncate(fd, size).await })
+    }
+    fn close(&self, _fd: usize) -> BoxedLocal<Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+    fn release(&self, fd: usize) {
+        let opened = self.inner.opened.borrow_mut().remove(fd);
+        if let Some(opened) = opened {
+            self.inner.unlock(&opened.key, opened.writable);
+        }
+    }
+    fn stat(&self, path: &Path) -> BoxedLocal<Result<Metadata>> {
+        let inner = self.inner.clone();
+        let key = key_of(path);
+        Box::pin(async move { inner.stat(key).await })
+    }
+    fn list(&self, path: &Path) -> BoxedLocal<Result<Vec<(String, FileType)>>> {
+        let inner = self.inner.clone();
+        let key = key_of(path);
+        Box::pin(async move { inner.list(key).await })
+    }
+    fn create_dir(&self, path: &Path, recursive: bool) -> BoxedLocal<Result<()>> {
+        let inner = self.inner.clone();
+        let key = key_of(path);
+        Box::pin(async move { inner.create_dir(key, recursive).await })
+    }
+    fn remove(&self, path: &Path, recursive: bool) -> BoxedLocal<Result<()>> {
+        let inner = self.inner.clone();
+        let key = key_of(path);
+        Box::pin(async move { inner.remove(key, recursive).await })
+    }
+    fn rename(&self, from: &Path, to: &Path) -> BoxedLocal<Result<()>> {
+        let inner = self.inner.clone();
+        let (from, to) = (key_of(from), key_of(to));
+        Box::pin(async move { inner.rename(from, to).await })
+    }
+}
@@ -1,373 +1,89 @@
 #![doc = include_str!("../README.md")]
 
-use wasm_bindgen_futures::{JsFuture, stream::JsStream};
-
 mod c_static_str;
 pub(crate) use c_static_str::*;
 mod open_options;
-use arena::Arena;
-use js_sys::{ArrayBuffer, Object, Reflect};
 pub use open_options::{OpenFileFuture, OpenOptions};
-use read::ReadResult;
-use util::{Task, get_value, get_value_as_f64, js_value_to_error, set_value};
+use util::normalize;
+mod archive;
 mod arena;
+mod atomic;
+mod backend;
+mod batch;
+mod blob;
+mod buf_file;
+mod dir_cache;
 mod file;
+mod fs;
+mod idb;
+mod locks;
+mod memory;
+mod mount;
+#[cfg(not(target_arch = "wasm32"))]
+mod native;
+mod opfs;
+mod overlay;
 mod read;
+mod read_ahead;
+mod seed;
 mod seek;
+#[cfg(feature = "tokio")]
+mod tokio_io;
+mod web_stream;
 mod write;
+pub use archive::{ArchiveFormat, ArchiveReader, export_dir, import_archive};
+pub use atomic::{AtomicFile, remove_stale_atomic_files, remove_stale_files, write_atomic};
+use backend::backend;
+pub use backend::{Backend, set_backend};
+pub use batch::{Batch, BatchOp, BatchOutput};
+pub use blob::{ObjectUrl, blob, object_url, write_blob};
+pub use buf_file::BufFile;
 pub use file::{File, TruncateFuture};
+pub use idb::IdbBackend;
+pub use memory::MemoryBackend;
+#[cfg(not(target_arch = "wasm32"))]
+pub use native::NativeBackend;
+pub use opfs::OpfsBackend;
+pub use overlay::{OverlayBackend, WHITEOUT_PREFIX};
+pub use read_ahead::{ReadAhead, lines};
 mod metadata;
+mod temp;
+mod tree;
 mod util;
+mod watch;
 pub use metadata::*;
+pub use mount::{MountOptions, mount, unmount};
+pub use seed::{SEED_MANIFEST, SeedOptions, seed};
+pub use temp::{TEMP_DIR, TempDir, TempFile, remove_stale_temp_files, tempdir, tempfile};
+pub use tree::{CopyDirOptions, CopyProgress, copy_dir_all, remove_dir_contents};
+pub use watch::{
+    CreateKind, DataChange, Event, EventKind, ModifyKind, POLL_INTERVAL, RemoveKind, RenameMode,
+    Watcher, watch, watch_polling,
+};
+pub use web_stream::{StreamReader, StreamWriter, readable_stream, writable_stream, write_stream};
 
 use std::{
-    cell::RefCell,
     ffi::OsString,
     io::{Error, ErrorKind, Result},
-    path::{Component, Path, PathBuf},
+    path::{Path, PathBuf},
     rc::Rc,
 };
 
-use futures_lite::{AsyncReadExt, AsyncWriteExt, Stream, StreamExt};
-use wasm_bindgen::prelude::*;
-use web_sys::{
-    FileSystemDirectoryHandle, FileSystemFileHandle, FileSystemGetDirectoryOptions,
-    FileSystemGetFileOptions, FileSystemRemoveOptions, MessageEvent, Worker, WorkerGlobalScope,
-    window,
-};
+use futures_lite::{AsyncReadExt, AsyncWriteExt, Stream, stream};
 
 const GETTING_JS_FIELD_ERROR: &str = "Getting js field error, this is an error of the crate.";
 const ARENA_REMOVE_ERROR: &str = "Removing from arena error, this is an error of the crate.";
 const DYN_INTO_ERROR: &str = "Converting js type failed, this is an error of the crate.";
 const POST_ERROR: &str = "Posting message to worker failed, this is an error of the crate";
 
-struct FsInner {
-    opening_tasks: Arena<Rc<RefCell<Task<Result<File>>>>>,
-    reading_tasks: Arena<Rc<RefCell<Task<Result<ReadResult>>>>>,
-    writing_tasks: Arena<Rc<RefCell<Task<Result<usize>>>>>,
-    flushing_tasks: Arena<Rc<RefCell<Task<Result<()>>>>>,
-    closing_tasks: Arena<Rc<RefCell<Task<Result<()>>>>>,
-    truncating_tasks: Arena<Rc<RefCell<Task<Result<()>>>>>,
-}
-struct Fs {
-    inner: Rc<RefCell<FsInner>>,
-    _closure: Closure<dyn FnMut(MessageEvent)>,
-    worker: Worker,
-}
-impl Fs {
-    fn new() -> Self {
-        let worker = Worker::new(&wasm_bindgen::link_to!(module = "/src/worker.js"))
-            .expect("Creating web worker failed. This crate relies on web worker to work.");
-
-        let inner = FsInner {
-            opening_tasks: Arena::new(),
-            reading_tasks: Arena::new(),
-            writing_tasks: Arena::new(),
-            flushing_tasks: Arena::new(),
-            closing_tasks: Arena::new(),
-            truncating_tasks: Arena::new(),
-        };
-        let inner = Rc::new(RefCell::new(inner));
-        let inner_clone = inner.clone();
-        #[repr(u32)]
-        enum InMsgType {
-            Open = 0,
-            Read,
-            Write,
-            Flush,
-            Close,
-            Truncate,
-        }
-        let on_message: Closure<dyn FnMut(MessageEvent)> =
-            Closure::new(move |msg: MessageEvent| {
-                let received = msg.data();
-                let error = get_value(&received, &ERROR);
-                let error = if !error.is_undefined() {
-                    Some(error.as_string()).expect(
-                        "Converting js error to string failed, this is an error of the crate.",
-                    )
-                } else {
-                    None
-                };
-
-                let open_msg = Reflect::get_u32(&received, InMsgType::Open as u32)
-                    .expect(GETTING_JS_FIELD_ERROR);
-                if !open_msg.is_undefined() {
-                    let index = get_value_as_f64(&open_msg, &INDEX) as usize;
-                    let task = inner_clone
-                        .borrow_mut()
-                        .opening_tasks
-                        .remove(index)
-                        .expect(ARENA_REMOVE_ERROR);
-                    let mut state = task.borrow_mut();
-                    if let Some(error) = error {
-                        state.result = Some(Err(Error::other(error)));
-                    } else {
-                        let fd = get_value_as_f64(&open_msg, &FD) as usize;
-                        let size = get_value_as_f64(&open_msg, &SIZE) as u64;
-                        state.result = Some(Ok(File::new(fd, size)));
-                    }
-                    if let Some(waker) = state.waker.take() {
-                        waker.wake();
-                    }
-                    return;
-                }
-                let read_msg = Reflect::get_u32(&received, InMsgType::Read as u32)
-                    .expect(GETTING_JS_FIELD_ERROR);
-                if !read_msg.is_undefined() {
-                    let index = get_value_as_f64(&read_msg, &INDEX) as usize;
-                    let task = inner_clone
-                        .borrow_mut()
-                        .reading_tasks
-                        .remove(index)
-                        .expect(ARENA_REMOVE_ERROR);
-                    let mut state = task.borrow_mut();
-                    if let Some(error) = error {
-                        state.result = Some(Err(Error::other(error)));
-                    } else {
-                        let size = get_value_as_f64(&read_msg, &SIZE) as usize;
-                        let array_buffer = get_value(&read_msg, &BUF)
-                            .dyn_into::<ArrayBuffer>()
-                            .expect(DYN_INTO_ERROR);
-                        state.result = Some(Ok(ReadResult {
-                            buf: array_buffer,
-                            size,
-                        }));
-                    }
-                    if let Some(waker) = state.waker.take() {
-                        waker.wake();
-                    }
-                    return;
-                }
-                let write_msg = Reflect::get_u32(&received, InMsgType::Write as u32)
-                    .expect(GETTING_JS_FIELD_ERROR);
-                if !write_msg.is_undefined() {
-                    let index = get_value_as_f64(&write_msg, &INDEX) as usize;
-                    let task = inner_clone
-                        .borrow_mut()
-                        .writing_tasks
-                        .remove(index)
-                        .expect(ARENA_REMOVE_ERROR);
-                    let mut state = task.borrow_mut();
-
-                    if let Some(error) = error {
-                        state.result = Some(Err(Error::other(error)));
-                    } else {
-                        let size = get_value_as_f64(&write_msg, &SIZE) as usize;
-                        state.result = Some(Ok(size));
-                    }
-
-                    if let Some(waker) = state.waker.take() {
-                        waker.wake();
-                    }
-                    return;
-                }
-                let flush_msg = Reflect::get_u32(&received, InMsgType::Flush as u32)
-                    .expect(GETTING_JS_FIELD_ERROR);
-                if !flush_msg.is_undefined() {
-                    let index = get_value_as_f64(&flush_msg, &INDEX) as usize;
-                    let task = inner_clone
-                        .borrow_mut()
-                        .flushing_tasks
-                        .remove(index)
-                        .expect(ARENA_REMOVE_ERROR);
-                    let mut state = task.borrow_mut();
-
-                    if let Some(error) = error {
-                        state.result = Some(Err(Error::other(error)));
-                    } else {
-                        state.result = Some(Ok(()))
-                    }
-
-                    if let Some(waker) = state.waker.take() {
-                        waker.wake();
-                    }
-                    return;
-                }
-                let close_msg = Reflect::get_u32(&received, InMsgType::Close as u32)
-                    .expect(GETTING_JS_FIELD_ERROR);
-                if !close_msg.is_undefined() {
-                    let index = get_value_as_f64(&close_msg, &INDEX) as usize;
-                    let task = inner_clone
-                        .borrow_mut()
-                        .closing_tasks
-                        .remove(index)
-                        .expect(ARENA_REMOVE_ERROR);
-                    let mut state = task.borrow_mut();
-
-                    if let Some(error) = error {
-                        state.result = Some(Err(Error::other(error)));
-                    } else {
-                        state.result = Some(Ok(()))
-                    }
-
-                    if let Some(waker) = state.waker.take() {
-                        waker.wake();
-                    }
-                    return;
-                }
-                let truncate_msg = Reflect::get_u32(&received, InMsgType::Truncate as u32)
-                    .expect(GETTING_JS_FIELD_ERROR);
-                if !truncate_msg.is_undefined() {
-                    let index = get_value_as_f64(&truncate_msg, &INDEX) as usize;
-                    let task = inner_clone
-                        .borrow_mut()
-                        .truncating_tasks
-                        .remove(index)
-                        .expect(ARENA_REMOVE_ERROR);
-                    let mut state = task.borrow_mut();
-
-                    if let Some(error) = error {
-                        state.result = Some(Err(Error::other(error)));
-                    } else {
-                        state.result = Some(Ok(()))
-                    }
-
-                    if let Some(waker) = state.waker.take() {
-                        waker.wake();
-                    }
-                    return;
-                }
-            });
-        worker.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
-        Self {
-            inner,
-            _closure: on_message,
-            worker,
-        }
-    }
-    fn drop_file(&self, fd: usize) {
-        let msg = Object::new();
-        let drop = Object::new();
-        set_value(&drop, &FD, &JsValue::from(fd));
-        set_value(&msg, &DROP, &drop);
-
-        self.worker.post_message(&msg).expect(POST_ERROR);
-    }
-}
-thread_local! {
-    static FS: RefCell<Fs> = RefCell::new(Fs::new());
-}
-
-async fn get_root() -> Result<FileSystemDirectoryHandle> {
-    let storage = if let Some(window) = window() {
-        let navigator = window.navigator();
-        navigator.storage()
-    } else if js_sys::global().is_instance_of::<WorkerGlobalScope>() {
-        let global = js_sys::global().unchecked_into::<WorkerGlobalScope>();
-        global.navigator().storage()
-    } else {
-        return Err(std::io::Error::new(
-            std::io::ErrorKind::Unsupported,
-            "unable to access browser storage",
-        ));
-    };
-    JsFuture::from(storage.get_directory())
-        .await
-        .map_err(|_| {
-            std::io::Error::new(
-                std::io::ErrorKind::Unsupported,
-                "unable to get root directory",
-            )
-        })?
-        .dyn_into::<FileSystemDirectoryHandle>()
-        .map_err(|_| std::io::Error::new(std::io::ErrorKind::Unsupported, DYN_INTO_ERROR))
-}
-
-async fn child_dir(
-    parent: &FileSystemDirectoryHandle,
-    name: &str,
-    create: bool,
-) -> Result<FileSystemDirectoryHandle> {
-    let options = FileSystemGetDirectoryOptions::new();
-    options.set_create(create);
-    let result = JsFuture::from(parent.get_directory_handle_with_options(name, &options))
-        .await
-        .map_err(|e| js_value_to_error(e))?
-        .dyn_into::<FileSystemDirectoryHandle>()
-        .expect(DYN_INTO_ERROR);
-    Ok(result)
-}
-
-async fn child_file(
-    parent: &FileSystemDirectoryHandle,
-    name: &str,
-    create: bool,
-) -> Result<FileSystemFileHandle> {
-    let options = FileSystemGetFileOptions::new();
-    options.set_create(create);
-    let result = JsFuture::from(parent.get_file_handle_with_options(name, &options))
-        .await
-        .map_err(|e| js_value_to_error(e))?
-        .dyn_into::<FileSystemFileHandle>()
-        .expect(DYN_INTO_ERROR);
-    Ok(result)
-}
-
-async fn get_parent_dir<P: AsRef<Path>>(
-    path: P,
-    create: bool,
-) -> Result<FileSystemDirectoryHandle> {
-    let path = path.as_ref();
-    let root = get_root().await?;
-    let mut parents_stack = vec![root];
-    if let Some(path) = path.parent() {
-        for component in path.components() {
-            match component {
-                // Browser can't access system root, so this is PermissionDenied.
-                Component::Prefix(_) => return Err(Error::from(ErrorKind::PermissionDenied)),
-                Component::CurDir | Component::RootDir => (),
-                Component::ParentDir => {
-                    // Accessing the parent of the root is also not allowed.
-                    if parents_stack.len() == 1 {
-                        return Err(Error::from(ErrorKind::PermissionDenied));
-                    } else {
-                        parents_stack.pop();
-                    }
-                }
-                Component::Normal(name) => {
-                    let name = name.to_string_lossy();
-                    parents_stack.push(
-                        child_dir(parents_stack.last().as_ref().unwrap(), &name, create).await?,
-                    );
-                }
-            }
-        }
-    }
-    Ok(parents_stack.pop().unwrap())
-}
-
-async fn get_dir<P: AsRef<Path>>(
-    path: P,
-    create: bool,
-    create_parents: bool,
-) -> Result<FileSystemDirectoryHandle> {
-    let parent_dir = get_parent_dir(&path, create_parents).await?;
-    if let Some(name) = path.as_ref().file_name() {
-        let name = name.to_string_lossy();
-        child_dir(&parent_dir, &name, create).await
-    } else {
-        Ok(parent_dir)
-    }
-}
-
-async fn get_file<P: AsRef<Path>>(path: P, create: bool) -> Result<FileSystemFileHandle> {
-    let parent_dir = get_parent_dir(&path, false).await?;
-    if let Some(name) = path.as_ref().file_name() {
-        let name = name.to_string_lossy();
-        child_file(&parent_dir, &name, create).await
-    } else {
-        Err(Error::from(ErrorKind::AlreadyExists))
-    }
-}
+/// Size of the buffer used when copying between files of different backends.
+const COPY_BUF_SIZE: u64 = 1 << 20;
 
 pub async fn create_dir<P: AsRef<Path>>(path: P) -> Result<()> {
-    get_dir(path, true, false).await?;
-    Ok(())
+    backend().await.create_dir(&normalize(path)?, false).await
 }
 pub async fn create_dir_all<P: AsRef<Path>>(path: P) -> Result<()> {
-    get_dir(path, true, true).await?;
-    Ok(())
+    backend().await.create_dir(&normalize(path)?, true).await
 }
 
 /// Symlink is not supported.
@@ -402,9 +118,8 @@ impl DirEntry {
     pub async fn file_type(&self) -> Result<FileType> {
         Ok(self.file_type)
     }
-    /// Currently not supported.
-    pub async fn metadata(&self) -> Result<std::fs::Metadata> {
-        Err(Error::other("Metadata is not supported currently"))
+    pub async fn metadata(&self) -> Result<Metadata> {
+        metadata(&self.path).await
     }
     pub fn path(&self) -> PathBuf {
         self.path.clone()
@@ -412,52 +127,22 @@ impl DirEntry {
 }
 
 pub async fn read_dir<P: AsRef<Path>>(path: P) -> Result<impl Stream<Item = Result<DirEntry>>> {
-    let dir = get_dir(&path, false, false).await?;
-    let stream = JsStream::from(dir.entries());
-    let read_dir = stream.map(move |v| {
-        let entry = v.map_err(|e| js_value_to_error(e))?;
-        const RESOLVE_ENTRY_ERROR: &str =
-            "Getting the key and value of the dir entry failed, this is an error of the crate.";
-        let key = Reflect::get_u32(&entry, 0)
-            .expect(RESOLVE_ENTRY_ERROR)
-            .as_string()
-            .expect("This is supposed to be a string, else this is an error of the crate.");
-        let value = Reflect::get_u32(&entry, 1).expect(RESOLVE_ENTRY_ERROR);
-
+    let entries = backend().await.list(&normalize(&path)?).await?;
+    let read_dir = entries.into_iter().map(move |(name, file_type)| {
         let mut path = path.as_ref().to_path_buf();
-        path.push(&key);
-        let name = OsString::from(key);
-        if let Some(_) = value.dyn_ref::<FileSystemFileHandle>() {
-            Ok(DirEntry {
-                name,
-                file_type: FileType::File,
-                path,
-            })
-        } else {
-            Ok(DirEntry {
-                name,
-                file_type: FileType::Dir,
-                path,
-            })
-        }
+        path.push(&name);
+        Ok(DirEntry {
+            name: OsString::from(name),
+            file_type,
+            path,
+        })
     });
-    Ok(read_dir)
+    Ok(stream::iter(read_dir))
 }
 
 /// Currently `remove_dir()` and `remove_file()` work the same.
 pub async fn remove_dir<P: AsRef<Path>>(path: P) -> Result<()> {
-    let parent_dir = get_parent_dir(&path, false).await?;
-    let name = path
-        .as_ref()
-        .file_name()
-        .ok_or(Error::from(ErrorKind::NotFound))?
-        .to_string_lossy();
-
-    JsFuture::from(parent_dir.remove_entry(&name))
-        .await
-        .map_err(|e| js_value_to_error(e))?;
-
-    Ok(())
+    backend().await.remove(&normalize(path)?, false).await
 }
 
 /// Currently `remove_dir()` and `remove_file()` work the same.
@@ -466,55 +151,64 @@ pub async fn remove_file<P: AsRef<Path>>(path: P) -> Result<()> {
 }
 
 pub async fn remove_dir_all<P: AsRef<Path>>(path: P) -> Result<()> {
-    let parent_dir = get_parent_dir(&path, false).await?;
-    let name = path
-        .as_ref()
-        .file_name()
-        .ok_or(Error::from(ErrorKind::NotFound))?
-        .to_string_lossy();
-
-    let options = FileSystemRemoveOptions::new();
-    options.set_recursive(true);
-
-    JsFuture::from(parent_dir.remove_entry_with_options(&name, &options))
-        .await
-        .map_err(|e| js_value_to_error(e))?;
+    backend().await.remove(&normalize(path)?, true).await
+}
 
-    Ok(())
+/// Returns the metadata of the file or directory at `path`.
+pub async fn metadata<P: AsRef<Path>>(path: P) -> Result<Metadata> {
+    backend().await.stat(&normalize(path)?).await
 }
 
+/// Reads the whole file, fetching it with one read of its known size, or a few large ones.
 pub async fn read<P: AsRef<Path>>(path: P) -> Result<Vec<u8>> {
     let mut file = File::open(path).await?;
     let mut buf = Vec::new();
-    file.read_to_end(&mut buf).await?;
+    file.read_sized(&mut buf, false).await?;
     Ok(buf)
 }
 
+/// Reads the whole file as a string, see [`read`].
 pub async fn read_to_string<P: AsRef<Path>>(path: P) -> Result<String> {
-    let mut file = File::open(path).await?;
-    let mut buf = String::new();
-    file.read_to_string(&mut buf).await?;
-    Ok(buf)
+    String::from_utf8(read(path).await?)
+        .map_err(|_| Error::new(ErrorKind::InvalidData, "stream did not contain valid UTF-8"))
 }
 
 pub async fn write<P: AsRef<Path>, C: AsRef<[u8]>>(path: P, contents: C) -> Result<()> {
-    let mut file = File::create_new(path).await?;
-    file.write_all(contents.as_ref()).await.unwrap();
+    let mut file = File::create(path).await?;
+    file.write_all(contents.as_ref()).await?;
     Ok(())
 }
 
+/// Renames a file or directory, replacing `to` if it is an existing file.
+pub async fn rename<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) -> Result<()> {
+    backend()
+        .await
+        .rename(&normalize(from)?, &normalize(to)?)
+        .await
+}
+
+/// Copies the content of `from` into `to`, overwriting `to` if it exists.
+///
+/// Uses [`Backend::copy`] when possible, for example the bytes never pass through wasm memory with [`OpfsBackend`].
 pub async fn copy<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) -> Result<u64> {
     let mut src = File::open(from).await?;
-    let mut dst = File::create_new(to).await?;
-    let buf_size = src.size.min(1 << 6) as usize;
-    let mut buf = vec![0; buf_size];
+    let mut dst = File::create(to).await?;
+    if Rc::ptr_eq(&src.backend, &dst.backend) {
+        match src.backend.copy(src.fd, dst.fd).await {
+            Err(e) if e.kind() == ErrorKind::Unsupported => (),
+            result => return result,
+        }
+    }
+    let mut buf = vec![0; src.size.clamp(1, COPY_BUF_SIZE) as usize];
+    let mut copied = 0;
     loop {
         let read_size = src.read(&mut buf).await?;
         if read_size == 0 {
             break;
         }
         dst.write_all(&buf[0..read_size]).await?;
-        buf[0..read_size].fill(0);
+        copied += read_size as u64;
     }
-    Ok(src.size)
+    dst.flush().await?;
+    Ok(copied)
 }
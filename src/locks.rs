@@ -0,0 +1,273 @@
+use std::{
+    cell::RefCell,
+    fs::TryLockError,
+    io::{Error, ErrorKind, Result},
+    path::Path,
+};
+
+use js_sys::{Function, Object, Promise, Reflect};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+
+use crate::{File, util::js_value_to_error};
+
+const FILE_LOCK_PREFIX: &str = "web-fs-file-";
+/// Appended to the name of a file lock for the lock taken while requesting it.
+const GATE_SUFFIX: &str = "-gate";
+
+// web-sys only exposes the Web Locks API behind `web_sys_unstable_apis`.
+#[wasm_bindgen]
+extern "C" {
+    pub(crate) type LockManager;
+    #[wasm_bindgen(method)]
+    fn request(this: &LockManager, name: &str, options: &Object, callback: &Function) -> Promise;
+}
+
+/// `navigator.locks` of the current window or worker, if the browser supports it.
+pub(crate) fn lock_manager() -> Option<LockManager> {
+    // There is no browser to ask on native targets.
+    if cfg!(not(target_arch = "wasm32")) {
+        return None;
+    }
+    let navigator = Reflect::get(&js_sys::global(), &JsValue::from_str("navigator")).ok()?;
+    let locks = Reflect::get(&navigator, &JsValue::from_str("locks")).ok()?;
+    if locks.is_undefined() {
+        None
+    } else {
+        Some(locks.unchecked_into())
+    }
+}
+
+/// Holds an exclusive lock on `name` until the page or worker goes away.
+pub(crate) fn hold_forever(manager: &LockManager, name: &str) {
+    let callback = Closure::once_into_js(|_lock: JsValue| Promise::new(&mut |_, _| {}));
+    let _ = manager.request(name, &Object::new(), callback.unchecked_ref());
+}
+
+/// Whether nobody holds the lock `name` right now.
+pub(crate) async fn is_lock_free(manager: &LockManager, name: &str) -> bool {
+    let options = Object::new();
+    Reflect::set(&options, &JsValue::from_str("ifAvailable"), &JsValue::TRUE)
+        .expect("Setting js field error, this is an error of the crate.");
+    // The callback gets `null` instead of a lock if it is held by someone else.
+    let callback = Closure::once_into_js(|lock: JsValue| JsValue::from_bool(!lock.is_null()));
+    JsFuture::from(manager.request(name, &options, callback.unchecked_ref()))
+        .await
+        .map(|free| free.is_truthy())
+        .unwrap_or(false)
+}
+
+/// A lock granted by the *Web Locks API*, released when dropped.
+pub(crate) struct HeldLock {
+    shared: bool,
+    release: Function,
+    /// Settled once the lock is released.
+    done: Promise,
+    /// Called by JS if the request fails, kept alive as long as the request.
+    _on_error: Closure<dyn FnMut(JsValue)>,
+}
+
+impl HeldLock {
+    /// Releases the lock, waiting until other requests can be granted it.
+    async fn release(self) {
+        let done = self.done.clone();
+        drop(self);
+        let _ = JsFuture::from(done).await;
+    }
+}
+
+impl Drop for HeldLock {
+    fn drop(&mut self) {
+        let _ = self.release.call0(&JsValue::UNDEFINED);
+    }
+}
+
+/// Requests the lock `name` and waits until it is granted.
+///
+/// Returns `None` without waiting if `if_available` is set and the lock is held by someone else.
+async fn request(
+    manager: &LockManager,
+    name: &str,
+    shared: bool,
+    if_available: bool,
+) -> Result<Option<HeldLock>> {
+    const RESOLVE_ERROR: &str =
+        "Getting the resolve function of a promise failed, this is an error of the crate.";
+    let mut release = None;
+    let released = Promise::new(&mut |resolve, _| release = Some(resolve));
+    let release = release.expect(RESOLVE_ERROR);
+    let mut settle = None;
+    let granted = Promise::new(&mut |resolve, reject| settle = Some((resolve, reject)));
+    let (on_granted, on_error) = settle.expect(RESOLVE_ERROR);
+
+    // The lock is held until the promise returned by the callback resolves.
+    let callback = Closure::once_into_js(move |lock: JsValue| {
+        let held = !lock.is_null();
+        let _ = on_granted.call1(&JsValue::UNDEFINED, &JsValue::from_bool(held));
+        if held {
+            released.into()
+        } else {
+            JsValue::UNDEFINED
+        }
+    });
+    let options = Object::new();
+    let mode = if shared { "shared" } else { "exclusive" };
+    Reflect::set(
+        &options,
+        &JsValue::from_str("mode"),
+        &JsValue::from_str(mode),
+    )
+    .expect("Setting js field error, this is an error of the crate.");
+    Reflect::set(
+        &options,
+        &JsValue::from_str("ifAvailable"),
+        &JsValue::from_bool(if_available),
+    )
+    .expect("Setting js field error, this is an error of the crate.");
+    let on_error = Closure::new(move |e: JsValue| {
+        let _ = on_error.call1(&JsValue::UNDEFINED, &e);
+    });
+    let done = manager
+        .request(name, &options, callback.unchecked_ref())
+        .catch(&on_error);
+
+    let held = JsFuture::from(granted)
+        .await
+        .map_err(js_value_to_error)?
+        .is_truthy();
+    Ok(held.then_some(HeldLock {
+        shared,
+        release,
+        done,
+        _on_error: on_error,
+    }))
+}
+
+/// The lock manager and the name of the lock of the file at the normalized `path`.
+fn file_lock(path: &Path) -> Result<(LockManager, String)> {
+    let manager = lock_manager().ok_or(Error::new(
+        ErrorKind::Unsupported,
+        "the Web Locks API is not available",
+    ))?;
+    Ok((
+        manager,
+        format!("{}{}", FILE_LOCK_PREFIX, path.to_string_lossy()),
+    ))
+}
+
+/// Locks the file at the normalized `path` for every tab and worker of the origin.
+///
+/// The gate of the lock is held until the request is granted,
+/// so nothing else queues for the lock while [`try_convert`] runs.
+/// Returns [`ErrorKind::Unsupported`] if the browser doesn't support the *Web Locks API*.
+pub(crate) async fn lock_path(
+    path: &Path,
+    shared: bool,
+    if_available: bool,
+) -> Result<Option<HeldLock>> {
+    let (manager, name) = file_lock(path)?;
+    let gate = format!("{name}{GATE_SUFFIX}");
+    let Some(gate) = request(&manager, &gate, false, if_available).await? else {
+        return Ok(None);
+    };
+    let lock = request(&manager, &name, shared, if_available).await;
+    // Released before returning, so that a following `try_lock` doesn't find it held.
+    gate.release().await;
+    lock
+}
+
+/// Converts `held`, the lock of the file at `path`, to the `shared` mode or out of it without waiting,
+/// returning whether it was converted.
+///
+/// Two requests on the same name always conflict, so the lock is released before requesting the new mode.
+/// Holding the gate meanwhile keeps others from queuing for it,
+/// so if the new mode can't be granted right away the old one is.
+async fn try_convert(path: &Path, held: &RefCell<Option<HeldLock>>, shared: bool) -> Result<bool> {
+    let (manager, name) = file_lock(path)?;
+    let gate = format!("{name}{GATE_SUFFIX}");
+    let Some(gate) = request(&manager, &gate, false, true).await? else {
+        return Ok(false);
+    };
+    let lock = held.borrow_mut().take();
+    if let Some(lock) = lock {
+        lock.release().await;
+    }
+    let result = async {
+        if let Some(lock) = request(&manager, &name, shared, true).await? {
+            *held.borrow_mut() = Some(lock);
+            return Ok(true);
+        }
+        let lock = request(&manager, &name, !shared, true)
+            .await?
+            .ok_or(Error::other("the file lock was taken while converting it"))?;
+        *held.borrow_mut() = Some(lock);
+        Ok(false)
+    }
+    .await;
+    gate.release().await;
+    result
+}
+
+/// File locks are advisory: they only exclude other locks on the same path,
+/// including files opened with [`OpenOptions::wait_for_lock`](crate::OpenOptions::wait_for_lock),
+/// and are shared by every tab and worker of the origin.
+/// A file holds at most one lock, which is released by [`unlock`](File::unlock) or once the file is closed or dropped.
+/// Locking it again in the other mode converts the lock:
+/// [`try_lock`](File::try_lock) and [`try_lock_shared`](File::try_lock_shared) keep the held lock if they fail,
+/// while [`lock`](File::lock) and [`lock_shared`](File::lock_shared) release it before waiting, as `flock` does,
+/// because a request waiting on a lock held by the same file would never be granted.
+///
+/// These rely on the *Web Locks API* and return [`ErrorKind::Unsupported`] without it, as on native targets.
+impl File {
+    async fn lock_with(&self, shared: bool, if_available: bool) -> Result<bool> {
+        let held = self.lock.borrow().as_ref().map(|lock| lock.shared);
+        match held {
+            Some(held) if held == shared => return Ok(true),
+            Some(_) if if_available => return try_convert(&self.path, &self.lock, shared).await,
+            Some(_) => {
+                let lock = self.lock.borrow_mut().take();
+                if let Some(lock) = lock {
+                    lock.release().await;
+                }
+            }
+            None => (),
+        }
+        let lock = lock_path(&self.path, shared, if_available).await?;
+        let held = lock.is_some();
+        if held {
+            *self.lock.borrow_mut() = lock;
+        }
+        Ok(held)
+    }
+    /// Waits for an exclusive lock on the file.
+    pub async fn lock(&self) -> Result<()> {
+        self.lock_with(false, false).await.map(drop)
+    }
+    /// Waits for a shared lock on the file.
+    pub async fn lock_shared(&self) -> Result<()> {
+        self.lock_with(true, false).await.map(drop)
+    }
+    /// Takes an exclusive lock on the file,
+    /// or returns [`TryLockError::WouldBlock`] if another lock is held on it.
+    pub async fn try_lock(&self) -> std::result::Result<(), TryLockError> {
+        match self.lock_with(false, true).await {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(TryLockError::WouldBlock),
+            Err(e) => Err(TryLockError::Error(e)),
+        }
+    }
+    /// Takes a shared lock on the file,
+    /// or returns [`TryLockError::WouldBlock`] if an exclusive lock is held on it.
+    pub async fn try_lock_shared(&self) -> std::result::Result<(), TryLockError> {
+        match self.lock_with(true, true).await {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(TryLockError::WouldBlock),
+            Err(e) => Err(TryLockError::Error(e)),
+        }
+    }
+    /// Releases the lock held by the file, if any.
+    pub fn unlock(&self) -> Result<()> {
+        self.lock.borrow_mut().take();
+        Ok(())
+    }
+}
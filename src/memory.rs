@@ -0,0 +1,387 @@
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    future::ready,
+    io::{Error, ErrorKind, Result},
+    path::Path,
+    rc::Rc,
+    time::SystemTime,
+};
+
+use futures_lite::future::BoxedLocal;
+
+use crate::{Backend, FileType, Metadata, OpenOptions, arena::Arena, util::now};
+
+struct MemoryFile {
+    data: Vec<u8>,
+    modified: SystemTime,
+    readers: usize,
+    writer: bool,
+}
+
+impl MemoryFile {
+    fn new() -> Self {
+        Self {
+            data: Vec::new(),
+            modified: now(),
+            readers: 0,
+            writer: false,
+        }
+    }
+    fn is_open(&self) -> bool {
+        self.writer || self.readers > 0
+    }
+}
+
+enum Node {
+    File(Rc<RefCell<MemoryFile>>),
+    Dir(BTreeMap<String, Node>),
+}
+
+impl Node {
+    /// Whether this is, or contains, a file that is open.
+    fn is_busy(&self) -> bool {
+        match self {
+            Node::File(file) => file.borrow().is_open(),
+            Node::Dir(entries) => entries.values().any(Node::is_busy),
+        }
+    }
+}
+
+struct Opened {
+    file: Rc<RefCell<MemoryFile>>,
+    writable: bool,
+}
+
+struct MemoryInner {
+    root: BTreeMap<String, Node>,
+    opened: Arena<Opened>,
+}
+
+fn name_of(path: &Path) -> Result<String> {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .ok_or(Error::new(
+            ErrorKind::PermissionDenied,
+            "the root can't be modified",
+        ))
+}
+
+fn busy_error() -> Error {
+    Error::new(
+        ErrorKind::PermissionDenied,
+        "the file is open, like with a locked sync access handle",
+    )
+}
+
+/// Resizes `data` to `len` bytes, failing instead of aborting if they can't be allocated.
+fn resize(data: &mut Vec<u8>, len: u64) -> Result<()> {
+    let len = usize::try_from(len).map_err(|_| {
+        Error::new(
+            ErrorKind::InvalidInput,
+            "the file size doesn't fit in memory",
+        )
+    })?;
+    data.try_reserve_exact(len.saturating_sub(data.len()))
+        .map_err(|_| Error::from(ErrorKind::OutOfMemory))?;
+    data.resize(len, 0);
+    Ok(())
+}
+
+impl MemoryInner {
+    fn dir(&self, path: &Path) -> Result<&BTreeMap<String, Node>> {
+        let mut dir = &self.root;
+        for name in path.iter() {
+            match dir.get(&*name.to_string_lossy()) {
+                Some(Node::Dir(entries)) => dir = entries,
+                Some(Node::File(_)) => return Err(Error::from(ErrorKind::NotADirectory)),
+                None => return Err(Error::from(ErrorKind::NotFound)),
+            }
+        }
+        Ok(dir)
+    }
+    fn dir_mut(&mut self, path: &Path, create: bool) -> Result<&mut BTreeMap<String, Node>> {
+        let mut dir = &mut self.root;
+        for name in path.iter() {
+            let name = name.to_string_lossy().into_owned();
+            if create && !dir.contains_key(&name) {
+                dir.insert(name.clone(), Node::Dir(BTreeMap::new()));
+            }
+            match dir.get_mut(&name) {
+                Some(Node::Dir(entries)) => dir = entries,
+                Some(Node::File(_)) => return Err(Error::from(ErrorKind::NotADirectory)),
+                None => return Err(Error::from(ErrorKind::NotFound)),
+            }
+        }
+        Ok(dir)
+    }
+    /// The directory containing `path`, which must already exist, and the name of `path` in it.
+    fn parent_mut(&mut self, path: &Path) -> Result<(&mut BTreeMap<String, Node>, String)> {
+        let name = name_of(path)?;
+        let parent = self.dir_mut(path.parent().unwrap_or(Path::new("")), false)?;
+        Ok((parent, name))
+    }
+    fn node(&self, path: &Path) -> Result<Option<&Node>> {
+        let Ok(name) = name_of(path) else {
+            return Ok(None);
+        };
+        let parent = self.dir(path.parent().unwrap_or(Path::new("")))?;
+        parent
+            .get(&name)
+            .map(Some)
+            .ok_or(Error::from(ErrorKind::NotFound))
+    }
+    fn opened(&self, fd: usize) -> &Opened {
+        self.opened
+            .get(fd)
+            .expect("Using a closed file descriptor, this is an error of the crate.")
+    }
+    fn writable(&self, fd: usize) -> Result<Rc<RefCell<MemoryFile>>> {
+        let opened = self.opened(fd);
+        if opened.writable {
+            Ok(opened.file.clone())
+        } else {
+            Err(Error::new(
+                ErrorKind::PermissionDenied,
+                "the file is not opened for writing",
+            ))
+        }
+    }
+
+    fn open(&mut self, path: &Path, options: &OpenOptions) -> Result<(usize, u64)> {
+        if options.is_truncate() && !options.is_write() {
+            return Err(Error::from(ErrorKind::InvalidInput));
+        }
+        let (dir, name) = self.parent_mut(path)?;
+        let file = match dir.get(&name) {
+            Some(Node::Dir(_)) => return Err(Error::from(ErrorKind::IsADirectory)),
+            Some(Node::File(_)) if options.is_create_new() => {
+                return Err(Error::from(ErrorKind::AlreadyExists));
+            }
+            Some(Node::File(file)) => file.clone(),
+            None if options.is_create() || options.is_create_new() => {
+                let file = Rc::new(RefCell::new(MemoryFile::new()));
+                dir.insert(name, Node::File(file.clone()));
+                file
+            }
+            None => return Err(Error::from(ErrorKind::NotFound)),
+        };
+        let writable = options.is_write();
+        let size = {
+            let mut file = file.borrow_mut();
+            // OPFS allows several read-only sync access handles or one readwrite handle.
+            if file.writer || (writable && file.readers > 0) {
+                return Err(busy_error());
+            }
+            if writable {
+                file.writer = true;
+            } else {
+                file.readers += 1;
+            }
+            if options.is_truncate() {
+                file.data.clear();
+                file.modified = now();
+            }
+            file.data.len() as u64
+        };
+        let fd = self.opened.insert(Opened { file, writable });
+        Ok((fd, size))
+    }
+    fn write(&self, fd: usize, cursor: u64, buf: &[u8]) -> Result<usize> {
+        let file = self.writable(fd)?;
+        let mut file = file.borrow_mut();
+        let end = cursor.checked_add(buf.len() as u64).ok_or(Error::new(
+            ErrorKind::InvalidInput,
+            "writing past the largest file size",
+        ))?;
+        if (file.data.len() as u64) < end {
+            resize(&mut file.data, end)?;
+        }
+        // Both fit in memory now.
+        let start = cursor as usize;
+        file.data[start..start + buf.len()].copy_from_slice(buf);
+        file.modified = now();
+        Ok(buf.len())
+    }
+    fn truncate(&self, fd: usize, size: u64) -> Result<()> {
+        let file = self.writable(fd)?;
+        let mut file = file.borrow_mut();
+        resize(&mut file.data, size)?;
+        file.modified = now();
+        Ok(())
+    }
+    fn create_dir(&mut self, path: &Path, recursive: bool) -> Result<()> {
+        if recursive {
+            return self.dir_mut(path, true).map(|_| ());
+        }
+        let Ok(name) = name_of(path) else {
+            // The root always exists.
+            return Ok(());
+        };
+        let (dir, _) = self.parent_mut(path)?;
+        match dir.get(&name) {
+            Some(Node::Dir(_)) => Ok(()),
+            Some(Node::File(_)) => Err(Error::from(ErrorKind::AlreadyExists)),
+            None => {
+                dir.insert(name, Node::Dir(BTreeMap::new()));
+                Ok(())
+            }
+        }
+    }
+    fn remove(&mut self, path: &Path, recursive: bool) -> Result<()> {
+        let (dir, name) = self.parent_mut(path)?;
+        let node = dir.get(&name).ok_or(Error::from(ErrorKind::NotFound))?;
+        if let Node::Dir(entries) = node
+            && !recursive
+            && !entries.is_empty()
+        {
+            return Err(Error::from(ErrorKind::DirectoryNotEmpty));
+        }
+        if node.is_busy() {
+            return Err(busy_error());
+        }
+        dir.remove(&name);
+        Ok(())
+    }
+    fn rename(&mut self, from: &Path, to: &Path) -> Result<()> {
+        if from == to {
+            return self.node(from).map(|_| ());
+        }
+        if to.starts_with(from) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "can't move a directory into itself",
+            ));
+        }
+        match self.node(from)? {
+            Some(node) if node.is_busy() => return Err(busy_error()),
+            Some(_) => (),
+            None => return Err(Error::from(ErrorKind::NotFound)),
+        }
+        let (to_dir, to_name) = self.parent_mut(to)?;
+        match to_dir.get(&to_name) {
+            Some(Node::Dir(_)) => return Err(Error::from(ErrorKind::IsADirectory)),
+            Some(node) if node.is_busy() => return Err(busy_error()),
+            _ => (),
+        }
+        let (from_dir, from_name) = self.parent_mut(from)?;
+        let node = from_dir
+            .remove(&from_name)
+            .expect("The entry was checked above.");
+        let (to_dir, to_name) = self.parent_mut(to)?;
+        to_dir.insert(to_name, node);
+        Ok(())
+    }
+}
+
+/// A [`Backend`] keeping everything in memory, mainly for testing code that uses this crate without a browser.
+///
+/// It follows the behaviors of [`OpfsBackend`](crate::OpfsBackend) where they differ from `std::fs`,
+/// for example a file opened for writing can't be opened again or removed until it is dropped.
+/// Clones share the same files.
+#[derive(Clone)]
+pub struct MemoryBackend {
+    inner: Rc<RefCell<MemoryInner>>,
+}
+
+impl MemoryBackend {
+    pub fn new() -> Self {
+        Self {
+            inner: Rc::new(RefCell::new(MemoryInner {
+                root: BTreeMap::new(),
+                opened: Arena::new(),
+            })),
+        }
+    }
+}
+
+impl Default for MemoryBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Backend for MemoryBackend {
+    fn open(&self, path: &Path, options: &OpenOptions) -> BoxedLocal<Result<(usize, u64)>> {
+        Box::pin(ready(self.inner.borrow_mut().open(path, options)))
+    }
+    fn read(&self, fd: usize, cursor: u64, len: usize) -> BoxedLocal<Result<Vec<u8>>> {
+        let inner = self.inner.borrow();
+        let file = inner.opened(fd).file.borrow();
+        let start = (cursor as usize).min(file.data.len());
+        let end = start.saturating_add(len).min(file.data.len());
+        Box::pin(ready(Ok(file.data[start..end].to_vec())))
+    }
+    fn concurrent_reads(&self) -> bool {
+        true
+    }
+    fn write(&self, fd: usize, cursor: u64, buf: &[u8]) -> BoxedLocal<Result<usize>> {
+        Box::pin(ready(self.inner.borrow().write(fd, cursor, buf)))
+    }
+    fn flush(&self, _fd: usize) -> BoxedLocal<Result<()>> {
+        Box::pin(ready(Ok(())))
+    }
+    fn truncate(&self, fd: usize, size: u64) -> BoxedLocal<Result<()>> {
+        Box::pin(ready(self.inner.borrow().truncate(fd, size)))
+    }
+    fn close(&self, _fd: usize) -> BoxedLocal<Result<()>> {
+        Box::pin(ready(Ok(())))
+    }
+    fn release(&self, fd: usize) {
+        let Some(opened) = self.inner.borrow_mut().opened.remove(fd) else {
+            return;
+        };
+        let mut file = opened.file.borrow_mut();
+        if opened.writable {
+            file.writer = false;
+        } else {
+            file.readers -= 1;
+        }
+    }
+    fn stat(&self, path: &Path) -> BoxedLocal<Result<Metadata>> {
+        let inner = self.inner.borrow();
+        let metadata = inner.node(path).map(|node| match node {
+            Some(Node::File(file)) => {
+                let file = file.borrow();
+                Metadata::new(FileType::File, file.data.len() as u64, Some(file.modified))
+            }
+            Some(Node::Dir(_)) | None => Metadata::new(FileType::Dir, 0, None),
+        });
+        Box::pin(ready(metadata))
+    }
+    fn list(&self, path: &Path) -> BoxedLocal<Result<Vec<(String, FileType)>>> {
+        let inner = self.inner.borrow();
+        let entries = inner.dir(path).map(|dir| {
+            dir.iter()
+                .map(|(name, node)| {
+                    let file_type = match node {
+                        Node::File(_) => FileType::File,
+                        Node::Dir(_) => FileType::Dir,
+                    };
+                    (name.clone(), file_type)
+                })
+                .collect()
+        });
+        Box::pin(ready(entries))
+    }
+    fn create_dir(&self, path: &Path, recursive: bool) -> BoxedLocal<Result<()>> {
+        Box::pin(ready(self.inner.borrow_mut().create_dir(path, recursive)))
+    }
+    fn remove(&self, path: &Path, recursive: bool) -> BoxedLocal<Result<()>> {
+        Box::pin(ready(self.inner.borrow_mut().remove(path, recursive)))
+    }
+    fn rename(&self, from: &Path, to: &Path) -> BoxedLocal<Result<()>> {
+        Box::pin(ready(self.inner.borrow_mut().rename(from, to)))
+    }
+    fn copy(&self, src: usize, dst: usize) -> BoxedLocal<Result<u64>> {
+        let inner = self.inner.borrow();
+        let result = inner.writable(dst).map(|dst| {
+            let data = inner.opened(src).file.borrow().data.clone();
+            let mut dst = dst.borrow_mut();
+            dst.data = data;
+            dst.modified = now();
+            dst.data.len() as u64
+        });
+        Box::pin(ready(result))
+    }
+}
@@ -3,12 +3,18 @@ use std::time::SystemTime;
 
 use crate::FileType;
 
+#[derive(Debug, Clone)]
 pub struct Metadata {
     pub(crate) ty: FileType,
     pub(crate) len: u64,
+    pub(crate) modified: Option<SystemTime>,
 }
 
 impl Metadata {
+    /// For [`Backend`](crate::Backend) implementations, `modified` is `None` if the backend doesn't track it.
+    pub fn new(ty: FileType, len: u64, modified: Option<SystemTime>) -> Self {
+        Self { ty, len, modified }
+    }
     /// Always returns Err because it is currently not supported in *File System API*.
     pub fn accsessed(&self) -> Result<SystemTime> {
         Err(Error::from(ErrorKind::Other))
@@ -31,12 +37,14 @@ impl Metadata {
     pub fn is_symlink(&self) -> bool {
         self.ty.is_symlink()
     }
+    #[allow(clippy::len_without_is_empty)]
     pub fn len(&self) -> u64 {
         self.len
     }
-    /// Always returns Err because it is currently not supported in *File System API*.
+    /// Returns Err for directories and for metadata of an open [`File`](crate::File),
+    /// because *File System API* only tracks it for files and only exposes it by path.
     pub fn modified(&self) -> Result<SystemTime> {
-        Err(Error::from(ErrorKind::Other))
+        self.modified.ok_or(Error::from(ErrorKind::Other))
     }
     pub fn permissions(&self) -> Permissions {
         Permissions { readonly: false }
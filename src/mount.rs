@@ -0,0 +1,140 @@
+use std::{
+    cell::RefCell,
+    io::{Error, ErrorKind, Result},
+    path::{Path, PathBuf},
+};
+
+use wasm_bindgen_futures::JsFuture;
+use web_sys::FileSystemDirectoryHandle;
+
+use crate::{
+    dir_cache,
+    opfs::get_root,
+    util::{js_value_to_error, normalize},
+};
+
+/// A directory mounted by [`MountOptions::mount`].
+#[derive(Clone)]
+pub(crate) struct Mount {
+    pub(crate) handle: FileSystemDirectoryHandle,
+    /// Whether the directory is inside OPFS, where its files can be opened by the worker.
+    pub(crate) in_opfs: bool,
+    pub(crate) read_only: bool,
+}
+
+thread_local! {
+    /// The mount table, mount points are normalized paths.
+    static MOUNTS: RefCell<Vec<(PathBuf, Mount)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Options and flags which can be used to configure how a directory is mounted.
+///
+/// Mounts combine several directories into one path namespace for [`OpfsBackend`](crate::OpfsBackend) in this thread,
+/// for example the root of OPFS at `/opfs`, a folder picked by the user at `/project`
+/// and bundled data at `/assets`.
+/// A path refers to the mount with the longest mount point it starts with,
+/// paths outside of every mount are in the root of OPFS.
+///
+/// Renaming between two mounts fails with [`ErrorKind::CrossesDevices`],
+/// copy the files and remove the originals instead.
+#[derive(Debug, Clone, Default)]
+pub struct MountOptions {
+    read_only: bool,
+}
+
+impl MountOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Fail with [`ErrorKind::PermissionDenied`] when opening files for writing,
+    /// and when creating, removing or renaming anything in the mount.
+    pub fn read_only(&mut self, read_only: bool) -> &mut MountOptions {
+        self.read_only = read_only;
+        self
+    }
+    /// Mounts `handle` at `path`, so that `path` and everything under it refers to the content of `handle`.
+    ///
+    /// `handle` can be any `FileSystemDirectoryHandle`,
+    /// for example one from `showDirectoryPicker()` or from a `DataTransferItem` dropped on the page.
+    /// Mounting at the empty path or `/` replaces the root.
+    /// `path` doesn't have to exist, a mount point is listed as a directory in its parent.
+    /// Mounting at an existing mount point replaces what was mounted there.
+    ///
+    /// The worker can only open files in OPFS,
+    /// so files in other directories are accessed from the current thread as with [`OpfsBackend::main_thread`](crate::OpfsBackend::main_thread),
+    /// where writes only become visible on flush or close.
+    /// The page must have been granted `readwrite` permission on `handle` to write into it.
+    pub async fn mount<P: AsRef<Path>>(
+        &self,
+        path: P,
+        handle: FileSystemDirectoryHandle,
+    ) -> Result<()> {
+        let path = normalize(path)?;
+        let in_opfs = match get_root().await {
+            // `resolve` returns null if `handle` is not inside the root.
+            Ok(root) => !JsFuture::from(root.resolve(&handle))
+                .await
+                .map_err(js_value_to_error)?
+                .is_null(),
+            Err(_) => false,
+        };
+        let mount = Mount {
+            handle,
+            in_opfs,
+            read_only: self.read_only,
+        };
+        MOUNTS.with_borrow_mut(|mounts| {
+            match mounts.iter_mut().find(|(point, _)| *point == path) {
+                Some((_, existing)) => *existing = mount,
+                None => mounts.push((path.clone(), mount)),
+            }
+        });
+        dir_cache::invalidate(&path);
+        Ok(())
+    }
+}
+
+/// Mounts `handle` at `path` with the default [`MountOptions`].
+pub async fn mount<P: AsRef<Path>>(path: P, handle: FileSystemDirectoryHandle) -> Result<()> {
+    MountOptions::new().mount(path, handle).await
+}
+
+/// Removes the mount at `path`.
+///
+/// Files that are already open keep working.
+/// Returns [`ErrorKind::NotFound`] if nothing is mounted there.
+pub fn unmount<P: AsRef<Path>>(path: P) -> Result<()> {
+    let path = normalize(path)?;
+    MOUNTS.with_borrow_mut(|mounts| {
+        let index = mounts
+            .iter()
+            .position(|(point, _)| *point == path)
+            .ok_or(Error::from(ErrorKind::NotFound))?;
+        mounts.remove(index);
+        dir_cache::invalidate(&path);
+        Ok(())
+    })
+}
+
+/// Finds the mount with the longest mount point that `path` starts with, and its mount point.
+pub(crate) fn lookup(path: &Path) -> Option<(PathBuf, Mount)> {
+    MOUNTS.with_borrow(|mounts| {
+        mounts
+            .iter()
+            .filter(|(point, _)| path.starts_with(point))
+            .max_by_key(|(point, _)| point.components().count())
+            .cloned()
+    })
+}
+
+/// The names of the mount points directly inside the directory at `path`.
+pub(crate) fn mount_points_in(path: &Path) -> Vec<String> {
+    MOUNTS.with_borrow(|mounts| {
+        mounts
+            .iter()
+            .filter(|(point, _)| point.parent() == Some(path))
+            .filter_map(|(point, _)| point.file_name())
+            .map(|name| name.to_string_lossy().into_owned())
+            .collect()
+    })
+}
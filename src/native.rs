@@ -0,0 +1,200 @@
+use std::{
+    cell::RefCell,
+    fs,
+    io::{self, ErrorKind, Read, Result, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::Arc,
+};
+
+use blocking::unblock;
+use futures_lite::future::BoxedLocal;
+
+use crate::{Backend, FileType, Metadata, OpenOptions, arena::Arena};
+
+fn file_type(metadata: &fs::Metadata) -> FileType {
+    if metadata.is_dir() {
+        FileType::Dir
+    } else {
+        FileType::File
+    }
+}
+
+/// A [`Backend`] passing everything through to `std::fs` on a blocking thread pool,
+/// the default on native targets so the same code works in and out of the browser.
+///
+/// All paths are resolved inside a root directory, the current directory by default.
+/// The differences from `async-fs`, which mostly come from following the behaviors of [`OpfsBackend`](crate::OpfsBackend), are:
+/// - Absolute paths are relative to the root, and `..` can't leave it.
+/// - [`create_dir`](crate::create_dir) succeeds if the directory already exists.
+/// - [`remove_file`](crate::remove_file) and [`remove_dir`](crate::remove_dir) both remove files and empty directories.
+/// - Symlinks are followed and never reported.
+///
+/// Unlike OPFS, a file can be opened, renamed or removed while it is open elsewhere, as `std::fs` permits.
+pub struct NativeBackend {
+    root: PathBuf,
+    opened: Rc<RefCell<Arena<Arc<fs::File>>>>,
+}
+
+impl NativeBackend {
+    pub fn new<P: AsRef<Path>>(root: P) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+            opened: Rc::new(RefCell::new(Arena::new())),
+        }
+    }
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+    fn file(&self, fd: usize) -> Arc<fs::File> {
+        self.opened
+            .borrow()
+            .get(fd)
+            .expect("Using a closed file descriptor, this is an error of the crate.")
+            .clone()
+    }
+}
+
+impl Default for NativeBackend {
+    /// Rooted in the current directory.
+    fn default() -> Self {
+        Self::new(".")
+    }
+}
+
+impl Backend for NativeBackend {
+    fn open(&self, path: &Path, options: &OpenOptions) -> BoxedLocal<Result<(usize, u64)>> {
+        let path = self.root.join(path);
+        let options = *options;
+        let opening = unblock(move || {
+            // Like a sync access handle, a file is always readable.
+            let file = fs::OpenOptions::new()
+                .read(true)
+                .write(options.is_write() || options.is_append())
+                .create(options.is_create())
+                .create_new(options.is_create_new())
+                .truncate(options.is_truncate())
+                .open(&path)?;
+            if file.metadata()?.is_dir() {
+                return Err(io::Error::from(ErrorKind::IsADirectory));
+            }
+            let size = file.metadata()?.len();
+            Ok((file, size))
+        });
+        let opened = self.opened.clone();
+        Box::pin(async move {
+            let (file, size) = opening.await?;
+            let fd = opened.borrow_mut().insert(Arc::new(file));
+            Ok((fd, size))
+        })
+    }
+    fn read(&self, fd: usize, cursor: u64, len: usize) -> BoxedLocal<Result<Vec<u8>>> {
+        let file = self.file(fd);
+        Box::pin(unblock(move || {
+            let mut file = &*file;
+            file.seek(SeekFrom::Start(cursor))?;
+            let mut buf = Vec::with_capacity(len);
+            file.take(len as u64).read_to_end(&mut buf)?;
+            Ok(buf)
+        }))
+    }
+    fn write(&self, fd: usize, cursor: u64, buf: &[u8]) -> BoxedLocal<Result<usize>> {
+        let file = self.file(fd);
+        let buf = buf.to_vec();
+        Box::pin(unblock(move || {
+            let mut file = &*file;
+            file.seek(SeekFrom::Start(cursor))?;
+            file.write_all(&buf)?;
+            Ok(buf.len())
+        }))
+    }
+    fn flush(&self, fd: usize) -> BoxedLocal<Result<()>> {
+        let file = self.file(fd);
+        Box::pin(unblock(move || file.sync_data()))
+    }
+    fn truncate(&self, fd: usize, size: u64) -> BoxedLocal<Result<()>> {
+        let file = self.file(fd);
+        Box::pin(unblock(move || file.set_len(size)))
+    }
+    fn close(&self, _fd: usize) -> BoxedLocal<Result<()>> {
+        // The file is closed when released.
+        Box::pin(async { Ok(()) })
+    }
+    fn release(&self, fd: usize) {
+        self.opened.borrow_mut().remove(fd);
+    }
+    fn stat(&self, path: &Path) -> BoxedLocal<Result<Metadata>> {
+        let path = self.root.join(path);
+        Box::pin(unblock(move || {
+            let metadata = fs::metadata(path)?;
+            Ok(Metadata::new(
+                file_type(&metadata),
+                if metadata.is_dir() { 0 } else { metadata.len() },
+                metadata.modified().ok(),
+            ))
+        }))
+    }
+    fn list(&self, path: &Path) -> BoxedLocal<Result<Vec<(String, FileType)>>> {
+        let path = self.root.join(path);
+        Box::pin(unblock(move || {
+            let mut entries = Vec::new();
+            for entry in fs::read_dir(path)? {
+                let entry = entry?;
+                let metadata = match fs::metadata(entry.path()) {
+                    Ok(metadata) => metadata,
+                    // Removed since listing, or a broken symlink.
+                    Err(e) if e.kind() == ErrorKind::NotFound => continue,
+                    Err(e) => return Err(e),
+                };
+                entries.push((
+                    entry.file_name().to_string_lossy().into_owned(),
+                    file_type(&metadata),
+                ));
+            }
+            Ok(entries)
+        }))
+    }
+    fn create_dir(&self, path: &Path, recursive: bool) -> BoxedLocal<Result<()>> {
+        let path = self.root.join(path);
+        Box::pin(unblock(move || {
+            if recursive {
+                return fs::create_dir_all(path);
+            }
+            match fs::create_dir(&path) {
+                Err(e) if e.kind() == ErrorKind::AlreadyExists && path.is_dir() => Ok(()),
+                result => result,
+            }
+        }))
+    }
+    fn remove(&self, path: &Path, recursive: bool) -> BoxedLocal<Result<()>> {
+        if path.as_os_str().is_empty() {
+            // Never remove the root itself.
+            return Box::pin(async { Err(io::Error::from(ErrorKind::PermissionDenied)) });
+        }
+        let path = self.root.join(path);
+        Box::pin(unblock(move || {
+            if !fs::symlink_metadata(&path)?.is_dir() {
+                fs::remove_file(path)
+            } else if recursive {
+                fs::remove_dir_all(path)
+            } else {
+                fs::remove_dir(path)
+            }
+        }))
+    }
+    fn rename(&self, from: &Path, to: &Path) -> BoxedLocal<Result<()>> {
+        let from = self.root.join(from);
+        let to = self.root.join(to);
+        Box::pin(unblock(move || fs::rename(from, to)))
+    }
+    fn copy(&self, src: usize, dst: usize) -> BoxedLocal<Result<u64>> {
+        let src = self.file(src);
+        let dst = self.file(dst);
+        Box::pin(unblock(move || {
+            let (mut src, mut dst) = (&*src, &*dst);
+            src.seek(SeekFrom::Start(0))?;
+            dst.seek(SeekFrom::Start(0))?;
+            io::copy(&mut src, &mut dst)
+        }))
+    }
+}
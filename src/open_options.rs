@@ -1,19 +1,14 @@
 use std::{
-    cell::RefCell,
     future::Future,
-    io::Result,
+    io::{ErrorKind, Result},
     path::Path,
     pin::Pin,
-    rc::Rc,
     task::{Context, Poll},
 };
 
-use js_sys::Object;
-use wasm_bindgen::JsValue;
-use wasm_bindgen_futures::spawn_local;
-use web_sys::FileSystemFileHandle;
+use futures_lite::future::BoxedLocal;
 
-use crate::{get_file, set_value, File, Fs, Task, FS, HANDLE, INDEX, OPEN, OPTIONS, POST_ERROR};
+use crate::{File, backend, locks::lock_path, normalize};
 
 const APPEND: u8 = 0b0000_0001;
 const CREATE: u8 = 0b0000_0010;
@@ -21,48 +16,23 @@ const CREATE_NEW: u8 = 0b0000_0100;
 const READ: u8 = 0b0000_1000;
 const TRUNCATE: u8 = 0b0001_0000;
 const WRITE: u8 = 0b0010_0000;
+const WAIT_FOR_LOCK: u8 = 0b0100_0000;
+#[derive(Debug, Clone, Copy)]
 pub struct OpenOptions(u8);
 
-impl Fs {
-    fn open(
-        &self,
-        handle: FileSystemFileHandle,
-        options: u8,
-        inner: Rc<RefCell<Task<Result<File>>>>,
-    ) {
-        let index = self.inner.borrow_mut().opening_tasks.insert(inner);
-
-        let open = Object::new();
-        set_value(&open, &INDEX, &JsValue::from(index));
-        set_value(&open, &HANDLE, &handle);
-        set_value(&open, &OPTIONS, &JsValue::from(options));
-        let msg = Object::new();
-        set_value(&msg, &OPEN, &open);
-
-        self.worker.post_message(&msg).expect(POST_ERROR);
+impl Default for OpenOptions {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 pub struct OpenFileFuture {
-    inner: Rc<RefCell<Task<Result<File>>>>,
-    append: bool,
+    inner: BoxedLocal<Result<File>>,
 }
 impl Future for OpenFileFuture {
     type Output = Result<File>;
-    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let mut inner = self.inner.borrow_mut();
-
-        if let Some(val) = inner.result.take() {
-            let result = val.map(|mut file| {
-                if self.append {
-                    file.cursor = file.size
-                }
-                file
-            });
-            return Poll::Ready(result);
-        }
-        inner.waker = Some(cx.waker().clone());
-        Poll::Pending
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.inner.as_mut().poll(cx)
     }
 }
 impl OpenOptions {
@@ -100,33 +70,68 @@ impl OpenOptions {
         self.set_bit(WRITE, write);
         self
     }
+    /// Wait until no other file opened with this option or locked with [`File::lock`] holds the path, instead of failing.
+    ///
+    /// Files opened for writing take an exclusive lock, others a shared one,
+    /// so readers in several tabs don't wait for each other.
+    /// The lock is held by the file until it is closed or dropped, and can be released early with [`File::unlock`].
+    /// Files are opened without waiting if the browser doesn't support the *Web Locks API*, as on native targets.
+    pub fn wait_for_lock(&mut self, wait_for_lock: bool) -> &mut OpenOptions {
+        self.set_bit(WAIT_FOR_LOCK, wait_for_lock);
+        self
+    }
+    pub fn is_append(&self) -> bool {
+        self.0 & APPEND > 0
+    }
+    pub fn is_create(&self) -> bool {
+        self.0 & CREATE > 0
+    }
+    pub fn is_create_new(&self) -> bool {
+        self.0 & CREATE_NEW > 0
+    }
+    pub fn is_read(&self) -> bool {
+        self.0 & READ > 0
+    }
+    pub fn is_truncate(&self) -> bool {
+        self.0 & TRUNCATE > 0
+    }
+    pub fn is_write(&self) -> bool {
+        self.0 & WRITE > 0
+    }
+    pub fn is_wait_for_lock(&self) -> bool {
+        self.0 & WAIT_FOR_LOCK > 0
+    }
+    /// The flags in the layout the worker expects.
+    pub(crate) fn bits(&self) -> u8 {
+        self.0
+    }
     pub fn open<P: AsRef<Path>>(&self, path: P) -> OpenFileFuture {
-        let path = path.as_ref().to_string_lossy().to_string();
-
-        let state = Task {
-            waker: None,
-            result: None,
-        };
-        let inner = Rc::new(RefCell::new(state));
-        let inner_clone = inner.clone();
-
-        let options = self.0;
-        spawn_local(async move {
-            let handle = get_file(path, options & CREATE | options & CREATE_NEW > 0).await;
-            match handle {
-                Ok(handle) => FS.with_borrow(|fs| fs.open(handle, options, inner_clone)),
-                Err(e) => {
-                    let mut state = inner_clone.borrow_mut();
-                    state.result = Some(Err(e));
-                    if let Some(waker) = state.waker.take() {
-                        waker.wake();
-                    }
+        let path = normalize(path);
+        let options = *self;
+        let inner = Box::pin(async move {
+            let path = path?;
+            let lock = if options.is_wait_for_lock() {
+                let shared = !(options.is_write()
+                    || options.is_append()
+                    || options.is_create()
+                    || options.is_create_new()
+                    || options.is_truncate());
+                match lock_path(&path, shared, false).await {
+                    Err(e) if e.kind() == ErrorKind::Unsupported => None,
+                    lock => lock?,
                 }
+            } else {
+                None
+            };
+            let backend = backend().await;
+            let (fd, size) = backend.open(&path, &options).await?;
+            let mut file = File::new(backend, fd, size, path);
+            *file.lock.get_mut() = lock;
+            if options.is_append() {
+                file.cursor = file.size
             }
+            Ok(file)
         });
-        OpenFileFuture {
-            inner,
-            append: self.0 & APPEND > 0,
-        }
+        OpenFileFuture { inner }
     }
 }
@@ -0,0 +1,894 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    io::{Error, ErrorKind, Result},
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::{Duration, SystemTime},
+};
+
+use futures_lite::{StreamExt, future::BoxedLocal};
+use js_sys::{Array, Object, Promise, Reflect, Uint8Array};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::{JsFuture, stream::JsStream};
+use web_sys::{
+    Blob, FileSystemDirectoryHandle, FileSystemFileHandle, FileSystemGetDirectoryOptions,
+    FileSystemGetFileOptions, FileSystemHandle, FileSystemRemoveOptions, WorkerGlobalScope, window,
+};
+
+use crate::{
+    BUF, Backend, BatchOp, BatchOutput, CURSOR, DIR, DYN_INTO_ERROR, ERROR, ERROR_NAME, FD, FILE,
+    FileType, GETTING_JS_FIELD_ERROR, IS_DIR, KIND, MODIFIED, Metadata, NAME, OPTIONS, OpenOptions,
+    SIZE, WRITABLE,
+    arena::Arena,
+    backend::unsupported,
+    dir_cache,
+    fs::{DirectFs, FS},
+    mount::{lookup, mount_points_in},
+    util::{
+        TaskFuture, get_value, get_value_as_f64, join_all, js_value_to_error, new_task, set_value,
+        unique_suffix,
+    },
+};
+
+// Kinds of the operations of a batch, matching the worker.
+const BATCH_OPEN: u32 = 0;
+const BATCH_READ: u32 = 1;
+const BATCH_WRITE: u32 = 2;
+const BATCH_WRITE_AT: u32 = 3;
+const BATCH_SET_LEN: u32 = 4;
+const BATCH_FLUSH: u32 = 5;
+const BATCH_STAT: u32 = 6;
+const BATCH_REMOVE: u32 = 7;
+
+/// An operation of a batch once the directory of its file is resolved.
+enum BatchStep {
+    /// Done without the worker, like the metadata of a mount point.
+    Done(Result<BatchOutput>),
+    /// Posted to the worker.
+    Worker(Object),
+}
+
+/// Where the worker finds the file of a batch operation.
+struct BatchFile {
+    /// Numbers the distinct paths of the batch, the worker keeps one sync access handle for each.
+    number: usize,
+    /// Whether an operation of the batch writes to the file, which is then opened in `readwrite` mode.
+    writable: bool,
+    /// The worker file descriptor of a [`File`](crate::File) that has it open already.
+    fd: Option<usize>,
+}
+
+/// Resolves the directory of the file of `op` and describes the operation for the worker.
+///
+/// No operation creates directories, so resolving them all first doesn't change the outcome of the batch.
+async fn batch_step(op: BatchOp, file: BatchFile) -> BatchStep {
+    let path = op.path().to_path_buf();
+    let Some((parent, name)) = split(&path) else {
+        // Mount points and the root are always directories.
+        return BatchStep::Done(match op {
+            BatchOp::Metadata { .. } => {
+                Ok(BatchOutput::Metadata(Metadata::new(FileType::Dir, 0, None)))
+            }
+            _ => Err(Error::from(ErrorKind::PermissionDenied)),
+        });
+    };
+    if (op.is_write() || matches!(op, BatchOp::Remove { .. }))
+        && let Err(e) = check_writable(&path)
+    {
+        return BatchStep::Done(Err(e));
+    }
+    let dir = match with_dir(parent, false, async |dir| Ok(dir)).await {
+        Ok(dir) => dir,
+        Err(e) => return BatchStep::Done(Err(e)),
+    };
+    let msg = Object::new();
+    set_value(&msg, &DIR, &dir);
+    set_value(&msg, &NAME, &JsValue::from(name));
+    set_value(&msg, &FILE, &JsValue::from(file.number));
+    set_value(&msg, &WRITABLE, &JsValue::from_bool(file.writable));
+    if let Some(fd) = file.fd {
+        set_value(&msg, &FD, &JsValue::from(fd));
+    }
+    let kind = match op {
+        BatchOp::Open { options, .. } => {
+            set_value(&msg, &OPTIONS, &JsValue::from(options.bits()));
+            BATCH_OPEN
+        }
+        BatchOp::Read { offset, len, .. } => {
+            set_value(&msg, &CURSOR, &JsValue::from(offset as f64));
+            if let Some(len) = len {
+                set_value(&msg, &SIZE, &JsValue::from(len as f64));
+            }
+            BATCH_READ
+        }
+        BatchOp::Write { data, .. } => {
+            set_value(&msg, &BUF, &Uint8Array::from(data.as_slice()).buffer());
+            BATCH_WRITE
+        }
+        BatchOp::WriteAt { offset, data, .. } => {
+            set_value(&msg, &CURSOR, &JsValue::from(offset as f64));
+            set_value(&msg, &BUF, &Uint8Array::from(data.as_slice()).buffer());
+            BATCH_WRITE_AT
+        }
+        BatchOp::SetLen { size, .. } => {
+            set_value(&msg, &SIZE, &JsValue::from(size as f64));
+            BATCH_SET_LEN
+        }
+        BatchOp::Flush { .. } => BATCH_FLUSH,
+        BatchOp::Metadata { .. } => BATCH_STAT,
+        BatchOp::Remove { .. } => BATCH_REMOVE,
+    };
+    set_value(&msg, &KIND, &JsValue::from(kind));
+    BatchStep::Worker(msg)
+}
+
+/// Maps the name of the `DOMException` thrown in the worker to an error kind.
+fn batch_error(name: Option<String>, message: String) -> Error {
+    let kind = match name.as_deref() {
+        Some("NotFoundError") => ErrorKind::NotFound,
+        Some("NotAllowedError" | "NoModificationAllowedError") => ErrorKind::PermissionDenied,
+        // Thrown by the worker itself for `create_new`.
+        Some("AlreadyExistsError") => ErrorKind::AlreadyExists,
+        Some("InvalidModificationError") => ErrorKind::DirectoryNotEmpty,
+        Some("TypeMismatchError") => ErrorKind::IsADirectory,
+        _ => ErrorKind::Other,
+    };
+    Error::new(kind, message)
+}
+
+/// Reads the result the worker posted for the operation `msg`.
+fn batch_output(msg: &Object, result: &JsValue) -> Result<BatchOutput> {
+    if let Some(error) = get_value(result, &ERROR).as_string() {
+        return Err(batch_error(
+            get_value(result, &ERROR_NAME).as_string(),
+            error,
+        ));
+    }
+    Ok(match get_value_as_f64(msg, &KIND) as u32 {
+        BATCH_READ => {
+            let buf = get_value(result, &BUF);
+            let size = get_value_as_f64(result, &SIZE) as u32;
+            BatchOutput::Data(Uint8Array::new_with_byte_offset_and_length(&buf, 0, size).to_vec())
+        }
+        BATCH_STAT if get_value(result, &IS_DIR).is_truthy() => {
+            BatchOutput::Metadata(Metadata::new(FileType::Dir, 0, None))
+        }
+        BATCH_STAT => {
+            let modified = get_value_as_f64(result, &MODIFIED) as u64;
+            BatchOutput::Metadata(Metadata::new(
+                FileType::File,
+                get_value_as_f64(result, &SIZE) as u64,
+                Some(SystemTime::UNIX_EPOCH + Duration::from_millis(modified)),
+            ))
+        }
+        _ => BatchOutput::Done,
+    })
+}
+
+pub(crate) async fn get_root() -> Result<FileSystemDirectoryHandle> {
+    let storage = if let Some(window) = window() {
+        let navigator = window.navigator();
+        navigator.storage()
+    } else if js_sys::global().is_instance_of::<WorkerGlobalScope>() {
+        let global = js_sys::global().unchecked_into::<WorkerGlobalScope>();
+        global.navigator().storage()
+    } else {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "unable to access browser storage",
+        ));
+    };
+    JsFuture::from(storage.get_directory())
+        .await
+        .map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "unable to get root directory",
+            )
+        })?
+        .dyn_into::<FileSystemDirectoryHandle>()
+        .map_err(|_| std::io::Error::new(std::io::ErrorKind::Unsupported, DYN_INTO_ERROR))
+}
+
+pub(crate) async fn child_dir(
+    parent: &FileSystemDirectoryHandle,
+    name: &str,
+    create: bool,
+) -> Result<FileSystemDirectoryHandle> {
+    let options = FileSystemGetDirectoryOptions::new();
+    options.set_create(create);
+    let result = JsFuture::from(parent.get_directory_handle_with_options(name, &options))
+        .await
+        .map_err(js_value_to_error)?
+        .dyn_into::<FileSystemDirectoryHandle>()
+        .expect(DYN_INTO_ERROR);
+    Ok(result)
+}
+
+pub(crate) async fn child_file(
+    parent: &FileSystemDirectoryHandle,
+    name: &str,
+    create: bool,
+) -> Result<FileSystemFileHandle> {
+    let options = FileSystemGetFileOptions::new();
+    options.set_create(create);
+    let result = JsFuture::from(parent.get_file_handle_with_options(name, &options))
+        .await
+        .map_err(js_value_to_error)?
+        .dyn_into::<FileSystemFileHandle>()
+        .expect(DYN_INTO_ERROR);
+    Ok(result)
+}
+
+/// Finds the mount with the longest mount point that `path` starts with,
+/// returning the mount point and its directory.
+/// Paths outside of every mount are in the root of OPFS.
+async fn resolve(path: &Path) -> Result<(PathBuf, FileSystemDirectoryHandle)> {
+    match lookup(path) {
+        Some((point, mount)) => Ok((point, mount.handle)),
+        None => Ok((PathBuf::new(), get_root().await?)),
+    }
+}
+
+/// Whether the file at `path` is inside OPFS, where it can be opened by the worker.
+fn in_opfs(path: &Path) -> bool {
+    lookup(path).is_none_or(|(_, mount)| mount.in_opfs)
+}
+
+/// Returns [`ErrorKind::PermissionDenied`] if `path` is in a read-only mount.
+fn check_writable(path: &Path) -> Result<()> {
+    if lookup(path).is_some_and(|(_, mount)| mount.read_only) {
+        return Err(Error::new(
+            ErrorKind::PermissionDenied,
+            "the path is in a read-only mount",
+        ));
+    }
+    Ok(())
+}
+
+/// Gets the directory at `path`, walking from the closest cached ancestor.
+async fn cached_dir(path: &Path, create: bool) -> Result<FileSystemDirectoryHandle> {
+    let point = lookup(path).map(|(point, _)| point).unwrap_or_default();
+    let (start, mut dir) = match dir_cache::closest(path, &point) {
+        Some(found) => found,
+        None => {
+            let (point, dir) = resolve(path).await?;
+            dir_cache::insert(point.clone(), dir.clone());
+            (point, dir)
+        }
+    };
+    let mut current = start.clone();
+    for component in path.strip_prefix(&start).unwrap().components() {
+        current.push(component);
+        dir = child_dir(&dir, &component.as_os_str().to_string_lossy(), create).await?;
+        dir_cache::insert(current.clone(), dir.clone());
+    }
+    Ok(dir)
+}
+
+/// Whether the directory of `dir` was removed, which makes listing it fail.
+async fn is_removed(dir: &FileSystemDirectoryHandle) -> bool {
+    match JsStream::from(dir.entries()).next().await {
+        Some(Err(e)) => js_value_to_error(e).kind() == ErrorKind::NotFound,
+        _ => false,
+    }
+}
+
+/// Whether the walk to `path` starts from a cached handle of a removed directory.
+///
+/// Only the closest cached ancestor is checked, as the walk never uses the ones above it.
+async fn is_stale(path: &Path) -> bool {
+    let point = lookup(path).map(|(point, _)| point).unwrap_or_default();
+    match dir_cache::closest(path, &point) {
+        Some((cached, dir)) if cached != point => is_removed(&dir).await,
+        _ => false,
+    }
+}
+
+/// Runs `f` on the directory at `path`.
+///
+/// If that fails with [`ErrorKind::NotFound`] because a cached handle belongs to a directory removed by another tab,
+/// the handles of `path` and its ancestors are forgotten and the walk is done again.
+/// Other misses, like a missing file in the directory, keep the cache.
+async fn with_dir<T>(
+    path: &Path,
+    create: bool,
+    f: impl AsyncFn(FileSystemDirectoryHandle) -> Result<T>,
+) -> Result<T> {
+    match async { f(cached_dir(path, create).await?).await }.await {
+        Err(e) if e.kind() == ErrorKind::NotFound && is_stale(path).await => {
+            dir_cache::forget_ancestors(path);
+            f(cached_dir(path, create).await?).await
+        }
+        result => result,
+    }
+}
+
+/// Splits `path` into its parent and name, or returns `None` for a mount point or the root.
+fn split(path: &Path) -> Option<(&Path, String)> {
+    if lookup(path).is_some_and(|(point, _)| point == path) {
+        return None;
+    }
+    Some((
+        path.parent()?,
+        path.file_name()?.to_string_lossy().into_owned(),
+    ))
+}
+
+/// Gets the directory containing `path`.
+///
+/// Returns [`ErrorKind::PermissionDenied`] for a mount point or the root,
+/// whose parents are not in the same directory tree.
+pub(crate) async fn get_parent_dir<P: AsRef<Path>>(
+    path: P,
+    create: bool,
+) -> Result<FileSystemDirectoryHandle> {
+    let (parent, _) = split(path.as_ref()).ok_or(Error::from(ErrorKind::PermissionDenied))?;
+    with_dir(parent, create, async |dir| Ok(dir)).await
+}
+
+pub(crate) async fn get_dir<P: AsRef<Path>>(
+    path: P,
+    create: bool,
+    create_parents: bool,
+) -> Result<FileSystemDirectoryHandle> {
+    let path = path.as_ref();
+    match split(path) {
+        Some((parent, name)) => {
+            with_dir(parent, create_parents, async |parent_dir| {
+                let dir = child_dir(&parent_dir, &name, create).await?;
+                dir_cache::insert(path.to_path_buf(), dir.clone());
+                Ok(dir)
+            })
+            .await
+        }
+        None => cached_dir(path, false).await,
+    }
+}
+
+pub(crate) async fn get_file<P: AsRef<Path>>(
+    path: P,
+    create: bool,
+) -> Result<FileSystemFileHandle> {
+    match split(path.as_ref()) {
+        Some((parent, name)) => {
+            with_dir(parent, false, async |parent_dir| {
+                child_file(&parent_dir, &name, create).await
+            })
+            .await
+        }
+        None => Err(Error::from(ErrorKind::AlreadyExists)),
+    }
+}
+
+#[wasm_bindgen]
+extern "C" {
+    /// `FileSystemHandle.move()` is not exposed by web-sys yet.
+    #[wasm_bindgen(extends = FileSystemHandle)]
+    type MovableHandle;
+    #[wasm_bindgen(method, js_name = "move")]
+    fn move_to(this: &MovableHandle, parent: &FileSystemDirectoryHandle, name: &str) -> Promise;
+}
+
+pub(crate) async fn child_handle(
+    parent: &FileSystemDirectoryHandle,
+    name: &str,
+) -> Result<FileSystemHandle> {
+    match child_file(parent, name, false).await {
+        Ok(handle) => Ok(handle.into()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Err(e),
+        // Getting a directory as a file fails with `TypeMismatchError`.
+        Err(_) => Ok(child_dir(parent, name, false).await?.into()),
+    }
+}
+
+pub(crate) async fn get_handle(path: &Path) -> Result<FileSystemHandle> {
+    match split(path) {
+        Some((parent, name)) => {
+            with_dir(parent, false, async |parent_dir| {
+                child_handle(&parent_dir, &name).await
+            })
+            .await
+        }
+        None => Ok(cached_dir(path, false).await?.into()),
+    }
+}
+
+/// Lists the entries of `dir`.
+async fn list_dir(dir: FileSystemDirectoryHandle) -> Result<Vec<(String, FileType)>> {
+    let mut stream = JsStream::from(dir.entries());
+    let mut entries = Vec::new();
+    while let Some(entry) = stream.next().await {
+        let entry = entry.map_err(js_value_to_error)?;
+        const RESOLVE_ENTRY_ERROR: &str =
+            "Getting the key and value of the dir entry failed, this is an error of the crate.";
+        let key = Reflect::get_u32(&entry, 0)
+            .expect(RESOLVE_ENTRY_ERROR)
+            .as_string()
+            .expect("This is supposed to be a string, else this is an error of the crate.");
+        let value = Reflect::get_u32(&entry, 1).expect(RESOLVE_ENTRY_ERROR);
+        if value.dyn_ref::<FileSystemFileHandle>().is_some() {
+            entries.push((key, FileType::File));
+        } else {
+            entries.push((key, FileType::Dir));
+        }
+    }
+    Ok(entries)
+}
+
+async fn file_metadata(handle: &FileSystemFileHandle) -> Result<Metadata> {
+    let file = JsFuture::from(handle.get_file())
+        .await
+        .map_err(js_value_to_error)?
+        .dyn_into::<web_sys::File>()
+        .expect(DYN_INTO_ERROR);
+    let modified = SystemTime::UNIX_EPOCH + Duration::from_millis(file.last_modified() as u64);
+    Ok(Metadata::new(
+        FileType::File,
+        file.size() as u64,
+        Some(modified),
+    ))
+}
+
+/// Whether the worker can open a `FileSystemSyncAccessHandle` of a file in OPFS.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub(crate) async fn sync_access_handles_usable() -> bool {
+    if !Reflect::has(&js_sys::global(), &JsValue::from_str("Worker")).unwrap_or(false) {
+        return false;
+    }
+    let Ok(root) = get_root().await else {
+        return false;
+    };
+    // Unique so that a probe of another tab holding its handle can't make this one fail.
+    let name = format!(".web-fs-probe-{}", unique_suffix());
+    let Ok(handle) = child_file(&root, &name, true).await else {
+        return false;
+    };
+    let task = new_task();
+    let usable = match FS.with_borrow(|fs| fs.probe(handle, task.clone())) {
+        Ok(()) => TaskFuture(task).await.is_ok(),
+        Err(_) => false,
+    };
+    let _ = JsFuture::from(root.remove_entry(&name)).await;
+    usable
+}
+
+/// Whether files in OPFS can be written with `createWritable()` from the current thread.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub(crate) async fn writables_usable() -> bool {
+    let Ok(class) = Reflect::get(
+        &js_sys::global(),
+        &JsValue::from_str("FileSystemFileHandle"),
+    ) else {
+        return false;
+    };
+    if class.is_undefined() {
+        return false;
+    }
+    let prototype =
+        Reflect::get(&class, &JsValue::from_str("prototype")).expect(GETTING_JS_FIELD_ERROR);
+    Reflect::has(&prototype, &JsValue::from_str("createWritable")).unwrap_or(false)
+        && get_root().await.is_ok()
+}
+
+/// The default [`Backend`], storing files in the *Origin Private File System*,
+/// or in directories mounted with [`mount`](crate::mount).
+///
+/// File content is accessed through `FileSystemSyncAccessHandle`s in a web worker,
+/// or from the current thread with [`OpfsBackend::main_thread`].
+///
+/// The handles of recently used directories are cached,
+/// so opening a file in a deep tree doesn't walk it from the root every time.
+/// They are forgotten when the directory is removed or renamed,
+/// and looked up again when an operation fails with [`ErrorKind::NotFound`],
+/// in case another tab removed it.
+pub struct OpfsBackend {
+    main_thread: bool,
+    direct: Rc<DirectFs>,
+    opened: Rc<RefCell<Arena<Fd>>>,
+    /// The paths of the files opened by the worker, by worker file descriptor, for batches to use their handles.
+    worker_paths: Rc<RefCell<HashMap<usize, PathBuf>>>,
+}
+
+/// Where an opened file lives, with its file descriptor there.
+#[derive(Clone, Copy)]
+enum Fd {
+    Worker(usize),
+    Direct(usize),
+}
+
+impl OpfsBackend {
+    pub fn new() -> Self {
+        Self {
+            main_thread: false,
+            direct: Rc::new(DirectFs::new()),
+            opened: Rc::new(RefCell::new(Arena::new())),
+            worker_paths: Rc::new(RefCell::new(HashMap::new())),
+        }
+    }
+    /// A degraded mode for where the worker can't be used,
+    /// it is selected automatically if a `FileSystemFileHandle` can't be posted to the worker.
+    ///
+    /// Reads use `FileSystemFileHandle.getFile()` and writes use `FileSystemWritableFileStream`.
+    /// Such a stream writes to a swap file which replaces the file only when it is closed,
+    /// so writes become visible and durable only on [`flush`](futures_lite::AsyncWriteExt::flush),
+    /// [`close`](futures_lite::AsyncWriteExt::close) or drop.
+    /// Until then, reads return the content as of the last flush, and a crash loses the writes.
+    /// Each flush copies the whole file into a new swap file, so it is expensive for large files.
+    pub fn main_thread() -> Self {
+        Self {
+            main_thread: true,
+            ..Self::new()
+        }
+    }
+    fn fd(&self, fd: usize) -> Fd {
+        *self
+            .opened
+            .borrow()
+            .get(fd)
+            .expect("Using a closed file descriptor, this is an error of the crate.")
+    }
+}
+
+impl Default for OpfsBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Backend for OpfsBackend {
+    fn open(&self, path: &Path, options: &OpenOptions) -> BoxedLocal<Result<(usize, u64)>> {
+        let path = path.to_path_buf();
+        let options = *options;
+        let direct = (self.main_thread || !in_opfs(&path)).then(|| self.direct.clone());
+        let opened = self.opened.clone();
+        let worker_paths = self.worker_paths.clone();
+        Box::pin(async move {
+            if options.is_write()
+                || options.is_append()
+                || options.is_create()
+                || options.is_create_new()
+                || options.is_truncate()
+            {
+                check_writable(&path)?;
+            }
+            if options.is_create_new() {
+                match get_file(&path, false).await {
+                    Ok(_) => return Err(Error::from(ErrorKind::AlreadyExists)),
+                    Err(e) if e.kind() == ErrorKind::NotFound => (),
+                    Err(e) => return Err(e),
+                }
+            }
+            let handle = get_file(&path, options.is_create() || options.is_create_new()).await?;
+            let (fd, size) = if let Some(direct) = direct {
+                let (fd, size) = direct.open(handle, options).await?;
+                (Fd::Direct(fd), size)
+            } else {
+                let task = new_task();
+                FS.with_borrow(|fs| fs.open(handle, options.bits(), task.clone()));
+                let (fd, size) = TaskFuture(task).await?;
+                worker_paths.borrow_mut().insert(fd, path);
+                (Fd::Worker(fd), size)
+            };
+            Ok((opened.borrow_mut().insert(fd), size))
+        })
+    }
+    fn read(&self, fd: usize, cursor: u64, len: usize) -> BoxedLocal<Result<Vec<u8>>> {
+        let fd = match self.fd(fd) {
+            Fd::Worker(fd) => fd,
+            Fd::Direct(fd) => {
+                let direct = self.direct.clone();
+                return Box::pin(async move { direct.read(fd, cursor, len).await });
+            }
+        };
+        let task = new_task();
+        FS.with_borrow(|fs| fs.read(fd, len, cursor, task.clone()));
+        Box::pin(async move {
+            let result = TaskFuture(task).await?;
+            Ok(
+                Uint8Array::new_with_byte_offset_and_length(&result.buf, 0, result.size as u32)
+                    .to_vec(),
+            )
+        })
+    }
+    /// The worker handles reads in the order they are posted.
+    fn concurrent_reads(&self) -> bool {
+        true
+    }
+    fn write(&self, fd: usize, cursor: u64, buf: &[u8]) -> BoxedLocal<Result<usize>> {
+        let fd = match self.fd(fd) {
+            Fd::Worker(fd) => fd,
+            Fd::Direct(fd) => {
+                let direct = self.direct.clone();
+                let buf = buf.to_vec();
+                return Box::pin(async move { direct.write(fd, cursor, buf).await });
+            }
+        };
+        let task = new_task();
+        FS.with_borrow(|fs| fs.write(fd, buf, cursor, task.clone()));
+        Box::pin(TaskFuture(task))
+    }
+    fn flush(&self, fd: usize) -> BoxedLocal<Result<()>> {
+        let fd = match self.fd(fd) {
+            Fd::Worker(fd) => fd,
+            Fd::Direct(fd) => {
+                let direct = self.direct.clone();
+                return Box::pin(async move { direct.flush(fd).await });
+            }
+        };
+        let task = new_task();
+        FS.with_borrow(|fs| fs.flush(fd, task.clone()));
+        Box::pin(TaskFuture(task))
+    }
+    fn truncate(&self, fd: usize, size: u64) -> BoxedLocal<Result<()>> {
+        let fd = match self.fd(fd) {
+            Fd::Worker(fd) => fd,
+            Fd::Direct(fd) => {
+                let direct = self.direct.clone();
+                return Box::pin(async move { direct.truncate(fd, size).await });
+            }
+        };
+        let task = new_task();
+        FS.with_borrow(|fs| fs.truncate(fd, size, task.clone()));
+        Box::pin(TaskFuture(task))
+    }
+    fn close(&self, fd: usize) -> BoxedLocal<Result<()>> {
+        let fd = match self.fd(fd) {
+            Fd::Worker(fd) => fd,
+            Fd::Direct(fd) => {
+                let direct = self.direct.clone();
+                return Box::pin(async move { direct.close(fd).await });
+            }
+        };
+        let task = new_task();
+        FS.with_borrow(|fs| fs.close(fd, task.clone()));
+        Box::pin(TaskFuture(task))
+    }
+    fn release(&self, fd: usize) {
+        match self.opened.borrow_mut().remove(fd) {
+            Some(Fd::Worker(fd)) => {
+                self.worker_paths.borrow_mut().remove(&fd);
+                FS.with_borrow(|fs| fs.drop_file(fd));
+            }
+            Some(Fd::Direct(fd)) => self.direct.release(fd),
+            None => (),
+        }
+    }
+    fn stat(&self, path: &Path) -> BoxedLocal<Result<Metadata>> {
+        let path = path.to_path_buf();
+        Box::pin(async move {
+            let handle = get_handle(&path).await?;
+            if let Some(file) = handle.dyn_ref::<FileSystemFileHandle>() {
+                file_metadata(file).await
+            } else {
+                Ok(Metadata::new(FileType::Dir, 0, None))
+            }
+        })
+    }
+    fn list(&self, path: &Path) -> BoxedLocal<Result<Vec<(String, FileType)>>> {
+        let path = path.to_path_buf();
+        Box::pin(async move {
+            let mount_points = mount_points_in(&path);
+            let mut entries = with_dir(&path, false, list_dir).await?;
+            for name in mount_points {
+                if !entries.iter().any(|(key, _)| *key == name) {
+                    entries.push((name, FileType::Dir));
+                }
+            }
+            Ok(entries)
+        })
+    }
+    fn create_dir(&self, path: &Path, recursive: bool) -> BoxedLocal<Result<()>> {
+        let path = path.to_path_buf();
+        Box::pin(async move {
+            check_writable(&path)?;
+            get_dir(path, true, recursive).await?;
+            Ok(())
+        })
+    }
+    fn remove(&self, path: &Path, recursive: bool) -> BoxedLocal<Result<()>> {
+        let path = path.to_path_buf();
+        Box::pin(async move {
+            check_writable(&path)?;
+            let (parent, name) = split(&path).ok_or(Error::from(ErrorKind::PermissionDenied))?;
+
+            let options = FileSystemRemoveOptions::new();
+            options.set_recursive(recursive);
+
+            let result = with_dir(parent, false, async |parent_dir| {
+                JsFuture::from(parent_dir.remove_entry_with_options(&name, &options))
+                    .await
+                    .map_err(js_value_to_error)?;
+                Ok(())
+            })
+            .await;
+            dir_cache::invalidate(&path);
+            result
+        })
+    }
+    /// This relies on `FileSystemHandle.move()`,
+    /// returns [`ErrorKind::Unsupported`] if the browser doesn't provide it,
+    /// and [`ErrorKind::CrossesDevices`] if `from` and `to` are in different mounts.
+    fn rename(&self, from: &Path, to: &Path) -> BoxedLocal<Result<()>> {
+        let from = from.to_path_buf();
+        let to = to.to_path_buf();
+        Box::pin(async move {
+            check_writable(&from)?;
+            check_writable(&to)?;
+            let device = |path: &Path| lookup(path).map(|(point, _)| point);
+            if device(&from) != device(&to) {
+                return Err(Error::new(
+                    ErrorKind::CrossesDevices,
+                    "renaming between two mounts is not supported",
+                ));
+            }
+            let from_parent = get_parent_dir(&from, false).await?;
+            let from_name = from
+                .file_name()
+                .ok_or(Error::from(ErrorKind::NotFound))?
+                .to_string_lossy();
+            let handle = child_handle(&from_parent, &from_name)
+                .await?
+                .unchecked_into::<MovableHandle>();
+            if !Reflect::get(&handle, &JsValue::from_str("move"))
+                .expect(GETTING_JS_FIELD_ERROR)
+                .is_function()
+            {
+                return Err(Error::new(
+                    ErrorKind::Unsupported,
+                    "FileSystemHandle.move() is not supported by this browser",
+                ));
+            }
+
+            let to_parent = get_parent_dir(&to, false).await?;
+            let to_name = to
+                .file_name()
+                .ok_or(Error::from(ErrorKind::InvalidInput))?
+                .to_string_lossy();
+            let result = JsFuture::from(handle.move_to(&to_parent, &to_name)).await;
+            dir_cache::invalidate(&from);
+            dir_cache::invalidate(&to);
+            result.map_err(js_value_to_error)?;
+            Ok(())
+        })
+    }
+    /// The bytes are moved inside the worker and never pass through wasm memory.
+    fn copy(&self, src: usize, dst: usize) -> BoxedLocal<Result<u64>> {
+        let (Fd::Worker(src), Fd::Worker(dst)) = (self.fd(src), self.fd(dst)) else {
+            return unsupported();
+        };
+        let task = new_task();
+        FS.with_borrow(|fs| fs.copy(src, dst, task.clone()));
+        Box::pin(TaskFuture(task))
+    }
+    /// The blob is posted to the worker and never passes through wasm memory.
+    fn write_blob(&self, fd: usize, cursor: u64, blob: &Blob) -> BoxedLocal<Result<u64>> {
+        let fd = match self.fd(fd) {
+            Fd::Worker(fd) => fd,
+            Fd::Direct(fd) => {
+                let direct = self.direct.clone();
+                let blob = blob.clone();
+                return Box::pin(async move { direct.write_blob(fd, cursor, blob).await });
+            }
+        };
+        let task = new_task();
+        FS.with_borrow(|fs| fs.write_blob(fd, blob, cursor, task.clone()));
+        Box::pin(TaskFuture(task))
+    }
+    /// Files opened in the main thread use `getFile()`,
+    /// others are flushed by the worker, which posts back the `File` from `getFile()`.
+    fn blob(&self, fd: usize) -> BoxedLocal<Result<Blob>> {
+        let fd = match self.fd(fd) {
+            Fd::Worker(fd) => fd,
+            Fd::Direct(fd) => {
+                let direct = self.direct.clone();
+                return Box::pin(async move { direct.blob(fd).await });
+            }
+        };
+        let task = new_task();
+        FS.with_borrow(|fs| fs.to_blob(fd, task.clone()));
+        Box::pin(TaskFuture(task))
+    }
+    /// The directories of the files are resolved concurrently,
+    /// then every operation is posted to the worker in one message and run there in order.
+    ///
+    /// Returns [`ErrorKind::Unsupported`] in the main thread mode or for paths outside OPFS.
+    fn batch(&self, ops: &[BatchOp]) -> BoxedLocal<Result<Vec<Result<BatchOutput>>>> {
+        if self.main_thread || ops.iter().any(|op| !in_opfs(op.path())) {
+            return unsupported();
+        }
+        let mut files: HashMap<&Path, BatchFile> = HashMap::new();
+        for op in ops {
+            let count = files.len();
+            let file = files.entry(op.path()).or_insert_with(|| BatchFile {
+                number: count,
+                writable: false,
+                fd: self
+                    .worker_paths
+                    .borrow()
+                    .iter()
+                    .find(|(_, path)| path.as_path() == op.path())
+                    .map(|(fd, _)| *fd),
+            });
+            file.writable |= op.is_write();
+        }
+        let steps: Vec<_> = ops
+            .iter()
+            .map(|op| {
+                let file = &files[op.path()];
+                let file = BatchFile {
+                    number: file.number,
+                    writable: file.writable,
+                    fd: file.fd,
+                };
+                Box::pin(batch_step(op.clone(), file)) as BoxedLocal<BatchStep>
+            })
+            .collect();
+        Box::pin(async move {
+            let steps = join_all(steps).await;
+            let worker_ops = Array::new();
+            for step in &steps {
+                if let BatchStep::Worker(msg) = step {
+                    worker_ops.push(msg);
+                }
+            }
+            let mut worker_results = if worker_ops.length() > 0 {
+                let task = new_task();
+                FS.with_borrow(|fs| fs.batch(&worker_ops, task.clone()));
+                TaskFuture(task).await?.into_iter()
+            } else {
+                Array::new().into_iter()
+            };
+            Ok(steps
+                .into_iter()
+                .map(|step| match step {
+                    BatchStep::Done(output) => output,
+                    BatchStep::Worker(msg) => {
+                        let result = worker_results.next().expect(
+                            "The worker returned too few results, this is an error of the crate.",
+                        );
+                        batch_output(&msg, &result)
+                    }
+                })
+                .collect())
+        })
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use wasm_bindgen_test::*;
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+    wasm_bindgen_test_configure!(run_in_dedicated_worker);
+
+    #[wasm_bindgen_test]
+    async fn leaf_miss_keeps_cached_dirs() {
+        crate::set_backend(OpfsBackend::new());
+        crate::create_dir_all("leaf_miss/dir").await.unwrap();
+        let dir = Path::new("leaf_miss/dir");
+        get_dir(dir, false, false).await.unwrap();
+        assert!(dir_cache::contains(dir));
+
+        let error = get_file("leaf_miss/dir/missing", false).await.unwrap_err();
+        assert_eq!(ErrorKind::NotFound, error.kind());
+        assert!(dir_cache::contains(dir));
+        assert!(dir_cache::contains(Path::new("leaf_miss")));
+
+        // Removed behind the cache, as by another tab.
+        let parent = get_dir("leaf_miss", false, false).await.unwrap();
+        let options = FileSystemRemoveOptions::new();
+        options.set_recursive(true);
+        JsFuture::from(parent.remove_entry_with_options("dir", &options))
+            .await
+            .unwrap();
+        let error = get_file("leaf_miss/dir/missing", false).await.unwrap_err();
+        assert_eq!(ErrorKind::NotFound, error.kind());
+        assert!(!dir_cache::contains(dir));
+
+        crate::remove_dir_all("leaf_miss").await.unwrap();
+    }
+}
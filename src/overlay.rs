@@ -0,0 +1,415 @@
+use std::{
+    cell::RefCell,
+    collections::HashSet,
+    io::{Error, ErrorKind, Result},
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use futures_lite::future::BoxedLocal;
+use web_sys::Blob;
+
+use crate::{
+    Backend, COPY_BUF_SIZE, FileType, Metadata, OpenOptions, arena::Arena, backend::unsupported,
+};
+
+/// Names of whiteout markers start with this, followed by the name of what they hide.
+pub const WHITEOUT_PREFIX: &str = ".wh.";
+
+#[derive(Clone, Copy)]
+enum Layer {
+    Upper(usize),
+    Lower(usize),
+}
+
+struct OverlayInner {
+    upper: Rc<dyn Backend>,
+    lower: Rc<dyn Backend>,
+    opened: RefCell<Arena<Layer>>,
+}
+
+/// A [`Backend`] showing a writable upper backend on top of a read-only lower one,
+/// like the overlay file system of Linux.
+///
+/// It suits default content that users can modify,
+/// for example [`OpfsBackend`](crate::OpfsBackend) on top of a [`MemoryBackend`](crate::MemoryBackend)
+/// filled with bundled templates.
+/// The lower backend is never modified:
+/// - Files and directories of the upper backend hide those at the same paths in the lower one,
+///   and directories existing in both are merged.
+/// - Opening a file of the lower backend for writing first copies it up to the upper backend.
+/// - Removing something of the lower backend creates a whiteout marker in the upper backend,
+///   an empty file named [`WHITEOUT_PREFIX`] followed by the name of what is removed,
+///   which hides it and everything under it. Markers are not listed.
+/// - Renaming a directory that exists in the lower backend fails with [`ErrorKind::CrossesDevices`],
+///   as with Linux by default.
+pub struct OverlayBackend {
+    inner: Rc<OverlayInner>,
+}
+
+impl OverlayBackend {
+    pub fn new<U: Backend, L: Backend>(upper: U, lower: L) -> Self {
+        Self {
+            inner: Rc::new(OverlayInner {
+                upper: Rc::new(upper),
+                lower: Rc::new(lower),
+                opened: RefCell::new(Arena::new()),
+            }),
+        }
+    }
+}
+
+fn whiteout_of(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{WHITEOUT_PREFIX}{name}"))
+}
+
+fn not_found() -> Error {
+    Error::from(ErrorKind::NotFound)
+}
+
+/// The metadata of `path` in `backend`, or `None` if it doesn't exist.
+async fn stat_in(backend: &dyn Backend, path: &Path) -> Result<Option<Metadata>> {
+    match backend.stat(path).await {
+        Ok(metadata) => Ok(Some(metadata)),
+        Err(e) if matches!(e.kind(), ErrorKind::NotFound | ErrorKind::NotADirectory) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+async fn create_parents(backend: &dyn Backend, path: &Path) -> Result<()> {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => backend.create_dir(parent, true).await,
+        _ => Ok(()),
+    }
+}
+
+impl OverlayInner {
+    fn fd(&self, fd: usize) -> Layer {
+        *self
+            .opened
+            .borrow()
+            .get(fd)
+            .expect("Using a closed file descriptor, this is an error of the crate.")
+    }
+    /// Whether neither `path` nor any of its ancestors is whited out.
+    async fn lower_visible(&self, path: &Path) -> Result<bool> {
+        let mut prefix = PathBuf::new();
+        for name in path.iter() {
+            prefix.push(name);
+            if stat_in(&*self.upper, &whiteout_of(&prefix))
+                .await?
+                .is_some()
+            {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+    async fn lower_stat(&self, path: &Path) -> Result<Option<Metadata>> {
+        if !self.lower_visible(path).await? {
+            return Ok(None);
+        }
+        stat_in(&*self.lower, path).await
+    }
+    async fn stat(&self, path: &Path) -> Result<Option<Metadata>> {
+        match stat_in(&*self.upper, path).await? {
+            Some(metadata) => Ok(Some(metadata)),
+            None => self.lower_stat(path).await,
+        }
+    }
+    /// Fails with [`ErrorKind::NotFound`] unless the parent of `path` is a directory.
+    async fn check_parent(&self, path: &Path) -> Result<()> {
+        match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => match self.stat(parent).await? {
+                Some(metadata) if metadata.is_dir() => Ok(()),
+                _ => Err(not_found()),
+            },
+            _ => Ok(()),
+        }
+    }
+    async fn whiteout(&self, path: &Path) -> Result<()> {
+        create_parents(&*self.upper, path).await?;
+        let (fd, _) = self
+            .upper
+            .open(
+                &whiteout_of(path),
+                OpenOptions::new().write(true).create(true),
+            )
+            .await?;
+        let result = self.upper.close(fd).await;
+        self.upper.release(fd);
+        result
+    }
+    /// Copies the file at `path` from the lower backend to the upper one.
+    async fn copy_up(&self, path: &Path) -> Result<()> {
+        create_parents(&*self.upper, path).await?;
+        let (src, _) = self.lower.open(path, OpenOptions::new().read(true)).await?;
+        let copied = async {
+            let (dst, _) = self
+                .upper
+                .open(
+                    path,
+                    OpenOptions::new().write(true).create(true).truncate(true),
+                )
+                .await?;
+            let copied = async {
+                let mut cursor = 0;
+                loop {
+                    let buf = self.lower.read(src, cursor, COPY_BUF_SIZE as usize).await?;
+                    if buf.is_empty() {
+                        break;
+                    }
+                    self.upper.write(dst, cursor, &buf).await?;
+                    cursor += buf.len() as u64;
+                }
+                self.upper.close(dst).await
+            }
+            .await;
+            self.upper.release(dst);
+            copied
+        }
+        .await;
+        let _ = self.lower.close(src).await;
+        self.lower.release(src);
+        copied
+    }
+    async fn open(&self, path: &Path, options: OpenOptions) -> Result<(usize, u64)> {
+        let modifying = options.is_write() || options.is_append() || options.is_truncate();
+        let (layer, size) = if stat_in(&*self.upper, path).await?.is_some() {
+            if options.is_create_new() {
+                return Err(Error::from(ErrorKind::AlreadyExists));
+            }
+            let (fd, size) = self.upper.open(path, &options).await?;
+            (Layer::Upper(fd), size)
+        } else if let Some(metadata) = self.lower_stat(path).await? {
+            if options.is_create_new() {
+                return Err(Error::from(ErrorKind::AlreadyExists));
+            }
+            if !modifying || metadata.is_dir() {
+                let (fd, size) = self.lower.open(path, OpenOptions::new().read(true)).await?;
+                (Layer::Lower(fd), size)
+            } else {
+                if options.is_truncate() {
+                    create_parents(&*self.upper, path).await?;
+                } else {
+                    self.copy_up(path).await?;
+                }
+                let mut options = options;
+                options.create(true);
+                let (fd, size) = self.upper.open(path, &options).await?;
+                (Layer::Upper(fd), size)
+            }
+        } else {
+            if !options.is_create() && !options.is_create_new() {
+                return Err(not_found());
+            }
+            self.check_parent(path).await?;
+            create_parents(&*self.upper, path).await?;
+            let (fd, size) = self.upper.open(path, &options).await?;
+            (Layer::Upper(fd), size)
+        };
+        Ok((self.opened.borrow_mut().insert(layer), size))
+    }
+    async fn list(&self, path: &Path) -> Result<Vec<(String, FileType)>> {
+        let upper = match self.upper.list(path).await {
+            Ok(entries) => Some(entries),
+            Err(e) if e.kind() == ErrorKind::NotFound => None,
+            Err(e) => return Err(e),
+        };
+        let lower = if self.lower_visible(path).await? {
+            match self.lower.list(path).await {
+                Ok(entries) => Some(entries),
+                Err(e) if e.kind() == ErrorKind::NotFound => None,
+                Err(e) => return Err(e),
+            }
+        } else {
+            None
+        };
+        if upper.is_none() && lower.is_none() {
+            return Err(not_found());
+        }
+        let mut entries = Vec::new();
+        let mut hidden = HashSet::new();
+        for (name, file_type) in upper.unwrap_or_default() {
+            match name.strip_prefix(WHITEOUT_PREFIX) {
+                Some(whited_out) => {
+                    hidden.insert(whited_out.to_string());
+                }
+                None => {
+                    hidden.insert(name.clone());
+                    entries.push((name, file_type));
+                }
+            }
+        }
+        for (name, file_type) in lower.unwrap_or_default() {
+            if !hidden.contains(&name) {
+                entries.push((name, file_type));
+            }
+        }
+        Ok(entries)
+    }
+    async fn create_dir(&self, path: &Path, recursive: bool) -> Result<()> {
+        match self.stat(path).await? {
+            Some(metadata) if metadata.is_dir() => return Ok(()),
+            Some(_) => return Err(Error::from(ErrorKind::AlreadyExists)),
+            None => (),
+        }
+        if !recursive {
+            self.check_parent(path).await?;
+        }
+        self.upper.create_dir(path, true).await
+    }
+    async fn remove(&self, path: &Path, recursive: bool) -> Result<()> {
+        if path.as_os_str().is_empty() {
+            return Err(Error::new(
+                ErrorKind::PermissionDenied,
+                "the root can't be removed",
+            ));
+        }
+        let metadata = self.stat(path).await?.ok_or_else(not_found)?;
+        if metadata.is_dir() && !recursive && !self.list(path).await?.is_empty() {
+            return Err(Error::from(ErrorKind::DirectoryNotEmpty));
+        }
+        if stat_in(&*self.upper, path).await?.is_some() {
+            // Recursive for the whiteout markers inside.
+            self.upper.remove(path, true).await?;
+        }
+        if self.lower_stat(path).await?.is_some() {
+            self.whiteout(path).await?;
+        }
+        Ok(())
+    }
+    async fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        let metadata = self.stat(from).await?.ok_or_else(not_found)?;
+        let from_in_lower = self.lower_stat(from).await?.is_some();
+        if metadata.is_dir() && from_in_lower {
+            return Err(Error::new(
+                ErrorKind::CrossesDevices,
+                "directories of the lower layer can't be renamed",
+            ));
+        }
+        self.check_parent(to).await?;
+        create_parents(&*self.upper, to).await?;
+        if stat_in(&*self.upper, from).await?.is_none() {
+            self.copy_up(from).await?;
+        }
+        let to_in_lower = self.lower_stat(to).await?.is_some();
+        self.upper.rename(from, to).await?;
+        if from_in_lower {
+            self.whiteout(from).await?;
+        }
+        if to_in_lower {
+            self.whiteout(to).await?;
+        }
+        Ok(())
+    }
+}
+
+impl Backend for OverlayBackend {
+    fn open(&self, path: &Path, options: &OpenOptions) -> BoxedLocal<Result<(usize, u64)>> {
+        let inner = self.inner.clone();
+        let path = path.to_path_buf();
+        let options = *options;
+        Box::pin(async move { inner.open(&path, options).await })
+    }
+    fn read(&self, fd: usize, cursor: u64, len: usize) -> BoxedLocal<Result<Vec<u8>>> {
+        match self.inner.fd(fd) {
+            Layer::Upper(fd) => self.inner.upper.read(fd, cursor, len),
+            Layer::Lower(fd) => self.inner.lower.read(fd, cursor, len),
+        }
+    }
+    fn concurrent_reads(&self) -> bool {
+        self.inner.upper.concurrent_reads() && self.inner.lower.concurrent_reads()
+    }
+    fn write(&self, fd: usize, cursor: u64, buf: &[u8]) -> BoxedLocal<Result<usize>> {
+        match self.inner.fd(fd) {
+            Layer::Upper(fd) => self.inner.upper.write(fd, cursor, buf),
+            Layer::Lower(_) => Box::pin(async {
+                Err(Error::new(
+                    ErrorKind::PermissionDenied,
+                    "the file is not opened for writing",
+                ))
+            }),
+        }
+    }
+    fn flush(&self, fd: usize) -> BoxedLocal<Result<()>> {
+        match self.inner.fd(fd) {
+            Layer::Upper(fd) => self.inner.upper.flush(fd),
+            Layer::Lower(fd) => self.inner.lower.flush(fd),
+        }
+    }
+    fn truncate(&self, fd: usize, size: u64) -> BoxedLocal<Result<()>> {
+        match self.inner.fd(fd) {
+            Layer::Upper(fd) => self.inner.upper.truncate(fd, size),
+            Layer::Lower(_) => Box::pin(async {
+                Err(Error::new(
+                    ErrorKind::PermissionDenied,
+                    "the file is not opened for writing",
+                ))
+            }),
+        }
+    }
+    fn close(&self, fd: usize) -> BoxedLocal<Result<()>> {
+        match self.inner.fd(fd) {
+            Layer::Upper(fd) => self.inner.upper.close(fd),
+            Layer::Lower(fd) => self.inner.lower.close(fd),
+        }
+    }
+    fn release(&self, fd: usize) {
+        match self.inner.opened.borrow_mut().remove(fd) {
+            Some(Layer::Upper(fd)) => self.inner.upper.release(fd),
+            Some(Layer::Lower(fd)) => self.inner.lower.release(fd),
+            None => (),
+        }
+    }
+    fn stat(&self, path: &Path) -> BoxedLocal<Result<Metadata>> {
+        let inner = self.inner.clone();
+        let path = path.to_path_buf();
+        Box::pin(async move { inner.stat(&path).await?.ok_or_else(not_found) })
+    }
+    fn list(&self, path: &Path) -> BoxedLocal<Result<Vec<(String, FileType)>>> {
+        let inner = self.inner.clone();
+        let path = path.to_path_buf();
+        Box::pin(async move { inner.list(&path).await })
+    }
+    fn create_dir(&self, path: &Path, recursive: bool) -> BoxedLocal<Result<()>> {
+        let inner = self.inner.clone();
+        let path = path.to_path_buf();
+        Box::pin(async move { inner.create_dir(&path, recursive).await })
+    }
+    fn remove(&self, path: &Path, recursive: bool) -> BoxedLocal<Result<()>> {
+        let inner = self.inner.clone();
+        let path = path.to_path_buf();
+        Box::pin(async move { inner.remove(&path, recursive).await })
+    }
+    fn rename(&self, from: &Path, to: &Path) -> BoxedLocal<Result<()>> {
+        let inner = self.inner.clone();
+        let from = from.to_path_buf();
+        let to = to.to_path_buf();
+        Box::pin(async move { inner.rename(&from, &to).await })
+    }
+    fn copy(&self, src: usize, dst: usize) -> BoxedLocal<Result<u64>> {
+        match (self.inner.fd(src), self.inner.fd(dst)) {
+            (Layer::Upper(src), Layer::Upper(dst)) => self.inner.upper.copy(src, dst),
+            _ => unsupported(),
+        }
+    }
+    fn write_blob(&self, fd: usize, cursor: u64, blob: &Blob) -> BoxedLocal<Result<u64>> {
+        match self.inner.fd(fd) {
+            Layer::Upper(fd) => self.inner.upper.write_blob(fd, cursor, blob),
+            Layer::Lower(_) => Box::pin(async {
+                Err(Error::new(
+                    ErrorKind::PermissionDenied,
+                    "the file is not opened for writing",
+                ))
+            }),
+        }
+    }
+    fn blob(&self, fd: usize) -> BoxedLocal<Result<Blob>> {
+        match self.inner.fd(fd) {
+            Layer::Upper(fd) => self.inner.upper.blob(fd),
+            Layer::Lower(fd) => self.inner.lower.blob(fd),
+        }
+    }
+}
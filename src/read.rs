@@ -1,41 +1,72 @@
 use std::{
-    cell::RefCell,
-    io::Result,
+    io::{Error, ErrorKind, IoSliceMut, Result},
     pin::Pin,
-    rc::Rc,
     task::{Context, Poll},
 };
 
 use futures_lite::AsyncRead;
-use js_sys::{ArrayBuffer, Object, Uint8Array};
-use wasm_bindgen::JsValue;
 
-use crate::{set_value, File, Fs, Task, CURSOR, FD, FS, INDEX, READ, SIZE};
+use crate::File;
 
-pub(crate) struct ReadResult {
-    pub buf: ArrayBuffer,
-    pub size: usize,
-}
-
-impl Fs {
-    fn read(
-        &self,
-        fd: usize,
-        size: usize,
-        cursor: u64,
-        task: Rc<RefCell<Task<Result<ReadResult>>>>,
-    ) {
-        let index = self.inner.borrow_mut().reading_tasks.insert(task);
+/// Largest read sent to the backend when reading a whole file.
+const WHOLE_READ_CHUNK_SIZE: u64 = 16 << 20;
 
-        let msg = Object::new();
-        let read = Object::new();
-        set_value(&read, &FD, &JsValue::from(fd));
-        set_value(&read, &SIZE, &JsValue::from(size));
-        set_value(&read, &INDEX, &JsValue::from(index));
-        set_value(&read, &CURSOR, &JsValue::from_f64(cursor as f64));
-        set_value(&msg, &READ, &read);
-
-        self.worker.post_message(&msg).unwrap()
+impl File {
+    /// Reads from the cursor to the end of the file, trusting the size known by the file.
+    ///
+    /// Unlike `read_to_end`, the buffer is allocated once and the content is fetched with a single read,
+    /// or reads of 16 MiB for larger files, and no more read is needed to find the end.
+    /// Fails with [`ErrorKind::UnexpectedEof`] if the file turns out to be shorter.
+    pub async fn read_to_end_exact(&mut self, buf: &mut Vec<u8>) -> Result<usize> {
+        self.read_sized(buf, true).await
+    }
+    /// Like [`read_to_end_exact`](Self::read_to_end_exact), stopping early instead of failing if `exact` is false.
+    pub(crate) async fn read_sized(&mut self, buf: &mut Vec<u8>, exact: bool) -> Result<usize> {
+        let end = self.size.max(self.cursor);
+        let start = buf.len();
+        if buf.is_empty() && end - self.cursor <= WHOLE_READ_CHUNK_SIZE {
+            // The result of the read becomes the buffer.
+            *buf = self
+                .backend
+                .read(self.fd, self.cursor, (end - self.cursor) as usize)
+                .await?;
+        } else {
+            buf.reserve_exact((end - self.cursor) as usize);
+            let mut cursor = self.cursor;
+            while cursor < end {
+                let len = (end - cursor).min(WHOLE_READ_CHUNK_SIZE) as usize;
+                let data = self.backend.read(self.fd, cursor, len).await?;
+                if data.is_empty() {
+                    break;
+                }
+                cursor += data.len() as u64;
+                buf.extend_from_slice(&data);
+            }
+        }
+        let read = buf.len() - start;
+        self.cursor += read as u64;
+        if exact && self.cursor < end {
+            return Err(Error::from(ErrorKind::UnexpectedEof));
+        }
+        Ok(read)
+    }
+    /// Polls the read of at most `len` bytes at the cursor, without moving the cursor.
+    pub(crate) fn poll_read_data(
+        &mut self,
+        cx: &mut Context<'_>,
+        len: usize,
+    ) -> Poll<Result<Vec<u8>>> {
+        let mut task = if let Some(task) = self.read_task.take() {
+            task
+        } else {
+            self.backend.read(self.fd, self.cursor, len)
+        };
+        if let Poll::Ready(result) = task.as_mut().poll(cx) {
+            Poll::Ready(result)
+        } else {
+            self.read_task = Some(task);
+            Poll::Pending
+        }
     }
 }
 
@@ -45,31 +76,29 @@ impl AsyncRead for File {
         cx: &mut Context<'_>,
         buf: &mut [u8],
     ) -> Poll<Result<usize>> {
-        let task = if let Some(task) = self.read_task.clone() {
-            task
-        } else {
-            let task = Rc::new(RefCell::new(Task {
-                waker: Some(cx.waker().clone()),
-                result: None,
-            }));
-            let task_clone = task.clone();
-            FS.with_borrow(|fs| fs.read(self.fd, buf.len(), self.cursor, task_clone));
-
-            self.read_task = Some(task.clone());
-            task
-        };
-        let mut inner = task.borrow_mut();
-        if let Some(result) = inner.result.take() {
-            let result = result?;
-            let array = Uint8Array::new(&result.buf);
-            array
-                .slice(0, result.size as u32)
-                .copy_to(&mut buf[..result.size]);
-            self.read_task = None;
-            self.cursor += result.size as u64;
-            Poll::Ready(Ok(result.size))
-        } else {
-            Poll::Pending
+        let data = std::task::ready!(self.poll_read_data(cx, buf.len()))?;
+        // The buffer may be smaller when polled again.
+        let size = data.len().min(buf.len());
+        buf[..size].copy_from_slice(&data[..size]);
+        self.cursor += size as u64;
+        Poll::Ready(Ok(size))
+    }
+    /// The slices are filled by a single read.
+    fn poll_read_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &mut [IoSliceMut<'_>],
+    ) -> Poll<Result<usize>> {
+        let len = bufs.iter().map(|buf| buf.len()).sum();
+        let data = std::task::ready!(self.poll_read_data(cx, len))?;
+        // The buffers may be smaller when polled again.
+        let mut size = 0;
+        for buf in bufs {
+            let part = (data.len() - size).min(buf.len());
+            buf[..part].copy_from_slice(&data[size..size + part]);
+            size += part;
         }
+        self.cursor += size as u64;
+        Poll::Ready(Ok(size))
     }
 }
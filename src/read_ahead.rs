@@ -0,0 +1,148 @@
+use std::{
+    collections::VecDeque,
+    io::Result,
+    path::Path,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_lite::{AsyncBufRead, AsyncBufReadExt, AsyncRead, Stream, future::BoxedLocal};
+
+use crate::File;
+
+const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+const DEFAULT_DEPTH: usize = 4;
+
+/// A buffered reader that reads the following chunks of a file while the current one is consumed.
+///
+/// Up to `depth` reads are kept in flight ahead of the consumer,
+/// so with [`OpfsBackend`](crate::OpfsBackend) the worker is already reading the next chunks
+/// while the previous ones are parsed.
+/// Backends whose [`concurrent_reads`](crate::Backend::concurrent_reads) is false get one read at a time,
+/// which is still issued before the buffer is empty.
+///
+/// Reading starts at the cursor of the file.
+pub struct ReadAhead {
+    file: File,
+    chunk_size: usize,
+    depth: usize,
+    /// Reads in flight with their cursors, in file order.
+    in_flight: VecDeque<(u64, BoxedLocal<Result<Vec<u8>>>)>,
+    /// Cursor of the next read to issue.
+    next: u64,
+    buf: Vec<u8>,
+    consumed: usize,
+    /// Cursor of the start of `buf`.
+    buf_cursor: u64,
+    end: bool,
+}
+
+impl ReadAhead {
+    /// Reads chunks of 64 KiB, keeping 4 of them in flight.
+    pub fn new(file: File) -> Self {
+        Self::with_capacity(DEFAULT_CHUNK_SIZE, DEFAULT_DEPTH, file)
+    }
+    /// Reads chunks of `chunk_size` bytes, keeping up to `depth` of them in flight.
+    pub fn with_capacity(chunk_size: usize, depth: usize, file: File) -> Self {
+        Self {
+            next: file.cursor,
+            buf_cursor: file.cursor,
+            file,
+            chunk_size: chunk_size.max(1),
+            depth: depth.max(1),
+            in_flight: VecDeque::new(),
+            buf: Vec::new(),
+            consumed: 0,
+            end: false,
+        }
+    }
+    pub fn get_ref(&self) -> &File {
+        &self.file
+    }
+    /// Returns the file with its cursor after the consumed bytes, discarding what was read ahead.
+    pub fn into_inner(mut self) -> File {
+        self.file.cursor = self.buf_cursor + self.consumed as u64;
+        self.file
+    }
+    /// Issues reads until `depth` are in flight, not going past the size known when opening.
+    fn issue_reads(&mut self) {
+        let depth = if self.file.backend.concurrent_reads() {
+            self.depth
+        } else {
+            1
+        };
+        while self.in_flight.len() < depth
+            && (self.in_flight.is_empty() || self.next < self.file.size)
+        {
+            let task = self
+                .file
+                .backend
+                .read(self.file.fd, self.next, self.chunk_size);
+            self.in_flight.push_back((self.next, task));
+            self.next += self.chunk_size as u64;
+        }
+    }
+}
+
+impl AsyncBufRead for ReadAhead {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<&[u8]>> {
+        let this = self.get_mut();
+        if this.consumed == this.buf.len() && !this.end {
+            this.issue_reads();
+            let (cursor, task) = this.in_flight.front_mut().expect("a read was just issued");
+            let cursor = *cursor;
+            let data = std::task::ready!(task.as_mut().poll(cx));
+            this.in_flight.pop_front();
+            let data = match data {
+                Ok(data) => data,
+                Err(e) => {
+                    // Read again from here if polled after the error.
+                    this.in_flight.clear();
+                    this.next = cursor;
+                    return Poll::Ready(Err(e));
+                }
+            };
+            if data.is_empty() {
+                this.end = true;
+                this.in_flight.clear();
+            } else if data.len() < this.chunk_size {
+                // The following reads may have seen data appended since, leaving a gap.
+                this.in_flight.clear();
+                this.next = cursor + data.len() as u64;
+            }
+            this.buf_cursor = cursor;
+            this.buf = data;
+            this.consumed = 0;
+            // Keep the following reads going while this chunk is consumed.
+            if !this.end {
+                this.issue_reads();
+            }
+        }
+        Poll::Ready(Ok(&this.buf[this.consumed..]))
+    }
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        let this = self.get_mut();
+        this.consumed = (this.consumed + amt).min(this.buf.len());
+    }
+}
+
+impl AsyncRead for ReadAhead {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize>> {
+        let available = std::task::ready!(self.as_mut().poll_fill_buf(cx))?;
+        let size = available.len().min(buf.len());
+        buf[..size].copy_from_slice(&available[..size]);
+        self.consume(size);
+        Poll::Ready(Ok(size))
+    }
+}
+
+/// Returns a stream over the lines of a file, read with [`ReadAhead`].
+///
+/// Lines don't include the trailing `\n` or `\r\n`.
+pub async fn lines<P: AsRef<Path>>(path: P) -> Result<impl Stream<Item = Result<String>>> {
+    Ok(ReadAhead::new(File::open(path).await?).lines())
+}
@@ -0,0 +1,172 @@
+use std::{
+    collections::HashMap,
+    io::{ErrorKind, Result},
+    path::{Path, PathBuf},
+};
+
+use js_sys::Uint8Array;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::Blob;
+
+use crate::{
+    archive::{crc32, read_archive},
+    create_dir_all, metadata, read, read_to_string, remove_file,
+    util::js_value_to_error,
+    write_atomic,
+};
+
+/// Name of the file recording what was seeded into a directory, see [`SeedOptions`].
+pub const SEED_MANIFEST: &str = ".web-fs-seed";
+
+/// What was seeded into a directory: the version, and the checksum and size of every file.
+struct Manifest {
+    version: String,
+    files: HashMap<PathBuf, (u32, u64)>,
+}
+
+impl Manifest {
+    fn parse(text: &str) -> Option<Manifest> {
+        let mut lines = text.lines();
+        let version = lines.next()?.to_string();
+        let mut files = HashMap::new();
+        for line in lines {
+            let mut fields = line.splitn(3, ' ');
+            let crc = u32::from_str_radix(fields.next()?, 16).ok()?;
+            let size = fields.next()?.parse().ok()?;
+            files.insert(PathBuf::from(fields.next()?), (crc, size));
+        }
+        Some(Manifest { version, files })
+    }
+    fn to_text(&self) -> String {
+        let mut text = format!("{}\n", self.version);
+        for (path, (crc, size)) in &self.files {
+            let path = path
+                .iter()
+                .map(|name| name.to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            text += &format!("{crc:08x} {size} {path}\n");
+        }
+        text
+    }
+}
+
+/// Options and flags which can be used to configure how a directory is seeded from an archive.
+///
+/// Seeding extracts a tar, gzipped tar or zip archive into a directory, for example default content
+/// embedded in the binary with `include_bytes!`.
+/// The version of the archive is recorded in [`SEED_MANIFEST`] inside the directory,
+/// and the archive is only extracted again when the version changes,
+/// so seeding can be done on every start.
+/// Files of the previous version that are no longer in the archive are removed.
+///
+/// Compressed archives and zip entries are decompressed with `DecompressionStream`,
+/// so only uncompressed tar archives and stored zip entries can be seeded on native targets.
+pub struct SeedOptions {
+    version: String,
+    preserve_modified: bool,
+}
+
+impl SeedOptions {
+    /// `version` identifies the content of the archive, any change triggers seeding again.
+    pub fn new<S: Into<String>>(version: S) -> Self {
+        Self {
+            version: version.into(),
+            preserve_modified: false,
+        }
+    }
+    /// Keep files that were changed since they were seeded, and files that were not seeded but are in the archive.
+    ///
+    /// Changes are detected by comparing checksums with those recorded when seeding.
+    /// Removed files are seeded again.
+    pub fn preserve_modified(&mut self, preserve_modified: bool) -> &mut SeedOptions {
+        self.preserve_modified = preserve_modified;
+        self
+    }
+    /// Extracts `archive` into `dest` unless this version was already seeded there,
+    /// creating `dest` and its parents if missing.
+    ///
+    /// Returns whether the archive was extracted.
+    /// If seeding is interrupted, it is done again the next time.
+    pub async fn seed<P: AsRef<Path>>(&self, archive: &[u8], dest: P) -> Result<bool> {
+        let dest = dest.as_ref();
+        let manifest_path = dest.join(SEED_MANIFEST);
+        let previous = match read_to_string(&manifest_path).await {
+            Ok(text) => Manifest::parse(&text),
+            Err(e) if e.kind() == ErrorKind::NotFound => None,
+            Err(e) => return Err(e),
+        };
+        if previous
+            .as_ref()
+            .is_some_and(|previous| previous.version == self.version)
+        {
+            return Ok(false);
+        }
+        let previous_files = previous.map(|previous| previous.files).unwrap_or_default();
+
+        let mut manifest = Manifest {
+            version: self.version.clone(),
+            files: HashMap::new(),
+        };
+        create_dir_all(dest).await?;
+        for entry in read_archive(archive).await? {
+            let path = dest.join(&entry.path);
+            let Some(data) = entry.data else {
+                create_dir_all(&path).await?;
+                continue;
+            };
+            manifest
+                .files
+                .insert(entry.path.clone(), (crc32(&data), data.len() as u64));
+            if self.preserve_modified && is_modified(&path, previous_files.get(&entry.path)).await?
+            {
+                continue;
+            }
+            if let Some(parent) = path.parent() {
+                create_dir_all(parent).await?;
+            }
+            // An interrupted seed must not leave a torn file that looks modified by the user.
+            write_atomic(&path, &data).await?;
+        }
+        for (path, seeded) in &previous_files {
+            if manifest.files.contains_key(path) {
+                continue;
+            }
+            let path = dest.join(path);
+            if self.preserve_modified && is_modified(&path, Some(seeded)).await? {
+                continue;
+            }
+            match remove_file(&path).await {
+                Err(e) if e.kind() != ErrorKind::NotFound => return Err(e),
+                _ => (),
+            }
+        }
+        write_atomic(&manifest_path, manifest.to_text()).await?;
+        Ok(true)
+    }
+    /// Like [`seed`](Self::seed), reading the archive from a `Blob`, for example a fetched response.
+    pub async fn seed_blob<P: AsRef<Path>>(&self, archive: &Blob, dest: P) -> Result<bool> {
+        let buf = JsFuture::from(archive.array_buffer())
+            .await
+            .map_err(js_value_to_error)?;
+        self.seed(&Uint8Array::new(&buf).to_vec(), dest).await
+    }
+}
+
+/// Whether the file at `path` exists and differs from what was seeded.
+async fn is_modified(path: &Path, seeded: Option<&(u32, u64)>) -> Result<bool> {
+    let len = match metadata(path).await {
+        Ok(metadata) => metadata.len(),
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e),
+    };
+    let Some((crc, size)) = seeded else {
+        return Ok(true);
+    };
+    Ok(len != *size || crc32(&read(path).await?) != *crc)
+}
+
+/// Extracts `archive` into `dest` unless `version` was already seeded there, see [`SeedOptions`].
+pub async fn seed<P: AsRef<Path>>(archive: &[u8], dest: P, version: &str) -> Result<bool> {
+    SeedOptions::new(version).seed(archive, dest).await
+}
@@ -10,13 +10,8 @@ use crate::File;
 
 const SEEK_ERROR: &str = "Move cursor to negative value";
 
-impl AsyncSeek for File {
-    /// File System API dosen't fully expose the cursor of the file, so this is a simulated one and does not actually require async.
-    fn poll_seek(
-        mut self: Pin<&mut Self>,
-        _cx: &mut Context<'_>,
-        pos: SeekFrom,
-    ) -> Poll<Result<u64>> {
+impl File {
+    pub(crate) fn seek_cursor(&mut self, pos: SeekFrom) -> Result<u64> {
         match pos {
             SeekFrom::Current(offset) => {
                 self.cursor = self
@@ -30,8 +25,19 @@ impl AsyncSeek for File {
                     .checked_add_signed(offset)
                     .ok_or(Error::other(SEEK_ERROR))?
             }
-            SeekFrom::Start(offset) => self.cursor += offset,
+            SeekFrom::Start(offset) => self.cursor = offset,
         }
-        Poll::Ready(Ok(self.cursor))
+        Ok(self.cursor)
+    }
+}
+
+impl AsyncSeek for File {
+    /// File System API dosen't fully expose the cursor of the file, so this is a simulated one and does not actually require async.
+    fn poll_seek(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        pos: SeekFrom,
+    ) -> Poll<Result<u64>> {
+        Poll::Ready(self.seek_cursor(pos))
     }
 }
@@ -0,0 +1,207 @@
+use std::{
+    cell::{Cell, RefCell},
+    io::{ErrorKind, Result},
+    ops::{Deref, DerefMut},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use futures_lite::{AsyncWriteExt, StreamExt};
+
+use crate::{
+    File, OpenOptions, create_dir, create_dir_all,
+    locks::{LockManager, hold_forever, is_lock_free, lock_manager},
+    read_dir, remove_dir_all, remove_file,
+    util::{spawn_local, unique_suffix},
+    watch::sleep,
+};
+
+/// The reserved directory holding the temp entries of every tab and worker.
+pub const TEMP_DIR: &str = ".web-fs-tmp";
+const LOCK_PREFIX: &str = "web-fs-tmp-";
+
+thread_local! {
+    /// The name of this tab or worker, naming its directory inside [`TEMP_DIR`]
+    /// and the temp files of its [`AtomicFile`](crate::AtomicFile)s.
+    ///
+    /// It is kept alive by a web lock, so other sessions can tell when its entries are left over by a crash.
+    static SESSION: RefCell<Option<String>> = const { RefCell::new(None) };
+    /// Whether the directory of the session was used yet.
+    static SESSION_DIR: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Returns the name of the current session, starting it on first use.
+pub(crate) fn session() -> String {
+    SESSION.with_borrow_mut(|session| {
+        session
+            .get_or_insert_with(|| {
+                let name = unique_suffix();
+                // Requested before any entry is named after it, so a sweep can never see it unlocked.
+                if let Some(manager) = lock_manager() {
+                    hold_forever(&manager, &format!("{}{}", LOCK_PREFIX, name));
+                }
+                name
+            })
+            .clone()
+    })
+}
+
+/// Whether the tab or worker of the session `name` is gone.
+pub(crate) async fn is_session_over(manager: &LockManager, name: &str) -> bool {
+    is_lock_free(manager, &format!("{}{}", LOCK_PREFIX, name)).await
+}
+
+async fn session_dir() -> Result<PathBuf> {
+    let dir = Path::new(TEMP_DIR).join(session());
+    if !SESSION_DIR.replace(true) {
+        remove_stale_temp_files().await?;
+    }
+    create_dir_all(&dir).await?;
+    Ok(dir)
+}
+
+/// Removes the temp entries left by tabs and workers that are gone, for example because they crashed.
+///
+/// This runs automatically the first time a temp entry is created in a session.
+/// It does nothing if the browser doesn't support the *Web Locks API*,
+/// because there is no way to tell whether the owner of the entries is still alive.
+pub async fn remove_stale_temp_files() -> Result<()> {
+    let Some(manager) = lock_manager() else {
+        return Ok(());
+    };
+    let mut entries = match read_dir(TEMP_DIR).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    let current = SESSION.with_borrow(|session| session.clone());
+    let mut sessions = Vec::new();
+    while let Some(entry) = entries.next().await {
+        let entry = entry?;
+        if Some(entry.file_name().to_string_lossy().into_owned()) != current {
+            sessions.push(entry);
+        }
+    }
+    for entry in sessions {
+        if is_session_over(&manager, &entry.file_name().to_string_lossy()).await {
+            // Failing here means some file in it is still open, try again next time.
+            let _ = remove_dir_all(entry.path()).await;
+        }
+    }
+    Ok(())
+}
+
+/// A readable and writable file under [`TEMP_DIR`] which is removed when dropped.
+///
+/// Dereferences to the underlying [`File`].
+pub struct TempFile {
+    file: Option<File>,
+    path: PathBuf,
+}
+
+impl TempFile {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Deref for TempFile {
+    type Target = File;
+    fn deref(&self) -> &File {
+        self.file
+            .as_ref()
+            .expect("The file is only taken when dropping.")
+    }
+}
+impl DerefMut for TempFile {
+    fn deref_mut(&mut self) -> &mut File {
+        self.file
+            .as_mut()
+            .expect("The file is only taken when dropping.")
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let file = self.file.take();
+        let path = std::mem::take(&mut self.path);
+        spawn_local(async move {
+            // The sync access handle must be released before the file can be removed.
+            if let Some(mut file) = file {
+                let _ = file.close().await;
+            }
+            // Left for `remove_stale_temp_files` if this fails.
+            let _ = remove_file(path).await;
+        });
+    }
+}
+
+/// Creates a uniquely named file under [`TEMP_DIR`] which is removed when dropped.
+pub async fn tempfile() -> Result<TempFile> {
+    let path = session_dir().await?.join(unique_suffix());
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .await?;
+    Ok(TempFile {
+        file: Some(file),
+        path,
+    })
+}
+
+/// A uniquely named directory under [`TEMP_DIR`] which is removed with all its content when dropped.
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    /// Removes the directory now and reports failures, which dropping can't.
+    pub async fn close(mut self) -> Result<()> {
+        let path = std::mem::take(&mut self.path);
+        std::mem::forget(self);
+        remove_released_dir(&path).await
+    }
+}
+
+/// Removes the directory at `path` with its content.
+///
+/// Files in it dropped just before only tell the worker to release their sync access handles,
+/// so while removing fails with [`ErrorKind::PermissionDenied`] it is tried again a few times.
+async fn remove_released_dir(path: &Path) -> Result<()> {
+    let mut attempts = 0;
+    loop {
+        match remove_dir_all(path).await {
+            Err(e)
+                if e.kind() == ErrorKind::PermissionDenied
+                    && attempts < 5
+                    && cfg!(target_arch = "wasm32") =>
+            {
+                attempts += 1;
+                sleep(Duration::from_millis(10 << attempts)).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let path = std::mem::take(&mut self.path);
+        spawn_local(async move {
+            // Left for `remove_stale_temp_files` if this fails.
+            let _ = remove_released_dir(&path).await;
+        });
+    }
+}
+
+/// Creates a uniquely named directory under [`TEMP_DIR`] which is removed with all its content when dropped.
+pub async fn tempdir() -> Result<TempDir> {
+    let path = session_dir().await?.join(unique_suffix());
+    create_dir(&path).await?;
+    Ok(TempDir { path })
+}
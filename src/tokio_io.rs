@@ -0,0 +1,57 @@
+use std::{
+    io::{IoSlice, Result, SeekFrom},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_lite::AsyncWrite as _;
+use tokio::io::{AsyncRead, AsyncSeek, AsyncWrite, ReadBuf};
+
+use crate::File;
+
+impl AsyncRead for File {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<Result<()>> {
+        let data = std::task::ready!(self.poll_read_data(cx, buf.remaining()))?;
+        // The buffer may be smaller when polled again.
+        let size = data.len().min(buf.remaining());
+        buf.put_slice(&data[..size]);
+        self.cursor += size as u64;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for File {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<Result<usize>> {
+        futures_lite::AsyncWrite::poll_write(self, cx, buf)
+    }
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<Result<usize>> {
+        futures_lite::AsyncWrite::poll_write_vectored(self, cx, bufs)
+    }
+    fn is_write_vectored(&self) -> bool {
+        true
+    }
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        futures_lite::AsyncWrite::poll_flush(self, cx)
+    }
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        self.poll_close(cx)
+    }
+}
+
+impl AsyncSeek for File {
+    /// The cursor is simulated, so seeking completes immediately.
+    fn start_seek(mut self: Pin<&mut Self>, position: SeekFrom) -> Result<()> {
+        self.seek_cursor(position).map(drop)
+    }
+    fn poll_complete(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<u64>> {
+        Poll::Ready(Ok(self.cursor))
+    }
+}
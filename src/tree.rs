@@ -0,0 +1,138 @@
+use std::{
+    io::{ErrorKind, Result},
+    path::{Path, PathBuf},
+};
+
+use futures_lite::StreamExt;
+
+use crate::{backend, copy, create_dir_all, metadata, normalize, read_dir};
+
+/// Passed to the progress callback of [`CopyDirOptions`] after each file is handled.
+#[derive(Debug, Clone)]
+pub struct CopyProgress {
+    /// Path of the source file that was just handled.
+    pub path: PathBuf,
+    /// Bytes copied so far, skipped files are not counted.
+    pub copied_bytes: u64,
+    /// Files copied so far, skipped files are not counted.
+    pub copied_files: u64,
+    /// Files skipped so far because the destination already existed.
+    pub skipped_files: u64,
+}
+
+type ProgressCallback = Box<dyn FnMut(&CopyProgress)>;
+
+/// Options and flags which can be used to configure how a directory tree is copied.
+///
+/// By default copying fails with [`ErrorKind::AlreadyExists`] if a destination file exists.
+/// `overwrite` has priority over `skip_existing` when both are set.
+#[derive(Default)]
+pub struct CopyDirOptions {
+    overwrite: bool,
+    skip_existing: bool,
+    progress: Option<ProgressCallback>,
+}
+
+impl CopyDirOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Replace files that already exist in the destination.
+    pub fn overwrite(&mut self, overwrite: bool) -> &mut CopyDirOptions {
+        self.overwrite = overwrite;
+        self
+    }
+    /// Keep files that already exist in the destination untouched.
+    pub fn skip_existing(&mut self, skip_existing: bool) -> &mut CopyDirOptions {
+        self.skip_existing = skip_existing;
+        self
+    }
+    /// Called after each file is copied or skipped.
+    pub fn progress<F: FnMut(&CopyProgress) + 'static>(&mut self, f: F) -> &mut CopyDirOptions {
+        self.progress = Some(Box::new(f));
+        self
+    }
+    /// Recursively copies the content of `from` into `to`, creating `to` and its parents if missing.
+    ///
+    /// Returns the total number of bytes copied.
+    /// Fails with [`ErrorKind::InvalidInput`] if `to` is inside `from`, which would never end.
+    pub async fn copy<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, from: P, to: Q) -> Result<u64> {
+        // Fail early with the right error if `from` is not a directory.
+        if !metadata(&from).await?.is_dir() {
+            return Err(std::io::Error::from(ErrorKind::NotADirectory));
+        }
+        if normalize(&to)?.starts_with(normalize(&from)?) {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                "cannot copy a directory into itself",
+            ));
+        }
+        let mut progress = CopyProgress {
+            path: PathBuf::new(),
+            copied_bytes: 0,
+            copied_files: 0,
+            skipped_files: 0,
+        };
+        self.copy_recursively(from.as_ref(), to.as_ref(), &mut progress)
+            .await?;
+        Ok(progress.copied_bytes)
+    }
+    async fn copy_recursively(
+        &mut self,
+        from: &Path,
+        to: &Path,
+        progress: &mut CopyProgress,
+    ) -> Result<()> {
+        create_dir_all(to).await?;
+        let mut entries = Vec::new();
+        let mut dir = read_dir(from).await?;
+        while let Some(entry) = dir.next().await {
+            entries.push(entry?);
+        }
+        for entry in entries {
+            let src = entry.path();
+            let dst = to.join(entry.file_name());
+            if entry.file_type().await?.is_dir() {
+                Box::pin(self.copy_recursively(&src, &dst, progress)).await?;
+                continue;
+            }
+            let exists = match metadata(&dst).await {
+                Ok(_) => true,
+                Err(e) if e.kind() == ErrorKind::NotFound => false,
+                Err(e) => return Err(e),
+            };
+            if exists && !self.overwrite {
+                if self.skip_existing {
+                    progress.skipped_files += 1;
+                } else {
+                    return Err(std::io::Error::from(ErrorKind::AlreadyExists));
+                }
+            } else {
+                progress.copied_bytes += copy(&src, &dst).await?;
+                progress.copied_files += 1;
+            }
+            if let Some(f) = self.progress.as_mut() {
+                progress.path = src;
+                f(progress);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Recursively copies the content of `from` into `to`.
+///
+/// Fails if any destination file already exists, see [`CopyDirOptions`] for other behaviors.
+pub async fn copy_dir_all<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) -> Result<u64> {
+    CopyDirOptions::new().copy(from, to).await
+}
+
+/// Removes everything inside the directory at `path`, but keeps the directory itself.
+pub async fn remove_dir_contents<P: AsRef<Path>>(path: P) -> Result<()> {
+    let path = normalize(path)?;
+    let backend = backend().await;
+    for (name, _) in backend.list(&path).await? {
+        backend.remove(&path.join(name), true).await?;
+    }
+    Ok(())
+}
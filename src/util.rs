@@ -1,6 +1,12 @@
 use std::{
-    io::{Error, ErrorKind},
-    task::Waker,
+    cell::RefCell,
+    future::Future,
+    io::{Error, ErrorKind, Result},
+    path::{Component, Path, PathBuf},
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll, Waker},
+    time::SystemTime,
 };
 
 use js_sys::{JsString, Object, Reflect};
@@ -11,7 +17,8 @@ use crate::GETTING_JS_FIELD_ERROR;
 
 pub(crate) fn get_value(target: &JsValue, key: &'static JsThreadLocal<JsString>) -> JsValue {
     let key = key.with(JsString::clone);
-    Reflect::get(target, &key).expect(&format!("{}, key: \"{}\"", GETTING_JS_FIELD_ERROR, key))
+    Reflect::get(target, &key)
+        .unwrap_or_else(|_| panic!("{}, key: \"{}\"", GETTING_JS_FIELD_ERROR, key))
 }
 pub(crate) fn set_value(target: &JsValue, key: &'static JsThreadLocal<JsString>, value: &JsValue) {
     Reflect::set(target, &key.with(JsString::clone), value)
@@ -28,6 +35,98 @@ pub(crate) struct Task<T> {
     pub(crate) result: Option<T>,
 }
 
+/// A [`Task`] shared between its future and the message handler.
+pub(crate) type SharedTask<T> = Rc<RefCell<Task<T>>>;
+
+pub(crate) fn new_task<T>() -> SharedTask<T> {
+    Rc::new(RefCell::new(Task {
+        waker: None,
+        result: None,
+    }))
+}
+
+/// Waits for a [`Task`] to be resolved by the message handler.
+pub(crate) struct TaskFuture<T>(pub(crate) SharedTask<T>);
+impl<T> Future for TaskFuture<T> {
+    type Output = T;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut inner = self.0.borrow_mut();
+        if let Some(val) = inner.result.take() {
+            return Poll::Ready(val);
+        }
+        inner.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+/// A name suffix that is unlikely to collide with one made by another tab or worker.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn unique_suffix() -> String {
+    format!(
+        "{:x}{:08x}",
+        js_sys::Date::now() as u64,
+        (js_sys::Math::random() * u32::MAX as f64) as u32
+    )
+}
+/// Like the browser version, unlikely to collide with one made by another process or thread.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn unique_suffix() -> String {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    static COUNTER: AtomicU32 = AtomicU32::new(0);
+    let millis = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    format!(
+        "{:x}{:08x}{:08x}",
+        millis,
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+/// The current time, `SystemTime::now()` panics on `wasm32-unknown-unknown`.
+pub(crate) fn now() -> SystemTime {
+    #[cfg(target_arch = "wasm32")]
+    {
+        SystemTime::UNIX_EPOCH + std::time::Duration::from_millis(js_sys::Date::now() as u64)
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        SystemTime::now()
+    }
+}
+
+/// Polls all the futures concurrently, returning their outputs in order.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub(crate) async fn join_all<T>(mut futures: Vec<Pin<Box<dyn Future<Output = T>>>>) -> Vec<T> {
+    let mut outputs: Vec<Option<T>> = futures.iter().map(|_| None).collect();
+    futures_lite::future::poll_fn(|cx| {
+        let mut done = true;
+        for (future, output) in futures.iter_mut().zip(&mut outputs) {
+            if output.is_none() {
+                match future.as_mut().poll(cx) {
+                    Poll::Ready(value) => *output = Some(value),
+                    Poll::Pending => done = false,
+                }
+            }
+        }
+        if done { Poll::Ready(()) } else { Poll::Pending }
+    })
+    .await;
+    outputs.into_iter().map(Option::unwrap).collect()
+}
+
+/// Runs cleanup work that can't be awaited, like removing entries when dropping.
+///
+/// It is spawned on the browser event loop, and done in place on native targets where there is no such loop.
+pub(crate) fn spawn_local<F: Future<Output = ()> + 'static>(future: F) {
+    #[cfg(target_arch = "wasm32")]
+    wasm_bindgen_futures::spawn_local(future);
+    #[cfg(not(target_arch = "wasm32"))]
+    futures_lite::future::block_on(future);
+}
+
 pub(crate) fn js_value_to_string(v: JsValue) -> String {
     format!("{}", Object::from(v).to_string())
 }
@@ -35,9 +134,7 @@ pub(crate) fn js_value_to_error(v: JsValue) -> Error {
     if let Ok(e) = v.clone().dyn_into::<DomException>() {
         if e.name() == "NotFoundError" {
             Error::from(ErrorKind::NotFound)
-        } else if e.name() == "NotAllowedError" {
-            Error::from(ErrorKind::PermissionDenied)
-        } else if e.name() == "NoModificationAllowedError" {
+        } else if e.name() == "NotAllowedError" || e.name() == "NoModificationAllowedError" {
             Error::from(ErrorKind::PermissionDenied)
         } else {
             Error::other(js_value_to_string(v))
@@ -46,3 +143,23 @@ pub(crate) fn js_value_to_error(v: JsValue) -> Error {
         Error::other(js_value_to_string(v))
     }
 }
+
+/// Resolves `.` and `..` and strips the root, giving the path that is handed to a [`Backend`](crate::Backend).
+pub(crate) fn normalize<P: AsRef<Path>>(path: P) -> Result<PathBuf> {
+    let mut normalized = PathBuf::new();
+    for component in path.as_ref().components() {
+        match component {
+            // Browser can't access system root, so this is PermissionDenied.
+            Component::Prefix(_) => return Err(Error::from(ErrorKind::PermissionDenied)),
+            Component::CurDir | Component::RootDir => (),
+            Component::ParentDir => {
+                // Accessing the parent of the root is also not allowed.
+                if !normalized.pop() {
+                    return Err(Error::from(ErrorKind::PermissionDenied));
+                }
+            }
+            Component::Normal(name) => normalized.push(name),
+        }
+    }
+    Ok(normalized)
+}
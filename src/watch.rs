@@ -0,0 +1,473 @@
+use std::{
+    any::Any,
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
+    io::{Error, ErrorKind, Result},
+    path::{Path, PathBuf},
+    pin::Pin,
+    rc::{Rc, Weak},
+    task::{Context, Poll, Waker},
+    time::{Duration, SystemTime},
+};
+
+use futures_lite::Stream;
+use js_sys::{Array, Function, Object, Promise, Reflect};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::{JsFuture, spawn_local};
+use web_sys::{FileSystemHandle, FileSystemHandleKind};
+
+use crate::{
+    Backend, Metadata, OpfsBackend, backend, normalize, opfs::get_handle, util::js_value_to_error,
+};
+
+/// How often the polling fallback of [`watch`] rescans.
+pub const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The kind of a created entry, resembling `notify::event::CreateKind`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CreateKind {
+    Any,
+    File,
+    Folder,
+    Other,
+}
+
+/// The kind of a removed entry, resembling `notify::event::RemoveKind`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemoveKind {
+    Any,
+    File,
+    Folder,
+    Other,
+}
+
+/// What changed about the data of a file, resembling `notify::event::DataChange`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataChange {
+    Any,
+    Size,
+    Content,
+    Other,
+}
+
+/// Which paths of a rename an event holds, resembling `notify::event::RenameMode`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenameMode {
+    Any,
+    To,
+    From,
+    /// The event holds the old path followed by the new path.
+    Both,
+    Other,
+}
+
+/// The kind of a modification, resembling `notify::event::ModifyKind`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModifyKind {
+    Any,
+    Data(DataChange),
+    Name(RenameMode),
+    Other,
+}
+
+/// The kind of an [`Event`], resembling `notify::EventKind`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    /// Something changed but the browser didn't tell what, rescanning is advised.
+    Any,
+    Create(CreateKind),
+    Modify(ModifyKind),
+    Remove(RemoveKind),
+    Other,
+}
+
+impl EventKind {
+    pub fn is_create(&self) -> bool {
+        matches!(self, EventKind::Create(_))
+    }
+    pub fn is_modify(&self) -> bool {
+        matches!(self, EventKind::Modify(_))
+    }
+    pub fn is_remove(&self) -> bool {
+        matches!(self, EventKind::Remove(_))
+    }
+}
+
+/// A change under a watched path, resembling `notify::Event`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    pub kind: EventKind,
+    /// The affected paths, prefixed by the watched path.
+    pub paths: Vec<PathBuf>,
+}
+
+#[derive(Default)]
+struct WatchState {
+    queue: VecDeque<Result<Event>>,
+    waker: Option<Waker>,
+}
+
+impl WatchState {
+    fn push(&mut self, event: Result<Event>) {
+        self.queue.push_back(event);
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+// web-sys doesn't expose `FileSystemObserver` yet.
+#[wasm_bindgen]
+extern "C" {
+    type FileSystemObserver;
+    #[wasm_bindgen(constructor, catch)]
+    fn new(callback: &Function) -> std::result::Result<FileSystemObserver, JsValue>;
+    #[wasm_bindgen(method)]
+    fn observe(this: &FileSystemObserver, handle: &FileSystemHandle, options: &Object) -> Promise;
+    #[wasm_bindgen(method)]
+    fn disconnect(this: &FileSystemObserver);
+
+    #[wasm_bindgen(js_name = setTimeout)]
+    fn set_timeout(handler: &Function, timeout: i32) -> JsValue;
+}
+
+pub(crate) async fn sleep(duration: Duration) {
+    let promise = Promise::new(&mut |resolve, _| {
+        set_timeout(&resolve, duration.as_millis() as i32);
+    });
+    let _ = JsFuture::from(promise).await;
+}
+
+fn get_field(target: &JsValue, key: &str) -> JsValue {
+    Reflect::get(target, &JsValue::from_str(key)).unwrap_or(JsValue::UNDEFINED)
+}
+
+struct Observation {
+    observer: FileSystemObserver,
+    _callback: Closure<dyn FnMut(Array)>,
+}
+
+/// A stream of [`Event`]s under a path, see [`watch`].
+///
+/// Watching stops when this is dropped.
+pub struct Watcher {
+    state: Rc<RefCell<WatchState>>,
+    observation: Option<Observation>,
+}
+
+impl Watcher {
+    /// Whether changes are reported by the browser instead of by polling.
+    pub fn is_native(&self) -> bool {
+        self.observation.is_some()
+    }
+}
+
+impl Stream for Watcher {
+    type Item = Result<Event>;
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut state = self.state.borrow_mut();
+        if let Some(event) = state.queue.pop_front() {
+            return Poll::Ready(Some(event));
+        }
+        state.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        if let Some(observation) = &self.observation {
+            observation.observer.disconnect();
+        }
+    }
+}
+
+/// Watches the file or directory at `path` for changes made by any tab or worker.
+///
+/// Uses `FileSystemObserver` where the browser provides it and the backend is [`OpfsBackend`],
+/// otherwise falls back to diffing the entries, sizes and modification times every [`POLL_INTERVAL`].
+/// The fallback can't detect renames, which are reported as a removal and a creation,
+/// and misses changes that are undone within one interval.
+///
+/// Returns [`ErrorKind::Unsupported`] on native targets, where there is no browser event loop to poll on.
+pub async fn watch<P: AsRef<Path>>(path: P, recursive: bool) -> Result<Watcher> {
+    let backend = backend().await;
+    let is_opfs = (&*backend as &dyn Any).is::<OpfsBackend>();
+    if is_opfs
+        && Reflect::has(&js_sys::global(), &JsValue::from_str("FileSystemObserver"))
+            .unwrap_or(false)
+    {
+        let handle = get_handle(&normalize(&path)?).await?;
+        observe(path.as_ref().to_path_buf(), handle, recursive).await
+    } else {
+        poll(
+            backend,
+            path.as_ref().to_path_buf(),
+            recursive,
+            POLL_INTERVAL,
+        )
+        .await
+    }
+}
+
+/// Like [`watch`], but always uses the polling fallback with the given interval.
+pub async fn watch_polling<P: AsRef<Path>>(
+    path: P,
+    recursive: bool,
+    interval: Duration,
+) -> Result<Watcher> {
+    poll(
+        backend().await,
+        path.as_ref().to_path_buf(),
+        recursive,
+        interval,
+    )
+    .await
+}
+
+async fn observe(path: PathBuf, handle: FileSystemHandle, recursive: bool) -> Result<Watcher> {
+    if cfg!(not(target_arch = "wasm32")) {
+        return Err(Error::new(
+            ErrorKind::Unsupported,
+            "watching is only supported in the browser",
+        ));
+    }
+    let state = Rc::new(RefCell::new(WatchState::default()));
+    let state_clone = state.clone();
+    let join = move |components: JsValue| {
+        let mut path = path.clone();
+        if let Some(components) = components.dyn_ref::<Array>() {
+            for component in components.iter() {
+                path.push(component.as_string().unwrap_or_default());
+            }
+        }
+        path
+    };
+    let callback: Closure<dyn FnMut(Array)> = Closure::new(move |records: Array| {
+        let mut state = state_clone.borrow_mut();
+        for record in records.iter() {
+            let path = join(get_field(&record, "relativePathComponents"));
+            let is_dir = get_field(&record, "changedHandle")
+                .dyn_into::<FileSystemHandle>()
+                .map(|handle| handle.kind() == FileSystemHandleKind::Directory)
+                .ok();
+            let ty = get_field(&record, "type").as_string().unwrap_or_default();
+            let event = match ty.as_str() {
+                "appeared" => Event {
+                    kind: EventKind::Create(match is_dir {
+                        Some(true) => CreateKind::Folder,
+                        Some(false) => CreateKind::File,
+                        None => CreateKind::Any,
+                    }),
+                    paths: vec![path],
+                },
+                "disappeared" => Event {
+                    kind: EventKind::Remove(match is_dir {
+                        Some(true) => RemoveKind::Folder,
+                        Some(false) => RemoveKind::File,
+                        None => RemoveKind::Any,
+                    }),
+                    paths: vec![path],
+                },
+                "modified" => Event {
+                    kind: EventKind::Modify(ModifyKind::Data(DataChange::Any)),
+                    paths: vec![path],
+                },
+                "moved" => Event {
+                    kind: EventKind::Modify(ModifyKind::Name(RenameMode::Both)),
+                    paths: vec![join(get_field(&record, "relativePathMovedFrom")), path],
+                },
+                "errored" => {
+                    state.push(Err(Error::other("the observation of the path failed")));
+                    continue;
+                }
+                _ => Event {
+                    kind: EventKind::Any,
+                    paths: vec![path],
+                },
+            };
+            state.push(Ok(event));
+        }
+    });
+    let observer =
+        FileSystemObserver::new(callback.as_ref().unchecked_ref()).map_err(js_value_to_error)?;
+    let options = Object::new();
+    Reflect::set(
+        &options,
+        &JsValue::from_str("recursive"),
+        &JsValue::from_bool(recursive),
+    )
+    .expect("Setting js field error, this is an error of the crate.");
+    JsFuture::from(observer.observe(&handle, &options))
+        .await
+        .map_err(js_value_to_error)?;
+    Ok(Watcher {
+        state,
+        observation: Some(Observation {
+            observer,
+            _callback: callback,
+        }),
+    })
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Snapshot {
+    File {
+        len: u64,
+        modified: Option<SystemTime>,
+    },
+    Dir,
+}
+
+impl From<Metadata> for Snapshot {
+    fn from(metadata: Metadata) -> Self {
+        if metadata.is_dir() {
+            Snapshot::Dir
+        } else {
+            Snapshot::File {
+                len: metadata.len(),
+                modified: metadata.modified().ok(),
+            }
+        }
+    }
+}
+
+async fn scan_dir(
+    backend: &Rc<dyn Backend>,
+    path: &Path,
+    normalized: &Path,
+    recursive: bool,
+    snapshots: &mut HashMap<PathBuf, Snapshot>,
+) -> Result<()> {
+    for (name, file_type) in backend.list(normalized).await? {
+        let path = path.join(&name);
+        let normalized = normalized.join(&name);
+        if file_type.is_dir() {
+            snapshots.insert(path.clone(), Snapshot::Dir);
+            if recursive {
+                Box::pin(scan_dir(backend, &path, &normalized, recursive, snapshots)).await?;
+            }
+        } else {
+            match backend.stat(&normalized).await {
+                Ok(metadata) => {
+                    snapshots.insert(path, metadata.into());
+                }
+                // Removed since listing.
+                Err(e) if e.kind() == ErrorKind::NotFound => (),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn scan(
+    backend: &Rc<dyn Backend>,
+    path: &Path,
+    recursive: bool,
+) -> Result<HashMap<PathBuf, Snapshot>> {
+    let normalized = normalize(path)?;
+    let mut snapshots = HashMap::new();
+    let metadata = match backend.stat(&normalized).await {
+        Ok(metadata) => metadata,
+        // Reported as the removal of everything.
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(snapshots),
+        Err(e) => return Err(e),
+    };
+    if metadata.is_file() {
+        snapshots.insert(path.to_path_buf(), metadata.into());
+    } else {
+        scan_dir(backend, path, &normalized, recursive, &mut snapshots).await?;
+    }
+    Ok(snapshots)
+}
+
+fn diff(
+    old: &HashMap<PathBuf, Snapshot>,
+    new: &HashMap<PathBuf, Snapshot>,
+    state: &mut WatchState,
+) {
+    for (path, snapshot) in new {
+        let kind = match (old.get(path), snapshot) {
+            (None, Snapshot::File { .. }) => EventKind::Create(CreateKind::File),
+            (None, Snapshot::Dir) => EventKind::Create(CreateKind::Folder),
+            (Some(old), new) if old == new => continue,
+            (Some(Snapshot::File { len: old_len, .. }), Snapshot::File { len: new_len, .. }) => {
+                EventKind::Modify(ModifyKind::Data(if old_len != new_len {
+                    DataChange::Size
+                } else {
+                    DataChange::Content
+                }))
+            }
+            // The type of the entry changed.
+            (Some(_), _) => EventKind::Modify(ModifyKind::Any),
+        };
+        state.push(Ok(Event {
+            kind,
+            paths: vec![path.clone()],
+        }));
+    }
+    for (path, snapshot) in old {
+        if !new.contains_key(path) {
+            let kind = match snapshot {
+                Snapshot::File { .. } => RemoveKind::File,
+                Snapshot::Dir => RemoveKind::Folder,
+            };
+            state.push(Ok(Event {
+                kind: EventKind::Remove(kind),
+                paths: vec![path.clone()],
+            }));
+        }
+    }
+}
+
+async fn poll(
+    backend: Rc<dyn Backend>,
+    path: PathBuf,
+    recursive: bool,
+    interval: Duration,
+) -> Result<Watcher> {
+    if cfg!(not(target_arch = "wasm32")) {
+        return Err(Error::new(
+            ErrorKind::Unsupported,
+            "watching is only supported in the browser",
+        ));
+    }
+    let state = Rc::new(RefCell::new(WatchState::default()));
+    let initial = scan(&backend, &path, recursive).await?;
+    let weak = Rc::downgrade(&state);
+    spawn_local(poll_loop(backend, path, recursive, interval, initial, weak));
+    Ok(Watcher {
+        state,
+        observation: None,
+    })
+}
+
+async fn poll_loop(
+    backend: Rc<dyn Backend>,
+    path: PathBuf,
+    recursive: bool,
+    interval: Duration,
+    mut snapshots: HashMap<PathBuf, Snapshot>,
+    state: Weak<RefCell<WatchState>>,
+) {
+    loop {
+        sleep(interval).await;
+        // The watcher was dropped.
+        if state.strong_count() == 0 {
+            return;
+        }
+        let scanned = scan(&backend, &path, recursive).await;
+        let Some(state) = state.upgrade() else {
+            return;
+        };
+        let mut state = state.borrow_mut();
+        match scanned {
+            Ok(scanned) => {
+                diff(&snapshots, &scanned, &mut state);
+                snapshots = scanned;
+            }
+            Err(e) => state.push(Err(e)),
+        }
+    }
+}
@@ -0,0 +1,294 @@
+use std::{
+    cell::{Cell, RefCell},
+    future::Future,
+    io::{Error, ErrorKind, Result},
+    path::Path,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll},
+};
+
+use futures_lite::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, io};
+use js_sys::{ArrayBuffer, Object, Promise, Uint8Array};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::{JsFuture, future_to_promise};
+use web_sys::{
+    ReadableStream, ReadableStreamDefaultController, ReadableStreamDefaultReader, WritableStream,
+    WritableStreamDefaultWriter,
+};
+
+use crate::{
+    ABORT, BUFFER, BYTE_LENGTH, BYTE_OFFSET, CANCEL, DONE, File, PULL, SINK_CLOSE, SINK_WRITE,
+    VALUE,
+    util::{get_value, get_value_as_f64, js_value_to_error, set_value},
+};
+
+/// Size of the chunks read into a `ReadableStream`.
+const STREAM_CHUNK_SIZE: usize = 64 * 1024;
+
+fn error_to_js_value(e: Error) -> JsValue {
+    js_sys::Error::new(&e.to_string()).into()
+}
+
+/// The bytes of a chunk written to a `WritableStream`, which may be a string or a buffer source.
+fn chunk_bytes(chunk: &JsValue) -> Result<Vec<u8>> {
+    if let Some(text) = chunk.as_string() {
+        return Ok(text.into_bytes());
+    }
+    if let Some(buf) = chunk.dyn_ref::<ArrayBuffer>() {
+        return Ok(Uint8Array::new(buf).to_vec());
+    }
+    if ArrayBuffer::is_view(chunk) {
+        let buf = get_value(chunk, &BUFFER);
+        let offset = get_value_as_f64(chunk, &BYTE_OFFSET) as u32;
+        let len = get_value_as_f64(chunk, &BYTE_LENGTH) as u32;
+        return Ok(Uint8Array::new_with_byte_offset_and_length(&buf, offset, len).to_vec());
+    }
+    Err(Error::new(
+        ErrorKind::InvalidInput,
+        "chunks must be strings, ArrayBuffers or ArrayBuffer views",
+    ))
+}
+
+/// Wraps `reader` in a `ReadableStream` of `Uint8Array` chunks.
+///
+/// Chunks are only read when the stream asks for more,
+/// so reading follows the pace of whatever consumes the stream.
+/// `reader` is dropped when the end is reached, on error, or when the stream is cancelled.
+pub fn readable_stream<R: AsyncRead + Unpin + 'static>(reader: R) -> ReadableStream {
+    let reader = Rc::new(RefCell::new(Some(reader)));
+    let cancelled = Rc::new(Cell::new(false));
+
+    let pull_reader = reader.clone();
+    let pull_cancelled = cancelled.clone();
+    let pull = Closure::<dyn FnMut(ReadableStreamDefaultController) -> Promise>::new(
+        move |controller: ReadableStreamDefaultController| {
+            let reader = pull_reader.clone();
+            let cancelled = pull_cancelled.clone();
+            future_to_promise(async move {
+                // The stream never pulls again before the previous pull completes,
+                // but it may be cancelled meanwhile.
+                let Some(mut taken) = reader.borrow_mut().take() else {
+                    return Ok(JsValue::UNDEFINED);
+                };
+                let mut buf = vec![0; STREAM_CHUNK_SIZE];
+                let size = taken.read(&mut buf).await.map_err(error_to_js_value)?;
+                if size == 0 {
+                    controller.close()?;
+                } else {
+                    controller.enqueue_with_chunk(&Uint8Array::from(&buf[..size]))?;
+                    if !cancelled.get() {
+                        *reader.borrow_mut() = Some(taken);
+                    }
+                }
+                Ok(JsValue::UNDEFINED)
+            })
+        },
+    );
+    let cancel = Closure::<dyn FnMut()>::new(move || {
+        cancelled.set(true);
+        reader.borrow_mut().take();
+    });
+
+    let source = Object::new();
+    set_value(&source, &PULL, &pull.into_js_value());
+    set_value(&source, &CANCEL, &cancel.into_js_value());
+    ReadableStream::new_with_underlying_source(&source)
+        .expect("Creating a ReadableStream failed, this is an error of the crate.")
+}
+
+/// Wraps `writer` in a `WritableStream`, accepting strings and buffer sources as chunks.
+///
+/// Each chunk is written completely before the next is accepted,
+/// so writers to the stream wait on `writer` when they respect its backpressure.
+/// `writer` is closed when the stream is closed, and dropped when it is aborted.
+pub fn writable_stream<W: AsyncWrite + Unpin + 'static>(writer: W) -> WritableStream {
+    let writer = Rc::new(RefCell::new(Some(writer)));
+
+    let write_writer = writer.clone();
+    let write = Closure::<dyn FnMut(JsValue) -> Promise>::new(move |chunk: JsValue| {
+        let writer = write_writer.clone();
+        future_to_promise(async move {
+            // The stream never calls the sink again before the previous call completes.
+            let Some(mut taken) = writer.borrow_mut().take() else {
+                return Err(error_to_js_value(Error::from(ErrorKind::BrokenPipe)));
+            };
+            let bytes = chunk_bytes(&chunk).map_err(error_to_js_value)?;
+            taken.write_all(&bytes).await.map_err(error_to_js_value)?;
+            *writer.borrow_mut() = Some(taken);
+            Ok(JsValue::UNDEFINED)
+        })
+    });
+    let close_writer = writer.clone();
+    let close = Closure::<dyn FnMut() -> Promise>::new(move || {
+        let writer = close_writer.clone();
+        future_to_promise(async move {
+            let Some(mut taken) = writer.borrow_mut().take() else {
+                return Ok(JsValue::UNDEFINED);
+            };
+            taken.close().await.map_err(error_to_js_value)?;
+            Ok(JsValue::UNDEFINED)
+        })
+    });
+    let abort = Closure::<dyn FnMut()>::new(move || {
+        writer.borrow_mut().take();
+    });
+
+    let sink = Object::new();
+    set_value(&sink, &SINK_WRITE, &write.into_js_value());
+    set_value(&sink, &SINK_CLOSE, &close.into_js_value());
+    set_value(&sink, &ABORT, &abort.into_js_value());
+    WritableStream::new_with_underlying_sink(&sink)
+        .expect("Creating a WritableStream failed, this is an error of the crate.")
+}
+
+impl File {
+    /// Turns the file into a `ReadableStream` of its content from the cursor, see [`readable_stream`].
+    pub fn into_readable_stream(self) -> ReadableStream {
+        readable_stream(self)
+    }
+    /// Turns the file into a `WritableStream` writing at the cursor, see [`writable_stream`].
+    pub fn into_writable_stream(self) -> WritableStream {
+        writable_stream(self)
+    }
+}
+
+/// Reads a `ReadableStream` of strings or buffer sources, for example `Response.body`.
+///
+/// The stream is locked to this reader until it is dropped,
+/// and the next chunk is only requested once the previous one is consumed.
+pub struct StreamReader {
+    reader: ReadableStreamDefaultReader,
+    pending: Option<JsFuture>,
+    chunk: Vec<u8>,
+    consumed: usize,
+    done: bool,
+}
+
+impl StreamReader {
+    pub fn new(stream: &ReadableStream) -> Result<Self> {
+        Ok(Self {
+            reader: ReadableStreamDefaultReader::new(stream).map_err(js_value_to_error)?,
+            pending: None,
+            chunk: Vec::new(),
+            consumed: 0,
+            done: false,
+        })
+    }
+}
+
+impl AsyncRead for StreamReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize>> {
+        let this = &mut *self;
+        // Empty chunks are skipped rather than reported as the end.
+        while this.consumed == this.chunk.len() && !this.done {
+            let pending = this
+                .pending
+                .get_or_insert_with(|| JsFuture::from(this.reader.read()));
+            let result = match Pin::new(pending).poll(cx) {
+                Poll::Ready(result) => result,
+                Poll::Pending => return Poll::Pending,
+            };
+            this.pending = None;
+            let result = result.map_err(js_value_to_error)?;
+            if get_value(&result, &DONE).is_truthy() {
+                this.done = true;
+            } else {
+                this.chunk = chunk_bytes(&get_value(&result, &VALUE))?;
+                this.consumed = 0;
+            }
+        }
+        let size = (this.chunk.len() - this.consumed).min(buf.len());
+        buf[..size].copy_from_slice(&this.chunk[this.consumed..this.consumed + size]);
+        this.consumed += size;
+        Poll::Ready(Ok(size))
+    }
+}
+
+impl Drop for StreamReader {
+    fn drop(&mut self) {
+        self.reader.release_lock();
+    }
+}
+
+/// Writes `Uint8Array` chunks to a `WritableStream`, for example one of a `CompressionStream`.
+///
+/// Writing waits until the stream is ready for more, as told by its queuing strategy.
+/// The stream is locked to this writer until it is dropped.
+pub struct StreamWriter {
+    writer: WritableStreamDefaultWriter,
+    ready: Option<JsFuture>,
+    last_write: Option<JsFuture>,
+    closing: Option<JsFuture>,
+}
+
+impl StreamWriter {
+    pub fn new(stream: &WritableStream) -> Result<Self> {
+        Ok(Self {
+            writer: stream.get_writer().map_err(js_value_to_error)?,
+            ready: None,
+            last_write: None,
+            closing: None,
+        })
+    }
+}
+
+fn poll_promise(
+    future: &mut Option<JsFuture>,
+    cx: &mut Context<'_>,
+    promise: impl FnOnce() -> Promise,
+) -> Poll<Result<()>> {
+    let pending = future.get_or_insert_with(|| JsFuture::from(promise()));
+    let result = std::task::ready!(Pin::new(pending).poll(cx));
+    *future = None;
+    Poll::Ready(result.map(drop).map_err(js_value_to_error))
+}
+
+impl AsyncWrite for StreamWriter {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize>> {
+        let this = &mut *self;
+        std::task::ready!(poll_promise(&mut this.ready, cx, || this.writer.ready()))?;
+        let promise = this.writer.write_with_chunk(&Uint8Array::from(buf));
+        // Errors of this write are reported by the next call.
+        this.last_write = Some(JsFuture::from(promise));
+        Poll::Ready(Ok(buf.len()))
+    }
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        let this = &mut *self;
+        if let Some(last_write) = &mut this.last_write {
+            let result = std::task::ready!(Pin::new(last_write).poll(cx));
+            this.last_write = None;
+            result.map_err(js_value_to_error)?;
+        }
+        Poll::Ready(Ok(()))
+    }
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        std::task::ready!(self.as_mut().poll_flush(cx))?;
+        let this = &mut *self;
+        poll_promise(&mut this.closing, cx, || this.writer.close())
+    }
+}
+
+impl Drop for StreamWriter {
+    fn drop(&mut self) {
+        self.writer.release_lock();
+    }
+}
+
+/// Writes the content of `stream` to a file, creating or truncating it, and returns its size.
+///
+/// For example to save `Response.body` without holding the whole response in memory.
+pub async fn write_stream<P: AsRef<Path>>(path: P, stream: &ReadableStream) -> Result<u64> {
+    let mut file = File::create(path).await?;
+    let size = io::copy(StreamReader::new(stream)?, &mut file).await?;
+    file.close().await?;
+    Ok(size)
+}
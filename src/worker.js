@@ -73,7 +73,23 @@ const READ = 0b0000_1000;
 const TRUNCATE = 0b0001_0000;
 const WRITE = 0b0010_0000;
 
+// Kinds of the operations of a batch.
+const BATCH_OPEN = 0;
+const BATCH_READ = 1;
+const BATCH_WRITE = 2;
+const BATCH_WRITE_AT = 3;
+const BATCH_SET_LEN = 4;
+const BATCH_FLUSH = 5;
+const BATCH_STAT = 6;
+const BATCH_REMOVE = 7;
+
+// Size of the chunks used when copying between two files.
+const COPY_CHUNK_SIZE = 1 << 20;
+
 let opened = new Arena();
+// The file handle of each opened fd, to get its content as a `File`,
+// and whether it can be flushed, which fails in read-only mode.
+let fileHandles = new Map();
 
 onmessage = async (e) => {
     let msg = e.data;
@@ -109,8 +125,12 @@ onmessage = async (e) => {
         try {
             let accessHandle = await openMsg.handle.createSyncAccessHandle(openOptions);
             let fd = opened.insert(accessHandle);
+            fileHandles.set(fd, {
+                handle: openMsg.handle,
+                writable: (openMsg.options & WRITE) > 0,
+            });
 
-            if(openMsg.options & TRUNCATE | openMsg.options & CREATE_NEW | openMsg.options & CREATE) {
+            if(openMsg.options & TRUNCATE) {
                 accessHandle.truncate(0);
             } 
 
@@ -133,6 +153,7 @@ onmessage = async (e) => {
          */
         let dropMsg = msg.Drop;
         let accessHandle = opened.remove(dropMsg.fd);
+        fileHandles.delete(dropMsg.fd);
         accessHandle.close();
     } else if(msg.Read != undefined) {
         /**
@@ -269,5 +290,294 @@ onmessage = async (e) => {
         } finally {
             postMessage(response);
         }
+    } else if(msg.Copy != undefined) {
+        /**
+         * @typedef InCopyMsg
+         * @type {object}
+         * @property {number} src
+         * @property {number} dst
+         * @property {number} index
+         */
+        /**
+         * @type {InCopyMsg}
+         */
+        let copyMsg = msg.Copy;
+
+        let response = {
+            6: {
+                index: copyMsg.index
+            }
+        }
+        try {
+            let src = opened.get(copyMsg.src);
+            let dst = opened.get(copyMsg.dst);
+            let buffer = new Uint8Array(Math.min(COPY_CHUNK_SIZE, Math.max(src.getSize(), 1)));
+            let cursor = 0;
+            while(true) {
+                let size = src.read(buffer, { at: cursor });
+                if(size == 0) {
+                    break;
+                }
+                dst.write(buffer.subarray(0, size), { at: cursor });
+                cursor += size;
+            }
+            dst.truncate(cursor);
+            dst.flush();
+            response[6].size = cursor;
+        } catch (error) {
+            response.error = error.toString();
+        } finally {
+            postMessage(response);
+        }
+    } else if(msg.Probe != undefined) {
+        /**
+         * @typedef InProbeMsg
+         * @type {object}
+         * @property {FileSystemFileHandle} handle
+         * @property {number} index
+         */
+        /**
+         * @type {InProbeMsg}
+         */
+        let probeMsg = msg.Probe;
+
+        let response = {
+            7: {
+                index: probeMsg.index
+            }
+        }
+        try {
+            let accessHandle = await probeMsg.handle.createSyncAccessHandle();
+            accessHandle.close();
+        } catch (error) {
+            response.error = error.toString();
+        } finally {
+            postMessage(response);
+        }
+    } else if(msg.WriteBlob != undefined) {
+        /**
+         * @typedef InWriteBlobMsg
+         * @type {object}
+         * @property {number} fd
+         * @property {Blob} blob
+         * @property {number} index
+         * @property {number} cursor
+         */
+        /**
+         * @type {InWriteBlobMsg}
+         */
+        let writeBlobMsg = msg.WriteBlob;
+
+        let response = {
+            8: {
+                index: writeBlobMsg.index
+            }
+        }
+        try {
+            let accessHandle = opened.get(writeBlobMsg.fd);
+            let blob = writeBlobMsg.blob;
+            // Reading synchronously so that no other message is handled in between.
+            let reader = new FileReaderSync();
+            let offset = 0;
+            while(offset < blob.size) {
+                let chunk = reader.readAsArrayBuffer(blob.slice(offset, offset + COPY_CHUNK_SIZE));
+                accessHandle.write(chunk, { at: writeBlobMsg.cursor + offset });
+                offset += chunk.byteLength;
+            }
+            response[8].size = offset;
+        } catch (error) {
+            response.error = error.toString();
+        } finally {
+            postMessage(response);
+        }
+    } else if(msg.ToBlob != undefined) {
+        /**
+         * @typedef InToBlobMsg
+         * @type {object}
+         * @property {number} fd
+         * @property {number} index
+         */
+        /**
+         * @type {InToBlobMsg}
+         */
+        let toBlobMsg = msg.ToBlob;
+
+        let response = {
+            9: {
+                index: toBlobMsg.index
+            }
+        }
+        try {
+            // The `File` is a snapshot of the flushed content, nothing is copied.
+            let fileHandle = fileHandles.get(toBlobMsg.fd);
+            if(fileHandle.writable) {
+                opened.get(toBlobMsg.fd).flush();
+            }
+            response[9].blob = await fileHandle.handle.getFile();
+        } catch (error) {
+            response.error = error.toString();
+        } finally {
+            postMessage(response);
+        }
+    } else if(msg.Batch != undefined) {
+        /**
+         * @typedef InBatchOp
+         * @type {object}
+         * @property {number} kind
+         * @property {FileSystemDirectoryHandle} dir
+         * @property {string} name
+         * @property {number} file
+         * @property {boolean} writable
+         * @property {number | undefined} fd
+         * @property {number | undefined} options
+         * @property {number | undefined} cursor
+         * @property {number | undefined} size
+         * @property {ArrayBuffer | undefined} buf
+         */
+        /**
+         * @typedef InBatchMsg
+         * @type {object}
+         * @property {InBatchOp[]} ops
+         * @property {number} index
+         */
+        /**
+         * @type {InBatchMsg}
+         */
+        let batchMsg = msg.Batch;
+
+        // The sync access handles opened by the batch, by file number, closed at its end.
+        let handles = new Map();
+        // The handles of opened files written by the batch, flushed at its end.
+        let written = new Set();
+        // Returns the sync access handle of the file of `op`, if it is open.
+        let openHandle = (op) => {
+            if(op.fd != undefined) {
+                return opened.get(op.fd);
+            }
+            let handle = handles.get(op.file);
+            return handle == undefined ? null : handle.accessHandle;
+        };
+        // Whether the open handle of the file of `op` can be flushed.
+        let canFlush = (op) => {
+            if(op.fd != undefined) {
+                let fileHandle = fileHandles.get(op.fd);
+                return fileHandle != undefined && fileHandle.writable;
+            }
+            return op.writable;
+        };
+        // Returns the sync access handle of the file of `op`, opening it if needed.
+        let accessHandleOf = async (op, create) => {
+            let accessHandle = openHandle(op);
+            if(accessHandle == null) {
+                let fileHandle = await op.dir.getFileHandle(op.name, { create: create });
+                accessHandle = await fileHandle.createSyncAccessHandle({
+                    mode: op.writable ? "readwrite" : "read-only"
+                });
+                handles.set(op.file, { accessHandle: accessHandle, writable: op.writable });
+            } else if(op.fd != undefined && canFlush(op)) {
+                written.add(accessHandle);
+            }
+            return accessHandle;
+        };
+
+        // Every operation has its own result or error, and runs after the previous one.
+        let results = [];
+        for(let op of batchMsg.ops) {
+            let result = {};
+            try {
+                if(op.kind == BATCH_OPEN) {
+                    if(op.options & CREATE_NEW) {
+                        let exists = true;
+                        try {
+                            await op.dir.getFileHandle(op.name);
+                        } catch (error) {
+                            if(error.name != "NotFoundError") {
+                                throw error;
+                            }
+                            exists = false;
+                        }
+                        if(exists) {
+                            throw new DOMException("the file already exists", "AlreadyExistsError");
+                        }
+                    }
+                    let accessHandle = await accessHandleOf(op, (op.options & (CREATE | CREATE_NEW)) > 0);
+                    if(op.options & TRUNCATE) {
+                        accessHandle.truncate(0);
+                    }
+                } else if(op.kind == BATCH_READ) {
+                    let accessHandle = await accessHandleOf(op, false);
+                    let available = Math.max(accessHandle.getSize() - op.cursor, 0);
+                    let size = op.size == undefined ? available : Math.min(op.size, available);
+                    let buffer = new Uint8Array(size);
+                    result.size = accessHandle.read(buffer, { at: op.cursor });
+                    result.buf = buffer.buffer;
+                } else if(op.kind == BATCH_WRITE) {
+                    let accessHandle = await accessHandleOf(op, true);
+                    accessHandle.truncate(0);
+                    accessHandle.write(new DataView(op.buf), { at: 0 });
+                } else if(op.kind == BATCH_WRITE_AT) {
+                    let accessHandle = await accessHandleOf(op, false);
+                    accessHandle.write(new DataView(op.buf), { at: op.cursor });
+                } else if(op.kind == BATCH_SET_LEN) {
+                    let accessHandle = await accessHandleOf(op, false);
+                    accessHandle.truncate(op.size);
+                } else if(op.kind == BATCH_FLUSH) {
+                    let accessHandle = openHandle(op);
+                    if(accessHandle != null && canFlush(op)) {
+                        accessHandle.flush();
+                    }
+                } else if(op.kind == BATCH_STAT) {
+                    // `getFile()` only sees what is flushed.
+                    let accessHandle = openHandle(op);
+                    if(accessHandle != null && canFlush(op)) {
+                        accessHandle.flush();
+                    }
+                    try {
+                        let file = await (await op.dir.getFileHandle(op.name)).getFile();
+                        result.size = file.size;
+                        result.modified = file.lastModified;
+                    } catch (error) {
+                        if(error.name != "TypeMismatchError") {
+                            throw error;
+                        }
+                        await op.dir.getDirectoryHandle(op.name);
+                        result.isDir = true;
+                    }
+                } else if(op.kind == BATCH_REMOVE) {
+                    // A file can't be removed while the batch has it open.
+                    let handle = handles.get(op.file);
+                    if(handle != undefined) {
+                        if(handle.writable) {
+                            handle.accessHandle.flush();
+                        }
+                        handle.accessHandle.close();
+                        handles.delete(op.file);
+                    }
+                    await op.dir.removeEntry(op.name);
+                }
+            } catch (error) {
+                result.error = error.toString();
+                result.errorName = error.name;
+            }
+            results.push(result);
+        }
+        for(let handle of handles.values()) {
+            try {
+                if(handle.writable) {
+                    handle.accessHandle.flush();
+                }
+            } finally {
+                handle.accessHandle.close();
+            }
+        }
+        for(let accessHandle of written) {
+            accessHandle.flush();
+        }
+        postMessage({
+            10: {
+                index: batchMsg.index,
+                results: results
+            }
+        });
     }
 }
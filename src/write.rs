@@ -1,130 +1,85 @@
 use std::{
-    cell::RefCell,
-    io::Result,
+    io::{IoSlice, Result},
     pin::Pin,
-    rc::Rc,
     task::{Context, Poll},
 };
 
-use futures_lite::AsyncWrite;
-use js_sys::{Object, Uint8Array};
-use wasm_bindgen::JsValue;
+use futures_lite::{AsyncWrite, future::BoxedLocal};
 
-use crate::{
-    set_value, File, Fs, Task, BUF, CLOSE, CURSOR, FD, FLUSH, FS, INDEX, POST_ERROR, WRITE,
-};
-
-impl Fs {
-    fn write(&self, fd: usize, buf: &[u8], cursor: u64, task: Rc<RefCell<Task<Result<usize>>>>) {
-        let write_obj = Object::new();
-        let index = self.inner.borrow_mut().writing_tasks.insert(task);
-        let typed_array = Uint8Array::new_with_length(buf.len() as u32);
-        typed_array.copy_from(buf);
-
-        set_value(&write_obj, &INDEX, &JsValue::from(index));
-        set_value(&write_obj, &FD, &JsValue::from(fd));
-        set_value(&write_obj, &BUF, &typed_array.buffer());
-        set_value(&write_obj, &CURSOR, &JsValue::from_f64(cursor as f64));
-        let msg = Object::new();
-        set_value(&msg, &WRITE, &write_obj);
-
-        self.worker.post_message(&msg).expect(POST_ERROR);
-    }
-    fn flush(&self, fd: usize, task: Rc<RefCell<Task<Result<()>>>>) {
-        let index = self.inner.borrow_mut().flushing_tasks.insert(task);
-
-        let msg = Object::new();
-        let flust = Object::new();
-        set_value(&flust, &FD, &JsValue::from(fd));
-        set_value(&flust, &INDEX, &JsValue::from(index));
-        set_value(&msg, &FLUSH, &flust);
-
-        self.worker.post_message(&msg).expect(POST_ERROR);
-    }
-    fn close(&self, fd: usize, task: Rc<RefCell<Task<Result<()>>>>) {
-        let index = self.inner.borrow_mut().closing_tasks.insert(task);
-
-        let msg = Object::new();
-        let close = Object::new();
-        set_value(&close, &FD, &JsValue::from(fd));
-        set_value(&close, &INDEX, &JsValue::from(index));
-        set_value(&msg, &CLOSE, &close);
+use crate::File;
 
-        self.worker.post_message(&msg).expect(POST_ERROR);
-    }
-}
-
-impl AsyncWrite for File {
-    fn poll_write(
-        mut self: Pin<&mut Self>,
+impl File {
+    /// Polls the write in progress, or the one started by `start`.
+    fn poll_write_with(
+        &mut self,
         cx: &mut Context<'_>,
-        buf: &[u8],
+        start: impl FnOnce(&Self) -> BoxedLocal<Result<usize>>,
     ) -> Poll<Result<usize>> {
-        let task = if let Some(task) = self.write_task.clone() {
+        let mut task = if let Some(task) = self.write_task.take() {
             task
         } else {
-            let task = Rc::new(RefCell::new(Task {
-                waker: Some(cx.waker().clone()),
-                result: None,
-            }));
-            let task_clone = task.clone();
-            FS.with_borrow(|fs| fs.write(self.fd, buf, self.cursor, task_clone));
-
-            self.write_task = Some(task.clone());
-            task
+            start(self)
         };
-        let mut inner = task.borrow_mut();
-        if let Some(result) = inner.result.take() {
-            self.write_task = None;
+        if let Poll::Ready(result) = task.as_mut().poll(cx) {
             if let Ok(size) = result {
-                self.size += size as u64;
+                self.cursor += size as u64;
+                self.size = self.size.max(self.cursor);
             }
             Poll::Ready(result)
         } else {
+            self.write_task = Some(task);
             Poll::Pending
         }
     }
+}
+
+impl AsyncWrite for File {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize>> {
+        self.poll_write_with(cx, |file| file.backend.write(file.fd, file.cursor, buf))
+    }
+    /// The slices are gathered into a single write.
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<Result<usize>> {
+        self.poll_write_with(cx, |file| {
+            let mut data = Vec::with_capacity(bufs.iter().map(|buf| buf.len()).sum());
+            for buf in bufs {
+                data.extend_from_slice(buf);
+            }
+            file.backend.write(file.fd, file.cursor, &data)
+        })
+    }
     fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
-        let task = if let Some(task) = self.flush_task.clone() {
+        let mut task = if let Some(task) = self.flush_task.take() {
             task
         } else {
-            let task = Rc::new(RefCell::new(Task {
-                waker: Some(cx.waker().clone()),
-                result: None,
-            }));
-            let task_clone = task.clone();
-            FS.with_borrow(|fs| fs.flush(self.fd, task_clone));
-
-            self.flush_task = Some(task.clone());
-            task
+            self.backend.flush(self.fd)
         };
-        let mut inner = task.borrow_mut();
-        if let Some(result) = inner.result.take() {
-            self.flush_task = None;
+        if let Poll::Ready(result) = task.as_mut().poll(cx) {
             Poll::Ready(result)
         } else {
+            self.flush_task = Some(task);
             Poll::Pending
         }
     }
     fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
-        let task = if let Some(task) = self.close_task.clone() {
+        let mut task = if let Some(task) = self.close_task.take() {
             task
         } else {
-            let task = Rc::new(RefCell::new(Task {
-                waker: Some(cx.waker().clone()),
-                result: None,
-            }));
-            let task_clone = task.clone();
-            FS.with_borrow(|fs| fs.close(self.fd, task_clone));
-
-            self.close_task = Some(task.clone());
-            task
+            self.backend.close(self.fd)
         };
-        let mut inner = task.borrow_mut();
-        if let Some(result) = inner.result.take() {
-            self.close_task = None;
+        if let Poll::Ready(result) = task.as_mut().poll(cx) {
+            // Nothing can be done with the file anymore.
+            self.lock.get_mut().take();
             Poll::Ready(result)
         } else {
+            self.close_task = Some(task);
             Poll::Pending
         }
     }
@@ -0,0 +1,151 @@
+// Runs natively with `cargo test`
+#![cfg(not(target_arch = "wasm32"))]
+
+use futures_lite::{AsyncReadExt, future::block_on};
+
+use web_fs::*;
+
+async fn fill_tree() -> Vec<u8> {
+    let big: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+    create_dir_all("data/empty").await.unwrap();
+    create_dir_all("data/nested/deeper").await.unwrap();
+    write("data/nested/deeper/big", &big).await.unwrap();
+    write("data/nested/empty_file", "").await.unwrap();
+    write(format!("data/nested/{}", "long".repeat(30)), "long name")
+        .await
+        .unwrap();
+    big
+}
+
+async fn check_tree(root: &str, big: &[u8]) {
+    assert!(metadata(format!("{root}/empty")).await.unwrap().is_dir());
+    assert_eq!(
+        big,
+        read(format!("{root}/nested/deeper/big")).await.unwrap()
+    );
+    assert_eq!(
+        0,
+        metadata(format!("{root}/nested/empty_file"))
+            .await
+            .unwrap()
+            .len()
+    );
+    assert_eq!(
+        "long name",
+        read_to_string(format!("{root}/nested/{}", "long".repeat(30)))
+            .await
+            .unwrap()
+    );
+}
+
+#[test]
+fn tar_round_trip() {
+    set_backend(MemoryBackend::new());
+    block_on(async {
+        let big = fill_tree().await;
+        let mut archive = Vec::new();
+        export_dir("data", ArchiveFormat::Tar)
+            .read_to_end(&mut archive)
+            .await
+            .unwrap();
+        assert_eq!(0, archive.len() % 512);
+
+        import_archive(&archive[..], "restored").await.unwrap();
+        check_tree("restored", &big).await;
+    });
+}
+
+#[test]
+fn zip_round_trip() {
+    set_backend(MemoryBackend::new());
+    block_on(async {
+        let big = fill_tree().await;
+        let mut archive = Vec::new();
+        export_dir("data", ArchiveFormat::Zip)
+            .read_to_end(&mut archive)
+            .await
+            .unwrap();
+
+        import_archive(&archive[..], "restored").await.unwrap();
+        check_tree("restored", &big).await;
+
+        // Also readable when seeding.
+        seed(&archive, "seeded", "1").await.unwrap();
+        check_tree("seeded", &big).await;
+    });
+}
+
+#[test]
+fn export_errors() {
+    set_backend(MemoryBackend::new());
+    block_on(async {
+        let mut archive = Vec::new();
+        assert!(
+            export_dir("missing", ArchiveFormat::Tar)
+                .read_to_end(&mut archive)
+                .await
+                .is_err()
+        );
+    });
+}
+
+/// A tar header with a size in the base-256 extension of GNU tar.
+fn tar_header(name: &str, size: u64, kind: u8) -> Vec<u8> {
+    let mut header = vec![0; 512];
+    header[..name.len()].copy_from_slice(name.as_bytes());
+    header[124] = 0x80;
+    header[128..136].copy_from_slice(&size.to_be_bytes());
+    header[156] = kind;
+    header
+}
+
+#[test]
+fn tar_oversized_records() {
+    set_backend(MemoryBackend::new());
+    block_on(async {
+        let archive = tar_header("././@LongLink", 1 << 40, b'L');
+        let error = import_archive(&archive[..], "restored").await.unwrap_err();
+        assert_eq!(std::io::ErrorKind::InvalidData, error.kind());
+    });
+}
+
+#[test]
+fn tar_oversized_sizes() {
+    set_backend(MemoryBackend::new());
+    block_on(async {
+        // Sizes overflowing the offsets or not fitting in u64 are invalid, not panics.
+        for size in [u64::MAX, u64::MAX - 511, 1 << 40] {
+            let mut archive = tar_header("file", size, b'0');
+            archive.resize(2048, 0);
+            let error = seed(&archive, "seeded", "1").await.unwrap_err();
+            assert_eq!(std::io::ErrorKind::InvalidData, error.kind());
+        }
+        let archive = tar_header("file", u64::MAX, b'0');
+        let error = import_archive(&archive[..], "restored").await.unwrap_err();
+        assert_eq!(std::io::ErrorKind::InvalidData, error.kind());
+        let mut archive = tar_header("file", 0, b'0');
+        archive[125..128].fill(0xff);
+        archive.resize(2048, 0);
+        let error = seed(&archive, "seeded", "1").await.unwrap_err();
+        assert_eq!(std::io::ErrorKind::InvalidData, error.kind());
+    });
+}
+
+#[test]
+fn export_skips_temp_dir() {
+    set_backend(MemoryBackend::new());
+    block_on(async {
+        write("kept", "data").await.unwrap();
+        let _temp = tempfile().await.unwrap();
+        for root in ["", "/", "./", "kept/.."] {
+            let mut archive = Vec::new();
+            export_dir(root, ArchiveFormat::Tar)
+                .read_to_end(&mut archive)
+                .await
+                .unwrap();
+            let contains = |name: &[u8]| archive.windows(name.len()).any(|window| window == name);
+            assert!(contains(b"kept"));
+            assert!(!contains(TEMP_DIR.as_bytes()), "{root:?}");
+        }
+    });
+}
@@ -0,0 +1,88 @@
+// This only runs in the browser
+#![cfg(target_arch = "wasm32")]
+
+use futures_lite::{AsyncWriteExt, StreamExt};
+use wasm_bindgen_test::*;
+
+use wasm_bindgen_test::wasm_bindgen_test_configure;
+
+wasm_bindgen_test_configure!(run_in_browser);
+wasm_bindgen_test_configure!(run_in_dedicated_worker);
+
+use web_fs::*;
+
+#[wasm_bindgen_test]
+async fn atomic_write() {
+    console_error_panic_hook::set_once();
+    create_dir_all("atomic").await.unwrap();
+    write("atomic/config", "old").await.unwrap();
+
+    // commit replaces
+    {
+        write_atomic("atomic/config", "new content").await.unwrap();
+        assert_eq!(
+            "new content",
+            read_to_string("atomic/config").await.unwrap()
+        );
+    }
+    // not committing keeps the old content
+    {
+        let mut file = AtomicFile::create("atomic/config").await.unwrap();
+        file.write_all(b"discarded").await.unwrap();
+        drop(file);
+        assert_eq!(
+            "new content",
+            read_to_string("atomic/config").await.unwrap()
+        );
+        // The temp file is closed, then removed in the background.
+        let mut entries = 0;
+        for _ in 0..100 {
+            entries = read_dir("atomic").await.unwrap().count().await;
+            if entries == 1 {
+                break;
+            }
+        }
+        assert_eq!(1, entries);
+    }
+    // stale temp files are removed, the ones of live sessions are kept
+    {
+        let mut file = AtomicFile::create("atomic/config").await.unwrap();
+        file.write_all(b"in progress").await.unwrap();
+        write("atomic/.config.gone.0.web-fs-atomic", "stale")
+            .await
+            .unwrap();
+        create_dir_all("atomic_nested/deep").await.unwrap();
+        write("atomic_nested/deep/.config.gone.0.web-fs-atomic", "stale")
+            .await
+            .unwrap();
+        remove_stale_atomic_files("atomic").await.unwrap();
+        file.commit().await.unwrap();
+        assert_eq!(
+            "in progress",
+            read_to_string("atomic/config").await.unwrap()
+        );
+        remove_stale_files().await.unwrap();
+        assert!(
+            metadata("atomic_nested/deep/.config.gone.0.web-fs-atomic")
+                .await
+                .is_err()
+        );
+        remove_dir_all("atomic_nested").await.unwrap();
+        let names: Vec<_> = read_dir("atomic")
+            .await
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect()
+            .await;
+        assert_eq!(vec!["config"], names);
+    }
+}
+
+#[wasm_bindgen_test]
+async fn rename_file() {
+    console_error_panic_hook::set_once();
+    write("rename_src", "moved").await.unwrap();
+    rename("rename_src", "rename_dst").await.unwrap();
+    assert_eq!("moved", read_to_string("rename_dst").await.unwrap());
+    assert!(File::open("rename_src").await.is_err());
+}
@@ -0,0 +1,133 @@
+// This only runs in the browser
+#![cfg(target_arch = "wasm32")]
+
+use std::io::ErrorKind;
+
+use wasm_bindgen_test::*;
+
+use wasm_bindgen_test::wasm_bindgen_test_configure;
+
+wasm_bindgen_test_configure!(run_in_browser);
+wasm_bindgen_test_configure!(run_in_dedicated_worker);
+
+use web_fs::*;
+
+#[wasm_bindgen_test]
+async fn batch() {
+    console_error_panic_hook::set_once();
+    create_dir_all("batch_test").await.unwrap();
+    for i in 0..50 {
+        write(format!("batch_test/{i}"), format!("file {i}"))
+            .await
+            .unwrap();
+    }
+
+    let mut batch = Batch::new();
+    for i in 0..50 {
+        batch.read(format!("batch_test/{i}"));
+    }
+    batch
+        .read_range("batch_test/7", 5, 100)
+        .read("batch_test/missing")
+        .write("batch_test/new", "new file")
+        .set_len("batch_test/0", 4)
+        .metadata("batch_test/1")
+        .remove("batch_test/2");
+    let results = batch.run().await;
+    for (i, result) in results[..50].iter().enumerate() {
+        assert_eq!(
+            format!("file {i}").as_bytes(),
+            result.as_ref().unwrap().data()
+        );
+    }
+    assert_eq!(b"7", results[50].as_ref().unwrap().data());
+    assert_eq!(
+        ErrorKind::NotFound,
+        results[51].as_ref().unwrap_err().kind()
+    );
+    results[52].as_ref().unwrap();
+    results[53].as_ref().unwrap();
+    let BatchOutput::Metadata(stat) = results[54].as_ref().unwrap() else {
+        panic!("expected metadata");
+    };
+    assert_eq!(6, stat.len());
+    results[55].as_ref().unwrap();
+
+    assert_eq!("new file", read_to_string("batch_test/new").await.unwrap());
+    assert_eq!("file", read_to_string("batch_test/0").await.unwrap());
+    assert_eq!(
+        ErrorKind::NotFound,
+        metadata("batch_test/2").await.unwrap_err().kind()
+    );
+
+    remove_dir_all("batch_test").await.unwrap();
+}
+
+#[wasm_bindgen_test]
+async fn batch_in_order() {
+    console_error_panic_hook::set_once();
+    create_dir_all("batch_order").await.unwrap();
+
+    // Every operation sees the ones before it.
+    let results = Batch::new()
+        .write("batch_order/a", "first")
+        .read("batch_order/a")
+        .write_at("batch_order/a", 2, "RS")
+        .flush("batch_order/a")
+        .metadata("batch_order/a")
+        .read("batch_order/a")
+        .remove("batch_order/a")
+        .read("batch_order/a")
+        .open(
+            "batch_order/a",
+            OpenOptions::new().write(true).create_new(true),
+        )
+        .open(
+            "batch_order/a",
+            OpenOptions::new().write(true).create_new(true),
+        )
+        .write_at("batch_order/a", 0, "new")
+        .run()
+        .await;
+    results[0].as_ref().unwrap();
+    assert_eq!(b"first", results[1].as_ref().unwrap().data());
+    results[2].as_ref().unwrap();
+    results[3].as_ref().unwrap();
+    let BatchOutput::Metadata(stat) = results[4].as_ref().unwrap() else {
+        panic!("expected metadata");
+    };
+    assert_eq!(5, stat.len());
+    assert_eq!(b"fiRSt", results[5].as_ref().unwrap().data());
+    results[6].as_ref().unwrap();
+    assert_eq!(ErrorKind::NotFound, results[7].as_ref().unwrap_err().kind());
+    results[8].as_ref().unwrap();
+    assert_eq!(
+        ErrorKind::AlreadyExists,
+        results[9].as_ref().unwrap_err().kind()
+    );
+    results[10].as_ref().unwrap();
+    assert_eq!("new", read_to_string("batch_order/a").await.unwrap());
+
+    // A file the app has open goes through its handle.
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("batch_order/a")
+        .await
+        .unwrap();
+    let results = Batch::new()
+        .write_at("batch_order/a", 3, "er")
+        .read("batch_order/a")
+        .metadata("batch_order/a")
+        .run()
+        .await;
+    results[0].as_ref().unwrap();
+    assert_eq!(b"newer", results[1].as_ref().unwrap().data());
+    let BatchOutput::Metadata(stat) = results[2].as_ref().unwrap() else {
+        panic!("expected metadata");
+    };
+    assert_eq!(5, stat.len());
+    drop(file);
+
+    remove_dir_all("batch_order").await.unwrap();
+}
@@ -0,0 +1,83 @@
+// This only runs in the browser
+#![cfg(target_arch = "wasm32")]
+
+use futures_lite::AsyncWriteExt;
+use js_sys::{Array, Uint8Array};
+use wasm_bindgen_futures::JsFuture;
+use wasm_bindgen_test::*;
+use web_sys::Blob;
+
+use wasm_bindgen_test::wasm_bindgen_test_configure;
+
+wasm_bindgen_test_configure!(run_in_browser);
+wasm_bindgen_test_configure!(run_in_dedicated_worker);
+
+use web_fs::*;
+
+async fn blob_bytes(blob: &Blob) -> Vec<u8> {
+    let buf = JsFuture::from(blob.array_buffer()).await.unwrap();
+    Uint8Array::new(&buf).to_vec()
+}
+
+async fn blob_round_trip(dir: &str) {
+    let data: Vec<u8> = (0..3_000_000u32).map(|i| (i % 251) as u8).collect();
+    let parts = Array::of1(&Uint8Array::from(&data[..]));
+    let blob = Blob::new_with_u8_array_sequence(&parts).unwrap();
+
+    create_dir_all(dir).await.unwrap();
+    let path = format!("{dir}/file");
+    write_blob(&path, &blob).await.unwrap();
+    assert_eq!(data, read(&path).await.unwrap());
+    assert_eq!(data, blob_bytes(&web_fs::blob(&path).await.unwrap()).await);
+
+    // Written at the cursor.
+    let mut file = OpenOptions::new()
+        .write(true)
+        .append(true)
+        .open(&path)
+        .await
+        .unwrap();
+    let tail = Blob::new_with_str_sequence(&Array::of1(&"tail".into())).unwrap();
+    assert_eq!(4, file.write_blob(&tail).await.unwrap());
+    drop(file);
+    let content = blob_bytes(&File::open(&path).await.unwrap().to_blob().await.unwrap()).await;
+    assert_eq!(&data[..], &content[..data.len()]);
+    assert_eq!(b"tail", &content[data.len()..]);
+
+    let url = object_url(&path).await.unwrap();
+    assert!(url.as_str().starts_with("blob:"));
+
+    remove_dir_all(dir).await.unwrap();
+}
+
+#[wasm_bindgen_test]
+async fn blob_worker() {
+    console_error_panic_hook::set_once();
+    set_backend(OpfsBackend::new());
+    blob_round_trip("blob_worker").await;
+
+    // Writes are flushed before taking the blob.
+    create_dir_all("blob_worker").await.unwrap();
+    let mut file = File::create("blob_worker/unflushed").await.unwrap();
+    file.write_all(b"unflushed").await.unwrap();
+    assert_eq!(
+        b"unflushed",
+        &blob_bytes(&file.to_blob().await.unwrap()).await[..]
+    );
+    drop(file);
+    remove_dir_all("blob_worker").await.unwrap();
+}
+
+#[wasm_bindgen_test]
+async fn blob_main_thread() {
+    console_error_panic_hook::set_once();
+    set_backend(OpfsBackend::main_thread());
+    blob_round_trip("blob_main_thread").await;
+}
+
+#[wasm_bindgen_test]
+async fn blob_memory() {
+    console_error_panic_hook::set_once();
+    set_backend(MemoryBackend::new());
+    blob_round_trip("blob_memory").await;
+}
@@ -0,0 +1,60 @@
+// Runs natively with `cargo test`
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::io::SeekFrom;
+
+use futures_lite::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt, future::block_on};
+
+use web_fs::*;
+
+#[test]
+fn buffered_writes() {
+    set_backend(MemoryBackend::new());
+    block_on(async {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open("records")
+            .await
+            .unwrap();
+        let mut file = BufFile::with_capacity(16, file);
+        let mut expected = String::new();
+        for i in 0..100 {
+            let record = format!("{i},");
+            file.write_all(record.as_bytes()).await.unwrap();
+            expected += &record;
+        }
+        file.flush().await.unwrap();
+
+        // Reads and seeks see the buffered writes.
+        file.write_all(b"end").await.unwrap();
+        file.seek(SeekFrom::Start(0)).await.unwrap();
+        let mut content = String::new();
+        file.read_to_string(&mut content).await.unwrap();
+        expected += "end";
+        assert_eq!(expected, content);
+
+        // Sent when dropped.
+        file.write_all(b"!").await.unwrap();
+        drop(file);
+        expected += "!";
+        assert_eq!(expected, read_to_string("records").await.unwrap());
+    });
+}
+
+#[test]
+fn errors_on_next_operation() {
+    set_backend(MemoryBackend::new());
+    block_on(async {
+        write("read_only", "data").await.unwrap();
+        let mut file = BufFile::new(File::open("read_only").await.unwrap());
+        // Only buffered, the error comes with the flush.
+        file.write_all(b"more").await.unwrap();
+        assert!(file.flush().await.is_err());
+        assert_eq!("data", read_to_string("read_only").await.unwrap());
+        // Nothing was written, so the cursor and size didn't move.
+        assert_eq!(0, file.seek(SeekFrom::Current(0)).await.unwrap());
+        assert_eq!(4, file.get_ref().metadata().await.unwrap().len());
+    });
+}
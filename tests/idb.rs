@@ -0,0 +1,105 @@
+// This only runs in the browser
+#![cfg(target_arch = "wasm32")]
+
+use std::io::{ErrorKind, SeekFrom};
+
+use futures_lite::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt, StreamExt};
+use wasm_bindgen_test::*;
+
+use wasm_bindgen_test::wasm_bindgen_test_configure;
+
+wasm_bindgen_test_configure!(run_in_browser);
+wasm_bindgen_test_configure!(run_in_dedicated_worker);
+
+use web_fs::*;
+
+#[wasm_bindgen_test]
+async fn idb_read_write() {
+    console_error_panic_hook::set_once();
+    set_backend(IdbBackend::with_name("web-fs-test-read-write"));
+    create_dir_all("idb/dir").await.unwrap();
+
+    // spans several chunks
+    let content: Vec<u8> = (0..200_000u32).map(|i| i as u8).collect();
+    write("idb/dir/file", &content).await.unwrap();
+    assert_eq!(content, read("idb/dir/file").await.unwrap());
+
+    {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open("idb/dir/file")
+            .await
+            .unwrap();
+        file.seek(SeekFrom::Start(65_530)).await.unwrap();
+        file.write_all(b"across a boundary").await.unwrap();
+        file.seek(SeekFrom::Start(65_530)).await.unwrap();
+        let mut buf = vec![0; 17];
+        file.read_exact(&mut buf).await.unwrap();
+        assert_eq!(b"across a boundary", &buf[..]);
+
+        file.set_len(70_000).await.unwrap();
+        // writing past the end fills the gap with zeros
+        file.seek(SeekFrom::Start(80_000)).await.unwrap();
+        file.write_all(b"end").await.unwrap();
+    }
+    let read_back = read("idb/dir/file").await.unwrap();
+    assert_eq!(80_003, read_back.len());
+    assert_eq!(&content[..65_530], &read_back[..65_530]);
+    assert!(read_back[70_000..80_000].iter().all(|byte| *byte == 0));
+    assert_eq!(b"end", &read_back[80_000..]);
+
+    // gaps spanning several chunks are filled one chunk at a time
+    {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .open("idb/dir/file")
+            .await
+            .unwrap();
+        file.set_len(150_000).await.unwrap();
+        file.seek(SeekFrom::Start(400_000)).await.unwrap();
+        file.write_all(b"far").await.unwrap();
+    }
+    let read_back = read("idb/dir/file").await.unwrap();
+    assert_eq!(400_003, read_back.len());
+    assert_eq!(b"end", &read_back[80_000..80_003]);
+    assert!(read_back[80_003..400_000].iter().all(|byte| *byte == 0));
+    assert_eq!(b"far", &read_back[400_000..]);
+
+    remove_dir_all("idb").await.unwrap();
+}
+
+#[wasm_bindgen_test]
+async fn idb_dirs() {
+    console_error_panic_hook::set_once();
+    set_backend(IdbBackend::with_name("web-fs-test-dirs"));
+    create_dir_all("idb/a/b").await.unwrap();
+    write("idb/a/file", "file").await.unwrap();
+    write("idb/a/b/nested", "nested").await.unwrap();
+
+    let mut names = Vec::new();
+    let mut entries = read_dir("idb/a").await.unwrap();
+    while let Some(entry) = entries.next().await {
+        names.push(entry.unwrap().file_name().into_string().unwrap());
+    }
+    names.sort();
+    assert_eq!(vec!["b", "file"], names);
+
+    assert_eq!(
+        ErrorKind::DirectoryNotEmpty,
+        remove_dir("idb/a").await.unwrap_err().kind()
+    );
+    {
+        let _file = File::create("idb/a/file").await.unwrap();
+        assert_eq!(
+            ErrorKind::PermissionDenied,
+            rename("idb/a", "idb/c").await.unwrap_err().kind()
+        );
+    }
+    rename("idb/a", "idb/c").await.unwrap();
+    assert_eq!("nested", read_to_string("idb/c/b/nested").await.unwrap());
+    assert!(metadata("idb/a").await.is_err());
+
+    remove_dir_all("idb").await.unwrap();
+    assert!(metadata("idb/c/b/nested").await.is_err());
+}
@@ -0,0 +1,91 @@
+// This only runs in the browser
+#![cfg(target_arch = "wasm32")]
+
+use std::fs::TryLockError;
+
+use futures_lite::future::poll_once;
+use wasm_bindgen_test::*;
+
+use wasm_bindgen_test::wasm_bindgen_test_configure;
+
+wasm_bindgen_test_configure!(run_in_browser);
+wasm_bindgen_test_configure!(run_in_dedicated_worker);
+
+use web_fs::*;
+
+#[wasm_bindgen_test]
+async fn file_locks() {
+    console_error_panic_hook::set_once();
+    write("locked", "content").await.unwrap();
+
+    let first = File::open("locked").await.unwrap();
+    let second = File::open("locked").await.unwrap();
+    first.lock().await.unwrap();
+    assert!(matches!(
+        second.try_lock().await,
+        Err(TryLockError::WouldBlock)
+    ));
+    assert!(matches!(
+        second.try_lock_shared().await,
+        Err(TryLockError::WouldBlock)
+    ));
+    first.unlock().unwrap();
+    second.try_lock().await.unwrap();
+    second.unlock().unwrap();
+
+    first.lock_shared().await.unwrap();
+    second.try_lock_shared().await.unwrap();
+    drop(first);
+    drop(second);
+
+    // The second writer waits until the first one is dropped.
+    let writer = OpenOptions::new()
+        .write(true)
+        .wait_for_lock(true)
+        .open("locked")
+        .await
+        .unwrap();
+    let mut waiting = OpenOptions::new()
+        .write(true)
+        .wait_for_lock(true)
+        .open("locked");
+    assert!(poll_once(&mut waiting).await.is_none());
+    drop(writer);
+    waiting.await.unwrap();
+
+    remove_file("locked").await.unwrap();
+}
+
+#[wasm_bindgen_test]
+async fn convert_file_locks() {
+    console_error_panic_hook::set_once();
+    write("converted", "content").await.unwrap();
+
+    let first = File::open("converted").await.unwrap();
+    let second = File::open("converted").await.unwrap();
+    // Upgraded while nobody else holds it.
+    first.try_lock_shared().await.unwrap();
+    first.try_lock().await.unwrap();
+    assert!(matches!(
+        second.try_lock_shared().await,
+        Err(TryLockError::WouldBlock)
+    ));
+    // Downgraded, letting other shared locks in.
+    first.try_lock_shared().await.unwrap();
+    second.try_lock_shared().await.unwrap();
+    // A failed upgrade keeps the shared lock.
+    assert!(matches!(
+        first.try_lock().await,
+        Err(TryLockError::WouldBlock)
+    ));
+    second.unlock().unwrap();
+    assert!(matches!(
+        second.try_lock().await,
+        Err(TryLockError::WouldBlock)
+    ));
+    first.try_lock().await.unwrap();
+    drop(first);
+    drop(second);
+
+    remove_file("converted").await.unwrap();
+}
@@ -0,0 +1,64 @@
+// This only runs in the browser
+#![cfg(target_arch = "wasm32")]
+
+use std::io::SeekFrom;
+
+use futures_lite::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use wasm_bindgen_test::*;
+
+use wasm_bindgen_test::wasm_bindgen_test_configure;
+
+wasm_bindgen_test_configure!(run_in_browser);
+wasm_bindgen_test_configure!(run_in_dedicated_worker);
+
+use web_fs::*;
+
+#[wasm_bindgen_test]
+async fn main_thread_read_write() {
+    console_error_panic_hook::set_once();
+    set_backend(OpfsBackend::main_thread());
+    create_dir_all("main_thread").await.unwrap();
+    write("main_thread/file", "Hello World").await.unwrap();
+    assert_eq!(
+        "Hello World",
+        read_to_string("main_thread/file").await.unwrap()
+    );
+
+    {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open("main_thread/file")
+            .await
+            .unwrap();
+        file.seek(SeekFrom::Start(6)).await.unwrap();
+        file.write_all(b"Rust!").await.unwrap();
+        // writes are only visible after a flush
+        file.flush().await.unwrap();
+        file.seek(SeekFrom::Start(0)).await.unwrap();
+        let mut buf = String::new();
+        file.read_to_string(&mut buf).await.unwrap();
+        assert_eq!("Hello Rust!", buf);
+        file.set_len(5).await.unwrap();
+    }
+    assert_eq!("Hello", read_to_string("main_thread/file").await.unwrap());
+
+    remove_dir_all("main_thread").await.unwrap();
+}
+
+#[wasm_bindgen_test]
+async fn main_thread_failed_flush() {
+    console_error_panic_hook::set_once();
+    set_backend(OpfsBackend::main_thread());
+    create_dir_all("main_thread_flush").await.unwrap();
+    let mut file = File::create("main_thread_flush/file").await.unwrap();
+    file.write_all(b"Hello").await.unwrap();
+    // committing fails once the file is gone
+    remove_file("main_thread_flush/file").await.unwrap();
+    assert!(file.flush().await.is_err());
+    let error = file.write_all(b"World").await.unwrap_err();
+    assert_ne!(std::io::ErrorKind::PermissionDenied, error.kind());
+    drop(file);
+
+    remove_dir_all("main_thread_flush").await.unwrap();
+}
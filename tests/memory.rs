@@ -0,0 +1,347 @@
+// Runs natively with `cargo test`
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::io::{ErrorKind, IoSlice, IoSliceMut, SeekFrom};
+
+use futures_lite::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt, StreamExt, future::block_on};
+
+use web_fs::*;
+
+#[test]
+fn read_write() {
+    set_backend(MemoryBackend::new());
+    block_on(async {
+        create_dir_all("dir/sub").await.unwrap();
+        write("dir/sub/file", "Hello World").await.unwrap();
+        assert_eq!("Hello World", read_to_string("dir/sub/file").await.unwrap());
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open("dir/sub/file")
+            .await
+            .unwrap();
+        file.seek(SeekFrom::Start(6)).await.unwrap();
+        file.write_all(b"Rust!").await.unwrap();
+        file.seek(SeekFrom::Start(0)).await.unwrap();
+        let mut buf = String::new();
+        file.read_to_string(&mut buf).await.unwrap();
+        assert_eq!("Hello Rust!", buf);
+        file.set_len(5).await.unwrap();
+        assert_eq!(5, file.metadata().await.unwrap().len());
+        drop(file);
+
+        let mut file = OpenOptions::new()
+            .append(true)
+            .write(true)
+            .open("dir/sub/file")
+            .await
+            .unwrap();
+        file.write_all(b"!").await.unwrap();
+        drop(file);
+        assert_eq!(b"Hello!", &read("dir/sub/file").await.unwrap()[..]);
+
+        let meta = metadata("dir/sub/file").await.unwrap();
+        assert!(meta.is_file());
+        assert_eq!(6, meta.len());
+        assert!(metadata("dir/sub").await.unwrap().is_dir());
+    });
+}
+
+#[test]
+fn errors() {
+    set_backend(MemoryBackend::new());
+    block_on(async {
+        assert_eq!(
+            ErrorKind::NotFound,
+            File::open("missing").await.err().unwrap().kind()
+        );
+        assert_eq!(
+            ErrorKind::NotFound,
+            File::create("missing/file").await.err().unwrap().kind()
+        );
+        write("file", "").await.unwrap();
+        assert_eq!(
+            ErrorKind::AlreadyExists,
+            File::create_new("file").await.err().unwrap().kind()
+        );
+        assert_eq!(
+            ErrorKind::PermissionDenied,
+            File::open("../file").await.err().unwrap().kind()
+        );
+
+        create_dir("dir").await.unwrap();
+        // Creating an existing directory succeeds, as in OPFS.
+        create_dir("dir").await.unwrap();
+        write("dir/file", "").await.unwrap();
+        assert_eq!(
+            ErrorKind::DirectoryNotEmpty,
+            remove_dir("dir").await.unwrap_err().kind()
+        );
+        remove_dir_all("dir").await.unwrap();
+        assert_eq!(
+            ErrorKind::NotFound,
+            metadata("dir").await.unwrap_err().kind()
+        );
+    });
+}
+
+#[test]
+fn huge_offsets() {
+    set_backend(MemoryBackend::new());
+    block_on(async {
+        let mut file = File::create("huge").await.unwrap();
+        file.seek(SeekFrom::Start(u64::MAX)).await.unwrap();
+        assert_eq!(
+            ErrorKind::InvalidInput,
+            file.write(b"data").await.unwrap_err().kind()
+        );
+        file.seek(SeekFrom::Start(1 << 62)).await.unwrap();
+        assert_eq!(
+            ErrorKind::OutOfMemory,
+            file.write(b"data").await.unwrap_err().kind()
+        );
+        assert_eq!(
+            ErrorKind::OutOfMemory,
+            file.set_len(u64::MAX).await.unwrap_err().kind()
+        );
+        // Still usable afterwards.
+        file.seek(SeekFrom::Start(0)).await.unwrap();
+        file.write_all(b"data").await.unwrap();
+        assert_eq!(4, metadata("huge").await.unwrap().len());
+    });
+}
+
+#[test]
+fn open_files_are_locked() {
+    set_backend(MemoryBackend::new());
+    block_on(async {
+        write("file", "content").await.unwrap();
+        {
+            let _writer = OpenOptions::new().write(true).open("file").await.unwrap();
+            assert_eq!(
+                ErrorKind::PermissionDenied,
+                File::open("file").await.err().unwrap().kind()
+            );
+            assert_eq!(
+                ErrorKind::PermissionDenied,
+                remove_file("file").await.unwrap_err().kind()
+            );
+        }
+        {
+            // Readers can share a file.
+            let _reader1 = File::open("file").await.unwrap();
+            let _reader2 = File::open("file").await.unwrap();
+            assert_eq!(
+                ErrorKind::PermissionDenied,
+                File::create("file").await.err().unwrap().kind()
+            );
+        }
+        remove_file("file").await.unwrap();
+    });
+}
+
+#[test]
+fn read_dir_rename_copy() {
+    set_backend(MemoryBackend::new());
+    block_on(async {
+        create_dir("dir").await.unwrap();
+        write("dir/a", "a").await.unwrap();
+        create_dir("dir/b").await.unwrap();
+
+        let mut names = Vec::new();
+        let mut entries = read_dir("dir").await.unwrap();
+        while let Some(entry) = entries.next().await {
+            let entry = entry.unwrap();
+            let file_type = entry.file_type().await.unwrap();
+            names.push((entry.file_name().into_string().unwrap(), file_type));
+        }
+        names.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            vec![
+                ("a".to_string(), FileType::File),
+                ("b".to_string(), FileType::Dir)
+            ],
+            names
+        );
+
+        rename("dir/a", "dir/b/c").await.unwrap();
+        assert_eq!("a", read_to_string("dir/b/c").await.unwrap());
+        assert_eq!(1, copy("dir/b/c", "dir/d").await.unwrap());
+        assert_eq!("a", read_to_string("dir/d").await.unwrap());
+
+        copy_dir_all("dir", "copied").await.unwrap();
+        assert_eq!("a", read_to_string("copied/b/c").await.unwrap());
+        assert_eq!(
+            ErrorKind::InvalidInput,
+            copy_dir_all("dir", "/dir/./b/inner")
+                .await
+                .unwrap_err()
+                .kind()
+        );
+        assert!(metadata("dir/b/inner").await.is_err());
+    });
+}
+
+#[test]
+fn atomic_and_temp() {
+    set_backend(MemoryBackend::new());
+    block_on(async {
+        write("config", "old").await.unwrap();
+        let mut file = AtomicFile::create("config").await.unwrap();
+        file.write_all(b"discarded").await.unwrap();
+        drop(file);
+        write_atomic("config", "new").await.unwrap();
+        assert_eq!("new", read_to_string("config").await.unwrap());
+        assert_eq!(1, read_dir("").await.unwrap().count().await);
+
+        let path = {
+            let mut file = tempfile().await.unwrap();
+            file.write_all(b"temp").await.unwrap();
+            file.path().to_path_buf()
+        };
+        assert!(metadata(path).await.is_err());
+
+        // Without the Web Locks API there is no telling whether the writer is gone, so it is kept.
+        create_dir_all("nested/deep").await.unwrap();
+        write("nested/deep/.state.1234.0.web-fs-atomic", "in progress")
+            .await
+            .unwrap();
+        write("nested/deep/state", "kept").await.unwrap();
+        remove_stale_files().await.unwrap();
+        assert!(
+            metadata("nested/deep/.state.1234.0.web-fs-atomic")
+                .await
+                .is_ok()
+        );
+        assert_eq!("kept", read_to_string("nested/deep/state").await.unwrap());
+    });
+}
+
+#[test]
+fn backends_are_isolated() {
+    let backend = MemoryBackend::new();
+    set_backend(backend.clone());
+    block_on(write("file", "shared")).unwrap();
+    set_backend(MemoryBackend::new());
+    assert!(block_on(metadata("file")).is_err());
+    set_backend(backend);
+    assert_eq!("shared", block_on(read_to_string("file")).unwrap());
+}
+
+#[test]
+fn vectored() {
+    set_backend(MemoryBackend::new());
+    block_on(async {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open("framed")
+            .await
+            .unwrap();
+        let header = 5u32.to_le_bytes();
+        let written = file
+            .write_vectored(&[IoSlice::new(&header), IoSlice::new(b"hello")])
+            .await
+            .unwrap();
+        assert_eq!(9, written);
+
+        file.seek(SeekFrom::Start(0)).await.unwrap();
+        let mut header = [0; 4];
+        let mut body = [0; 8];
+        let read = file
+            .read_vectored(&mut [IoSliceMut::new(&mut header), IoSliceMut::new(&mut body)])
+            .await
+            .unwrap();
+        assert_eq!(9, read);
+        assert_eq!(5, u32::from_le_bytes(header));
+        assert_eq!(b"hello", &body[..5]);
+    });
+}
+
+#[test]
+fn batch() {
+    set_backend(MemoryBackend::new());
+    block_on(async {
+        create_dir_all("batch").await.unwrap();
+        write("batch/a", "Hello World").await.unwrap();
+        write("batch/b", "to remove").await.unwrap();
+
+        // The memory backend runs the operations one after another.
+        let results = Batch::new()
+            .read("batch/a")
+            .read_range("batch/a", 6, 3)
+            .read("batch/missing")
+            .write("batch/c", "written")
+            .set_len("batch/a", 5)
+            .metadata("batch/a")
+            .remove("batch/b")
+            .read("")
+            .run()
+            .await;
+        assert_eq!(b"Hello World", results[0].as_ref().unwrap().data());
+        assert_eq!(b"Wor", results[1].as_ref().unwrap().data());
+        assert_eq!(ErrorKind::NotFound, results[2].as_ref().unwrap_err().kind());
+        results[3].as_ref().unwrap();
+        results[4].as_ref().unwrap();
+        let BatchOutput::Metadata(stat) = results[5].as_ref().unwrap() else {
+            panic!("expected metadata");
+        };
+        assert_eq!(5, stat.len());
+        results[6].as_ref().unwrap();
+        assert!(results[7].is_err());
+
+        assert_eq!("written", read_to_string("batch/c").await.unwrap());
+        assert_eq!("Hello", read_to_string("batch/a").await.unwrap());
+        assert_eq!(
+            ErrorKind::NotFound,
+            metadata("batch/b").await.unwrap_err().kind()
+        );
+
+        let results = Batch::new()
+            .write("batch/d", "first")
+            .write_at("batch/d", 2, "RS")
+            .flush("batch/d")
+            .read("batch/d")
+            .remove("batch/d")
+            .read("batch/d")
+            .open("batch/d", OpenOptions::new().write(true).create_new(true))
+            .open("batch/d", OpenOptions::new().write(true).create_new(true))
+            .run()
+            .await;
+        results[1].as_ref().unwrap();
+        results[2].as_ref().unwrap();
+        assert_eq!(b"fiRSt", results[3].as_ref().unwrap().data());
+        assert_eq!(ErrorKind::NotFound, results[5].as_ref().unwrap_err().kind());
+        results[6].as_ref().unwrap();
+        assert_eq!(
+            ErrorKind::AlreadyExists,
+            results[7].as_ref().unwrap_err().kind()
+        );
+    });
+}
+
+#[test]
+fn locks_unsupported() {
+    set_backend(MemoryBackend::new());
+    block_on(async {
+        write("locked", "content").await.unwrap();
+        // Without the Web Locks API, files open without waiting.
+        let file = OpenOptions::new()
+            .read(true)
+            .wait_for_lock(true)
+            .open("locked")
+            .await
+            .unwrap();
+        assert_eq!(
+            ErrorKind::Unsupported,
+            file.lock().await.unwrap_err().kind()
+        );
+        assert!(matches!(
+            file.try_lock().await,
+            Err(std::fs::TryLockError::Error(e)) if e.kind() == ErrorKind::Unsupported
+        ));
+        file.unlock().unwrap();
+    });
+}
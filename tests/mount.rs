@@ -0,0 +1,117 @@
+// This only runs in the browser
+#![cfg(target_arch = "wasm32")]
+
+use std::io::ErrorKind;
+
+use futures_lite::StreamExt;
+use wasm_bindgen_futures::JsFuture;
+use wasm_bindgen_test::*;
+use web_sys::{FileSystemDirectoryHandle, FileSystemGetDirectoryOptions, WorkerGlobalScope};
+
+use wasm_bindgen_test::wasm_bindgen_test_configure;
+
+wasm_bindgen_test_configure!(run_in_browser);
+wasm_bindgen_test_configure!(run_in_dedicated_worker);
+
+use wasm_bindgen::JsCast;
+use web_fs::*;
+
+/// Stands in for a handle from `showDirectoryPicker`, which needs a user gesture.
+async fn opfs_dir(name: &str) -> FileSystemDirectoryHandle {
+    let storage = js_sys::global()
+        .unchecked_into::<WorkerGlobalScope>()
+        .navigator()
+        .storage();
+    let root: FileSystemDirectoryHandle = JsFuture::from(storage.get_directory())
+        .await
+        .unwrap()
+        .unchecked_into();
+    let options = FileSystemGetDirectoryOptions::new();
+    options.set_create(true);
+    JsFuture::from(root.get_directory_handle_with_options(name, &options))
+        .await
+        .unwrap()
+        .unchecked_into()
+}
+
+#[wasm_bindgen_test]
+async fn mount_point() {
+    console_error_panic_hook::set_once();
+    set_backend(OpfsBackend::new());
+    let handle = opfs_dir("mount_source").await;
+    create_dir("mounted").await.unwrap();
+    mount("mounted/project", handle).await.unwrap();
+
+    write("mounted/project/file", "through the mount")
+        .await
+        .unwrap();
+    assert_eq!(
+        "through the mount",
+        read_to_string("mount_source/file").await.unwrap()
+    );
+    assert!(metadata("mounted/project").await.unwrap().is_dir());
+
+    // The mount point is listed although "mounted/project" doesn't exist in the root.
+    let mut names = Vec::new();
+    let mut entries = read_dir("mounted").await.unwrap();
+    while let Some(entry) = entries.next().await {
+        names.push(entry.unwrap().file_name().into_string().unwrap());
+    }
+    assert_eq!(vec!["project"], names);
+
+    unmount("mounted/project").unwrap();
+    assert!(metadata("mounted/project/file").await.is_err());
+    remove_dir("mounted").await.unwrap();
+    remove_dir_all("mount_source").await.unwrap();
+}
+
+#[wasm_bindgen_test]
+async fn mount_table() {
+    console_error_panic_hook::set_once();
+    set_backend(OpfsBackend::new());
+    write("mount_assets/data", "bundled").await.unwrap();
+    MountOptions::new()
+        .read_only(true)
+        .mount("assets", opfs_dir("mount_assets").await)
+        .await
+        .unwrap();
+    mount("project", opfs_dir("mount_project").await)
+        .await
+        .unwrap();
+    // The longest mount point wins.
+    mount("project/nested", opfs_dir("mount_nested").await)
+        .await
+        .unwrap();
+
+    assert_eq!("bundled", read_to_string("assets/data").await.unwrap());
+    assert_eq!(
+        ErrorKind::PermissionDenied,
+        write("assets/data", "changed").await.unwrap_err().kind()
+    );
+    assert_eq!(
+        ErrorKind::PermissionDenied,
+        remove_file("assets/data").await.unwrap_err().kind()
+    );
+
+    write("project/nested/file", "nested").await.unwrap();
+    assert_eq!("nested", read_to_string("mount_nested/file").await.unwrap());
+    assert_eq!(
+        ErrorKind::CrossesDevices,
+        rename("project/nested/file", "project/file")
+            .await
+            .unwrap_err()
+            .kind()
+    );
+    copy("project/nested/file", "project/file").await.unwrap();
+    assert_eq!(
+        "nested",
+        read_to_string("mount_project/file").await.unwrap()
+    );
+
+    for point in ["assets", "project", "project/nested"] {
+        unmount(point).unwrap();
+    }
+    for dir in ["mount_assets", "mount_project", "mount_nested"] {
+        remove_dir_all(dir).await.unwrap();
+    }
+}
@@ -0,0 +1,153 @@
+// Runs natively with `cargo test`
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::{
+    io::{ErrorKind, SeekFrom},
+    path::PathBuf,
+};
+
+use futures_lite::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt, StreamExt, future::block_on};
+
+use web_fs::*;
+
+/// Roots a fresh [`NativeBackend`] in its own directory under the system temp dir.
+fn setup(name: &str) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("web-fs-test-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(&root).unwrap();
+    set_backend(NativeBackend::new(&root));
+    root
+}
+
+#[test]
+fn read_write() {
+    let root = setup("read_write");
+    block_on(async {
+        create_dir_all("dir/sub").await.unwrap();
+        write("dir/sub/file", "Hello World").await.unwrap();
+        assert_eq!(
+            "Hello World",
+            std::fs::read_to_string(root.join("dir/sub/file")).unwrap()
+        );
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open("dir/sub/file")
+            .await
+            .unwrap();
+        file.seek(SeekFrom::Start(6)).await.unwrap();
+        file.write_all(b"Rust!").await.unwrap();
+        file.seek(SeekFrom::Start(0)).await.unwrap();
+        let mut buf = String::new();
+        file.read_to_string(&mut buf).await.unwrap();
+        assert_eq!("Hello Rust!", buf);
+        file.set_len(5).await.unwrap();
+        drop(file);
+
+        let mut file = OpenOptions::new()
+            .append(true)
+            .write(true)
+            .open("dir/sub/file")
+            .await
+            .unwrap();
+        file.write_all(b"!").await.unwrap();
+        drop(file);
+        assert_eq!("Hello!", read_to_string("dir/sub/file").await.unwrap());
+
+        let meta = metadata("dir/sub/file").await.unwrap();
+        assert!(meta.is_file());
+        assert_eq!(6, meta.len());
+        assert!(meta.modified().is_ok());
+
+        assert_eq!(6, copy("dir/sub/file", "copied").await.unwrap());
+        rename("copied", "dir/renamed").await.unwrap();
+        let mut names = Vec::new();
+        let mut entries = read_dir("dir").await.unwrap();
+        while let Some(entry) = entries.next().await {
+            names.push(entry.unwrap().file_name().into_string().unwrap());
+        }
+        names.sort();
+        assert_eq!(vec!["renamed", "sub"], names);
+    });
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn differences_from_async_fs() {
+    let root = setup("differences");
+    block_on(async {
+        // Paths can't leave the root.
+        write("/absolute", "inside").await.unwrap();
+        assert_eq!(
+            "inside",
+            std::fs::read_to_string(root.join("absolute")).unwrap()
+        );
+        assert_eq!(
+            ErrorKind::PermissionDenied,
+            File::open("../absolute").await.err().unwrap().kind()
+        );
+
+        // Creating an existing directory succeeds.
+        create_dir("dir").await.unwrap();
+        create_dir("dir").await.unwrap();
+
+        // `remove_file` removes empty directories and `remove_dir` removes files.
+        remove_file("dir").await.unwrap();
+        remove_dir("absolute").await.unwrap();
+        assert_eq!(0, read_dir("").await.unwrap().count().await);
+
+        // Unlike OPFS, a file can be opened twice.
+        write("file", "").await.unwrap();
+        let _writer = OpenOptions::new().write(true).open("file").await.unwrap();
+        File::open("file").await.unwrap();
+
+        assert_eq!(
+            ErrorKind::Unsupported,
+            watch("", true).await.err().unwrap().kind()
+        );
+    });
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn sized_reads() {
+    let root = setup("sized_reads");
+    block_on(async {
+        let data: Vec<u8> = (0..3_000_000u32).map(|i| (i % 251) as u8).collect();
+        write("big", &data).await.unwrap();
+        assert_eq!(data, read("big").await.unwrap());
+
+        let mut file = File::open("big").await.unwrap();
+        file.seek(SeekFrom::Start(1000)).await.unwrap();
+        let mut buf = Vec::new();
+        assert_eq!(
+            data.len() - 1000,
+            file.read_to_end_exact(&mut buf).await.unwrap()
+        );
+        assert_eq!(&data[1000..], buf);
+
+        // The file is shorter than when it was opened.
+        let mut file = File::open("big").await.unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(root.join("big"))
+            .unwrap()
+            .set_len(10)
+            .unwrap();
+        assert_eq!(
+            ErrorKind::UnexpectedEof,
+            file.read_to_end_exact(&mut Vec::new())
+                .await
+                .unwrap_err()
+                .kind()
+        );
+
+        write("invalid", [0xff, 0xfe]).await.unwrap();
+        assert_eq!(
+            ErrorKind::InvalidData,
+            read_to_string("invalid").await.unwrap_err().kind()
+        );
+    });
+    std::fs::remove_dir_all(root).unwrap();
+}
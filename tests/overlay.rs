@@ -0,0 +1,105 @@
+// Runs natively with `cargo test`
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::io::ErrorKind;
+
+use futures_lite::{AsyncWriteExt, StreamExt, future::block_on};
+
+use web_fs::*;
+
+/// An overlay on top of a lower layer with `templates/{a,b}` and `levels/1`.
+fn setup() -> (MemoryBackend, MemoryBackend) {
+    let lower = MemoryBackend::new();
+    set_backend(lower.clone());
+    block_on(async {
+        create_dir_all("templates").await.unwrap();
+        write("templates/a", "lower a").await.unwrap();
+        write("templates/b", "lower b").await.unwrap();
+        create_dir_all("levels").await.unwrap();
+        write("levels/1", "level 1").await.unwrap();
+    });
+    let upper = MemoryBackend::new();
+    set_backend(OverlayBackend::new(upper.clone(), lower.clone()));
+    (upper, lower)
+}
+
+async fn names(path: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut entries = read_dir(path).await.unwrap();
+    while let Some(entry) = entries.next().await {
+        names.push(entry.unwrap().file_name().into_string().unwrap());
+    }
+    names.sort();
+    names
+}
+
+#[test]
+fn copy_up_and_merge() {
+    let (upper, lower) = setup();
+    block_on(async {
+        assert_eq!("lower a", read_to_string("templates/a").await.unwrap());
+
+        write("templates/a", "upper a").await.unwrap();
+        write("templates/c", "upper c").await.unwrap();
+        assert_eq!("upper a", read_to_string("templates/a").await.unwrap());
+        assert_eq!(vec!["a", "b", "c"], names("templates").await);
+
+        // Appending copies the content up first.
+        let mut file = OpenOptions::new()
+            .append(true)
+            .write(true)
+            .open("templates/b")
+            .await
+            .unwrap();
+        file.write_all(b"!").await.unwrap();
+        drop(file);
+        assert_eq!("lower b!", read_to_string("templates/b").await.unwrap());
+    });
+    set_backend(lower);
+    block_on(async {
+        assert_eq!("lower a", read_to_string("templates/a").await.unwrap());
+        assert_eq!("lower b", read_to_string("templates/b").await.unwrap());
+        assert!(metadata("templates/c").await.is_err());
+    });
+    set_backend(upper);
+    assert_eq!(vec!["a", "b", "c"], block_on(names("templates")));
+}
+
+#[test]
+fn whiteouts() {
+    setup();
+    block_on(async {
+        remove_file("templates/a").await.unwrap();
+        assert_eq!(
+            ErrorKind::NotFound,
+            metadata("templates/a").await.unwrap_err().kind()
+        );
+        assert_eq!(vec!["b"], names("templates").await);
+
+        // A removed directory stays hidden when it is created again.
+        remove_dir_all("levels").await.unwrap();
+        create_dir("levels").await.unwrap();
+        assert!(names("levels").await.is_empty());
+        assert_eq!(vec!["levels", "templates"], names("").await);
+
+        write("templates/a", "new a").await.unwrap();
+        assert_eq!("new a", read_to_string("templates/a").await.unwrap());
+    });
+}
+
+#[test]
+fn rename_across_layers() {
+    setup();
+    block_on(async {
+        rename("templates/a", "templates/renamed").await.unwrap();
+        assert_eq!(
+            "lower a",
+            read_to_string("templates/renamed").await.unwrap()
+        );
+        assert_eq!(vec!["b", "renamed"], names("templates").await);
+        assert_eq!(
+            ErrorKind::CrossesDevices,
+            rename("levels", "moved").await.unwrap_err().kind()
+        );
+    });
+}
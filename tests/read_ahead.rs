@@ -0,0 +1,51 @@
+// Runs natively with `cargo test`
+#![cfg(not(target_arch = "wasm32"))]
+
+use futures_lite::{AsyncBufReadExt, AsyncReadExt, StreamExt, future::block_on};
+
+use web_fs::*;
+
+fn log() -> String {
+    (0..1000).map(|i| format!("entry {i}\n")).collect()
+}
+
+#[test]
+fn read_ahead_chunks() {
+    set_backend(MemoryBackend::new());
+    block_on(async {
+        let log = log();
+        write("log", &log).await.unwrap();
+
+        // Chunks smaller than a line, with several in flight.
+        let mut reader = ReadAhead::with_capacity(7, 3, File::open("log").await.unwrap());
+        let mut first = String::new();
+        reader.read_line(&mut first).await.unwrap();
+        assert_eq!("entry 0\n", first);
+        let mut rest = String::new();
+        reader.read_to_string(&mut rest).await.unwrap();
+        assert_eq!(log, first + &rest);
+
+        let mut reader = ReadAhead::with_capacity(100, 4, File::open("log").await.unwrap());
+        let mut buf = [0; 10];
+        reader.read_exact(&mut buf).await.unwrap();
+        let mut file = reader.into_inner();
+        let mut rest = String::new();
+        file.read_to_string(&mut rest).await.unwrap();
+        assert_eq!(&log[10..], rest);
+    });
+}
+
+#[test]
+fn lines_of_file() {
+    let dir = std::env::temp_dir().join(format!("web-fs-lines-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    // Reads are issued one at a time with the native backend.
+    set_backend(NativeBackend::new(&dir));
+    block_on(async {
+        write("log", log()).await.unwrap();
+        let lines: Vec<String> = lines("log").await.unwrap().try_collect().await.unwrap();
+        assert_eq!(1000, lines.len());
+        assert_eq!("entry 999", lines[999]);
+    });
+    std::fs::remove_dir_all(dir).unwrap();
+}
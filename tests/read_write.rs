@@ -53,6 +53,12 @@ async fn read_write() {
         copy("testf", "testf2").await.unwrap();
         assert_eq!("Hello, FS!", read_to_string("testf2").await.unwrap());
     }
+    // copy overwrites
+    {
+        write("testf3", "A longer existing content").await.unwrap();
+        assert_eq!(10, copy("testf", "testf3").await.unwrap());
+        assert_eq!("Hello, FS!", read_to_string("testf3").await.unwrap());
+    }
     // truncate
     {
         let mut file = OpenOptions::new()
@@ -97,3 +103,47 @@ async fn multi_file() {
         assert_eq!("This is file3", buf);
     }
 }
+
+#[wasm_bindgen_test]
+async fn metadata_and_create_new() {
+    console_error_panic_hook::set_once();
+    write("meta", b"12345").await.unwrap();
+    let meta = metadata("meta").await.unwrap();
+    assert!(meta.is_file());
+    assert_eq!(5, meta.len());
+    assert!(meta.modified().is_ok());
+    assert_eq!(
+        std::io::ErrorKind::AlreadyExists,
+        File::create_new("meta").await.err().unwrap().kind()
+    );
+    write("meta", b"1").await.unwrap();
+    assert_eq!(1, metadata("meta").await.unwrap().len());
+    remove_file("meta").await.unwrap();
+}
+
+#[wasm_bindgen_test]
+async fn cached_dirs() {
+    console_error_panic_hook::set_once();
+    create_dir_all("cached/a/b/c").await.unwrap();
+    write("cached/a/b/c/file", "first").await.unwrap();
+    assert_eq!("first", read_to_string("cached/a/b/c/file").await.unwrap());
+
+    // Handles of removed or renamed directories must not be reused.
+    remove_dir_all("cached/a").await.unwrap();
+    assert_eq!(
+        std::io::ErrorKind::NotFound,
+        read("cached/a/b/c/file").await.unwrap_err().kind()
+    );
+    create_dir_all("cached/a/b/c").await.unwrap();
+    write("cached/a/b/c/file", "second").await.unwrap();
+    assert_eq!("second", read_to_string("cached/a/b/c/file").await.unwrap());
+
+    if rename("cached/a", "cached/moved").await.is_ok() {
+        assert!(read("cached/a/b/c/file").await.is_err());
+        assert_eq!(
+            "second",
+            read_to_string("cached/moved/b/c/file").await.unwrap()
+        );
+    }
+    remove_dir_all("cached").await.unwrap();
+}
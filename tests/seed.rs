@@ -0,0 +1,75 @@
+// Runs natively with `cargo test`
+#![cfg(not(target_arch = "wasm32"))]
+
+use futures_lite::future::block_on;
+
+use web_fs::*;
+
+/// Builds a ustar archive, entries with `None` are directories.
+fn tar(entries: &[(&str, Option<&str>)]) -> Vec<u8> {
+    let mut archive = Vec::new();
+    for (name, content) in entries {
+        let mut header = [0; 512];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        let data = content.unwrap_or_default().as_bytes();
+        header[124..136].copy_from_slice(format!("{:011o}\0", data.len()).as_bytes());
+        header[156] = if content.is_some() { b'0' } else { b'5' };
+        header[257..263].copy_from_slice(b"ustar\0");
+        archive.extend_from_slice(&header);
+        archive.extend_from_slice(data);
+        archive.resize(archive.len().next_multiple_of(512), 0);
+    }
+    archive.resize(archive.len() + 1024, 0);
+    archive
+}
+
+#[test]
+fn seed_versions() {
+    set_backend(MemoryBackend::new());
+    block_on(async {
+        let v1 = tar(&[
+            ("levels/", None),
+            ("levels/1", Some("level 1")),
+            ("levels/2", Some("level 2")),
+            ("readme", Some("v1")),
+        ]);
+        assert!(seed(&v1, "data", "1").await.unwrap());
+        assert_eq!("level 1", read_to_string("data/levels/1").await.unwrap());
+        // The same version is not extracted again.
+        write("data/readme", "changed").await.unwrap();
+        assert!(!seed(&v1, "data", "1").await.unwrap());
+        assert_eq!("changed", read_to_string("data/readme").await.unwrap());
+
+        let v2 = tar(&[("levels/1", Some("level 1 v2")), ("readme", Some("v2"))]);
+        assert!(seed(&v2, "data", "2").await.unwrap());
+        assert_eq!("level 1 v2", read_to_string("data/levels/1").await.unwrap());
+        assert_eq!("v2", read_to_string("data/readme").await.unwrap());
+        // Removed from the bundle.
+        assert!(metadata("data/levels/2").await.is_err());
+    });
+}
+
+#[test]
+fn seed_preserving_modified() {
+    set_backend(MemoryBackend::new());
+    block_on(async {
+        let v1 = tar(&[("a", Some("a1")), ("b", Some("b1")), ("c", Some("c1"))]);
+        seed(&v1, "data", "1").await.unwrap();
+        write("data/a", "mine").await.unwrap();
+        write("data/c", "mine too").await.unwrap();
+        write("data/new", "not seeded").await.unwrap();
+
+        let v2 = tar(&[("a", Some("a2")), ("b", Some("b2")), ("new", Some("new2"))]);
+        assert!(
+            SeedOptions::new("2")
+                .preserve_modified(true)
+                .seed(&v2, "data")
+                .await
+                .unwrap()
+        );
+        assert_eq!("mine", read_to_string("data/a").await.unwrap());
+        assert_eq!("b2", read_to_string("data/b").await.unwrap());
+        assert_eq!("mine too", read_to_string("data/c").await.unwrap());
+        assert_eq!("not seeded", read_to_string("data/new").await.unwrap());
+    });
+}
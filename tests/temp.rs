@@ -0,0 +1,70 @@
+// This only runs in the browser
+#![cfg(target_arch = "wasm32")]
+
+use std::{io::ErrorKind, path::Path};
+
+use futures_lite::AsyncWriteExt;
+use wasm_bindgen_test::*;
+
+use wasm_bindgen_test::wasm_bindgen_test_configure;
+
+wasm_bindgen_test_configure!(run_in_browser);
+wasm_bindgen_test_configure!(run_in_dedicated_worker);
+
+use web_fs::*;
+
+/// Waits for the entry at `path` to be removed in the background, returning the last error of `metadata`.
+async fn removed(path: &Path) -> std::io::Result<Metadata> {
+    let mut result = metadata(path).await;
+    for _ in 0..100 {
+        if result.is_err() {
+            break;
+        }
+        result = metadata(path).await;
+    }
+    result
+}
+
+#[wasm_bindgen_test]
+async fn temp_entries() {
+    console_error_panic_hook::set_once();
+    // file
+    {
+        let mut file = tempfile().await.unwrap();
+        let path = file.path().to_path_buf();
+        assert!(path.starts_with(TEMP_DIR));
+        file.write_all(b"scratch").await.unwrap();
+        assert_eq!(7, file.metadata().await.unwrap().len());
+        drop(file);
+        assert_eq!(
+            ErrorKind::NotFound,
+            removed(&path).await.unwrap_err().kind()
+        );
+    }
+    // dir
+    {
+        let dir = tempdir().await.unwrap();
+        let path = dir.path().to_path_buf();
+        write(path.join("extracted"), "data").await.unwrap();
+        dir.close().await.unwrap();
+        assert!(read_dir(&path).await.is_err());
+    }
+    // dir with a file dropped just before
+    {
+        let dir = tempdir().await.unwrap();
+        let path = dir.path().to_path_buf();
+        let file = File::create(path.join("open")).await.unwrap();
+        drop(file);
+        drop(dir);
+        assert_eq!(
+            ErrorKind::NotFound,
+            removed(&path).await.unwrap_err().kind()
+        );
+    }
+    // sweeping keeps the entries of live sessions
+    {
+        let file = tempfile().await.unwrap();
+        remove_stale_temp_files().await.unwrap();
+        assert!(read_dir(file.path().parent().unwrap()).await.is_ok());
+    }
+}
@@ -0,0 +1,39 @@
+// Runs natively with `cargo test --features tokio`
+#![cfg(all(feature = "tokio", not(target_arch = "wasm32")))]
+
+use std::io::SeekFrom;
+
+use futures_lite::future::block_on;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+use web_fs::*;
+
+#[test]
+fn tokio_traits() {
+    set_backend(MemoryBackend::new());
+    block_on(async {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open("file")
+            .await
+            .unwrap();
+        file.write_all(b"Hello World").await.unwrap();
+        file.flush().await.unwrap();
+
+        assert_eq!(6, file.seek(SeekFrom::Start(6)).await.unwrap());
+        file.write_all(b"Tokio").await.unwrap();
+        assert_eq!(3, file.seek(SeekFrom::Current(-8)).await.unwrap());
+        let mut buf = [0; 3];
+        file.read_exact(&mut buf).await.unwrap();
+        assert_eq!(b"lo ", &buf);
+
+        file.rewind().await.unwrap();
+        let mut text = String::new();
+        file.read_to_string(&mut text).await.unwrap();
+        assert_eq!("Hello Tokio", text);
+        file.shutdown().await.unwrap();
+    });
+    assert_eq!("Hello Tokio", block_on(read_to_string("file")).unwrap());
+}
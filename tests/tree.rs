@@ -0,0 +1,60 @@
+// This only runs in the browser
+#![cfg(target_arch = "wasm32")]
+
+use std::{cell::Cell, rc::Rc};
+
+use futures_lite::StreamExt;
+use wasm_bindgen_test::*;
+
+use wasm_bindgen_test::wasm_bindgen_test_configure;
+
+wasm_bindgen_test_configure!(run_in_browser);
+wasm_bindgen_test_configure!(run_in_dedicated_worker);
+
+use web_fs::*;
+
+#[wasm_bindgen_test]
+async fn copy_and_empty_tree() {
+    console_error_panic_hook::set_once();
+    create_dir_all("tree_src/child").await.unwrap();
+    write("tree_src/a", "file a").await.unwrap();
+    write("tree_src/child/b", "file b").await.unwrap();
+
+    // plain copy
+    {
+        let size = copy_dir_all("tree_src", "tree_dst").await.unwrap();
+        assert_eq!(12, size);
+        assert_eq!("file a", read_to_string("tree_dst/a").await.unwrap());
+        assert_eq!("file b", read_to_string("tree_dst/child/b").await.unwrap());
+        assert!(copy_dir_all("tree_src", "tree_dst").await.is_err());
+    }
+    // skip existing
+    {
+        write("tree_dst/child/b", "changed").await.unwrap();
+        let progress_calls = Rc::new(Cell::new(0));
+        let progress_calls_clone = progress_calls.clone();
+        CopyDirOptions::new()
+            .skip_existing(true)
+            .progress(move |_| progress_calls_clone.set(progress_calls_clone.get() + 1))
+            .copy("tree_src", "tree_dst")
+            .await
+            .unwrap();
+        assert_eq!(2, progress_calls.get());
+        assert_eq!("changed", read_to_string("tree_dst/child/b").await.unwrap());
+    }
+    // overwrite
+    {
+        CopyDirOptions::new()
+            .overwrite(true)
+            .copy("tree_src", "tree_dst")
+            .await
+            .unwrap();
+        assert_eq!("file b", read_to_string("tree_dst/child/b").await.unwrap());
+    }
+    // remove contents
+    {
+        remove_dir_contents("tree_dst").await.unwrap();
+        let entries = read_dir("tree_dst").await.unwrap().count().await;
+        assert_eq!(0, entries);
+    }
+}
@@ -0,0 +1,43 @@
+// This only runs in the browser
+#![cfg(target_arch = "wasm32")]
+
+use std::{path::PathBuf, time::Duration};
+
+use futures_lite::StreamExt;
+use wasm_bindgen_test::*;
+
+use wasm_bindgen_test::wasm_bindgen_test_configure;
+
+wasm_bindgen_test_configure!(run_in_browser);
+wasm_bindgen_test_configure!(run_in_dedicated_worker);
+
+use web_fs::*;
+
+#[wasm_bindgen_test]
+async fn polling_watch() {
+    console_error_panic_hook::set_once();
+    create_dir_all("watched").await.unwrap();
+    let mut watcher = watch_polling("watched", true, Duration::from_millis(50))
+        .await
+        .unwrap();
+    assert!(!watcher.is_native());
+
+    write("watched/file", "content").await.unwrap();
+    let event = watcher.next().await.unwrap().unwrap();
+    assert_eq!(EventKind::Create(CreateKind::File), event.kind);
+    assert_eq!(vec![PathBuf::from("watched/file")], event.paths);
+
+    remove_file("watched/file").await.unwrap();
+    let event = watcher.next().await.unwrap().unwrap();
+    assert_eq!(EventKind::Remove(RemoveKind::File), event.kind);
+}
+
+#[wasm_bindgen_test]
+async fn any_watch() {
+    console_error_panic_hook::set_once();
+    create_dir_all("watched_any").await.unwrap();
+    let mut watcher = watch("watched_any", false).await.unwrap();
+    create_dir("watched_any/child").await.unwrap();
+    let event = watcher.next().await.unwrap().unwrap();
+    assert!(event.kind.is_create());
+}
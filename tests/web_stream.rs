@@ -0,0 +1,85 @@
+// This only runs in the browser
+#![cfg(target_arch = "wasm32")]
+
+use futures_lite::{AsyncReadExt, AsyncWriteExt};
+use wasm_bindgen_futures::JsFuture;
+use wasm_bindgen_test::*;
+use web_sys::Response;
+
+use wasm_bindgen_test::wasm_bindgen_test_configure;
+
+wasm_bindgen_test_configure!(run_in_browser);
+wasm_bindgen_test_configure!(run_in_dedicated_worker);
+
+use web_fs::*;
+
+fn big_text() -> String {
+    (0..20_000).map(|i| format!("line {i}\n")).collect()
+}
+
+#[wasm_bindgen_test]
+async fn file_to_js_streams() {
+    console_error_panic_hook::set_once();
+    create_dir_all("web_stream_out").await.unwrap();
+    let text = big_text();
+    write("web_stream_out/file", &text).await.unwrap();
+
+    let stream = File::open("web_stream_out/file")
+        .await
+        .unwrap()
+        .into_readable_stream();
+    let response = Response::new_with_opt_readable_stream(Some(&stream)).unwrap();
+    let read = JsFuture::from(response.text().unwrap()).await.unwrap();
+    assert_eq!(text, read.as_string().unwrap());
+
+    let writable = File::create("web_stream_out/copy")
+        .await
+        .unwrap()
+        .into_writable_stream();
+    let body = Response::new_with_opt_str(Some(&text))
+        .unwrap()
+        .body()
+        .unwrap();
+    JsFuture::from(body.pipe_to(&writable)).await.unwrap();
+    assert_eq!(text, read_to_string("web_stream_out/copy").await.unwrap());
+
+    remove_dir_all("web_stream_out").await.unwrap();
+}
+
+#[wasm_bindgen_test]
+async fn js_streams_to_rust() {
+    console_error_panic_hook::set_once();
+    create_dir_all("web_stream_in").await.unwrap();
+    let text = big_text();
+
+    let body = Response::new_with_opt_str(Some(&text))
+        .unwrap()
+        .body()
+        .unwrap();
+    let size = write_stream("web_stream_in/file", &body).await.unwrap();
+    assert_eq!(text.len() as u64, size);
+    assert_eq!(text, read_to_string("web_stream_in/file").await.unwrap());
+
+    let body = Response::new_with_opt_str(Some(&text))
+        .unwrap()
+        .body()
+        .unwrap();
+    let mut read = String::new();
+    StreamReader::new(&body)
+        .unwrap()
+        .read_to_string(&mut read)
+        .await
+        .unwrap();
+    assert_eq!(text, read);
+
+    let writable = File::create("web_stream_in/written")
+        .await
+        .unwrap()
+        .into_writable_stream();
+    let mut writer = StreamWriter::new(&writable).unwrap();
+    writer.write_all(text.as_bytes()).await.unwrap();
+    writer.close().await.unwrap();
+    assert_eq!(text, read_to_string("web_stream_in/written").await.unwrap());
+
+    remove_dir_all("web_stream_in").await.unwrap();
+}